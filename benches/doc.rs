@@ -0,0 +1,94 @@
+//! Compares reading a few values out of big documents with [`JsonDoc`]
+//! against parsing all of them into a [`Value`] with `from_str`.
+//!
+//! Run with `cargo bench --bench doc`. The fixtures are generated
+//! (the same way every time) instead of being checked in.
+
+use std::{
+    fmt::Write,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use json_proc::{from_str, JsonDoc};
+
+/// An array of log events, where the last one's fields are wanted.
+fn events(count: usize) -> String {
+    let mut json = String::from("[");
+    for i in 0..count {
+        let _ = write!(
+            json,
+            r#"{{"id":{i},"type":"{}","user":{{"name":"user {}","tags":["a","b\n{i}"]}},"score":{}.25,"text":"{}"}},"#,
+            ["click", "view", "buy"][i % 3],
+            i % 97,
+            i % 1000,
+            "lorem ipsum dolor sit amet ".repeat(4),
+        );
+    }
+    json.pop();
+    json.push(']');
+    json
+}
+
+/// One wide object, where a few keys near the end are wanted.
+fn wide(count: usize) -> String {
+    let values = (0..10).map(|i| format!("{i}.5")).collect::<Vec<_>>().join(",");
+    let mut json = String::from("{");
+    for i in 0..count {
+        let _ = write!(json, r#""key{i}":{{"values":[{values}],"flag":true,"name":"n{i}"}},"#);
+    }
+    json.push_str(r#""route":{"to":"x","hops":[1,2,3]}}"#);
+    json
+}
+
+/// The median time of running `f` a few times.
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    let mut times = (0..11)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .collect::<Vec<_>>();
+    times.sort();
+    times[times.len() / 2]
+}
+
+fn report(name: &str, size: usize, duration: Duration) {
+    let throughput = size as f64 / duration.as_secs_f64() / (1024.0 * 1024.0);
+    println!("  {name:<24} {:>10.2?} {throughput:>9.1} MiB/s", duration);
+}
+
+fn main() {
+    let fixtures = [("events (50k)", events(50_000)), ("wide (100k keys)", wide(100_000))];
+    for (name, json) in &fixtures {
+        let size = json.len();
+        println!("{name}: {:.1} MiB", size as f64 / (1024.0 * 1024.0));
+
+        report("from_str", size, time(|| from_str(json).unwrap()));
+        report("JsonDoc::parse", size, time(|| JsonDoc::parse(json).unwrap()));
+        report(
+            "JsonDoc::parse + 3 gets",
+            size,
+            time(|| {
+                let doc = JsonDoc::parse(json).unwrap();
+                let root = doc.root();
+                match root.get(root.len().saturating_sub(1)) {
+                    // The last event.
+                    Some(last) if name.starts_with("events") => (
+                        last.get("id").and_then(|id| id.as_u64()),
+                        last.get("user").and_then(|user| user.get("name")?.as_str()).map(|name| name.len()),
+                        last.get("score").and_then(|score| score.as_f64()),
+                    ),
+                    _ => (
+                        doc.get("route").and_then(|route| route.get("hops")?.get(2)?.as_u64()),
+                        doc.get("key99999").and_then(|key| key.get("name")?.as_str()).map(|name| name.len()),
+                        doc.get("key5").and_then(|key| key.get("values")?.get(0)?.as_f64()),
+                    ),
+                }
+            }),
+        );
+        let doc = JsonDoc::parse(json).unwrap();
+        report("1 get (already indexed)", size, time(|| doc.get("route").map(|route| route.len())));
+    }
+}
@@ -0,0 +1,240 @@
+//! Parsing for the `#[json(...)]` helper attributes of `#[derive(ToJson)]`
+//! and `#[derive(FromJson)]`, which share them so that what one writes
+//! the other can read.
+
+use proc_macro2::Span;
+use syn::{
+    ext::IdentExt, meta::ParseNestedMeta, punctuated::Punctuated, spanned::Spanned, Attribute, Error as SynError,
+    Field, GenericArgument, Ident, LitStr, Member, Path, PathArguments, Result as SynResult, Token, Type, Variant,
+    WherePredicate,
+};
+
+/// Attributes put on the struct or enum being derived.
+#[derive(Default)]
+pub struct ContainerAttrs {
+    /// `#[json(transparent)]`
+    pub transparent: Option<Span>,
+    /// `#[json(repr)]`
+    pub repr: Option<Span>,
+    /// `#[json(bound = "...")]`
+    pub bound: Option<Vec<WherePredicate>>,
+    /// `#[json(crate = "...")]`
+    pub krate: Option<Path>,
+    /// `#[json(tag = "...")]`
+    pub tag: Option<LitStr>,
+    /// `#[json(content = "...")]`, which needs a `tag` too.
+    pub content: Option<LitStr>,
+    /// `#[json(deny_unknown_fields)]`
+    pub deny_unknown_fields: bool,
+    /// `#[json(typed)]`, for `json!(as Type { ... })`.
+    pub typed: bool,
+}
+
+/// Attributes put on a single field of a struct or enum variant.
+#[derive(Default)]
+pub struct FieldAttrs {
+    /// `#[json(as_string)]` or `#[json(display)]`
+    pub as_string: bool,
+    /// `#[json(bound = "...")]`
+    pub bound: Option<Vec<WherePredicate>>,
+    /// `#[json(rename = "...")]`
+    pub rename: Option<LitStr>,
+    /// `#[json(skip)]`
+    pub skip: bool,
+    /// `#[json(default)]` (`Some(None)`) or `#[json(default = "path")]`.
+    pub default: Option<Option<Path>>,
+}
+
+/// Attributes put on an enum variant.
+#[derive(Default)]
+pub struct VariantAttrs {
+    /// `#[json(rename = "...")]`
+    pub rename: Option<LitStr>,
+}
+
+/// Calls `f` on every item inside every `#[json(...)]` attribute.
+fn for_each_meta(
+    attrs: &[Attribute],
+    mut f: impl FnMut(ParseNestedMeta) -> SynResult<()>,
+) -> SynResult<()> {
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("json")) {
+        attr.parse_nested_meta(&mut f)?;
+    }
+    Ok(())
+}
+
+/// Parses the `"T: Trait, U: Other"` in `bound = "..."`.
+fn parse_bound(meta: &ParseNestedMeta) -> SynResult<Vec<WherePredicate>> {
+    let lit = meta.value()?.parse::<LitStr>()?;
+    let predicates = lit.parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+    Ok(predicates.into_iter().collect())
+}
+
+impl ContainerAttrs {
+    pub fn from_attrs(attrs: &[Attribute]) -> SynResult<Self> {
+        let mut this = Self::default();
+        for_each_meta(attrs, |meta| {
+            if meta.path.is_ident("transparent") {
+                this.transparent = Some(meta.path.span());
+                Ok(())
+            } else if meta.path.is_ident("repr") {
+                this.repr = Some(meta.path.span());
+                Ok(())
+            } else if meta.path.is_ident("bound") {
+                this.bound = Some(parse_bound(&meta)?);
+                Ok(())
+            } else if meta.path.is_ident("crate") {
+                this.krate = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("tag") {
+                this.tag = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("content") {
+                this.content = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("deny_unknown_fields") {
+                this.deny_unknown_fields = true;
+                Ok(())
+            } else if meta.path.is_ident("typed") {
+                this.typed = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown container attribute"))
+            }
+        })?;
+        if let (Some(content), None) = (&this.content, &this.tag) {
+            return Err(SynError::new(content.span(), "#[json(content)] requires #[json(tag)] too"));
+        }
+        if let (Some(tag), Some(_)) = (&this.tag, this.repr) {
+            return Err(SynError::new(tag.span(), "#[json(tag)] can't be used with #[json(repr)]"));
+        }
+        Ok(this)
+    }
+
+    /// Checks that there are no enum-only attributes on a struct.
+    pub fn check_struct(&self) -> SynResult<()> {
+        if let Some(tag) = &self.tag {
+            return Err(SynError::new(tag.span(), "#[json(tag)] can only be used on enums"));
+        }
+        match self.repr {
+            Some(span) => Err(SynError::new(span, "#[json(repr)] can only be used on enums")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FieldAttrs {
+    pub fn from_attrs(attrs: &[Attribute]) -> SynResult<Self> {
+        let mut this = Self::default();
+        for_each_meta(attrs, |meta| {
+            if meta.path.is_ident("as_string") || meta.path.is_ident("display") {
+                this.as_string = true;
+                Ok(())
+            } else if meta.path.is_ident("bound") {
+                this.bound = Some(parse_bound(&meta)?);
+                Ok(())
+            } else if meta.path.is_ident("rename") {
+                this.rename = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("skip") {
+                this.skip = true;
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                this.default = Some(match meta.input.peek(Token![=]) {
+                    true => Some(meta.value()?.parse::<LitStr>()?.parse()?),
+                    false => None,
+                });
+                Ok(())
+            } else {
+                Err(meta.error("unknown field attribute"))
+            }
+        })?;
+        Ok(this)
+    }
+
+    /// Parses the attributes of every field, checking that the ones
+    /// that only make sense on named fields aren't on tuple fields.
+    pub fn from_fields<'a>(fields: impl IntoIterator<Item = &'a Field>) -> SynResult<Vec<Self>> {
+        fields
+            .into_iter()
+            .map(|field| {
+                let this = Self::from_attrs(&field.attrs)?;
+                if field.ident.is_none() && (this.rename.is_some() || this.default.is_some()) {
+                    return Err(SynError::new(
+                        field.span(),
+                        "#[json(rename)] and #[json(default)] can only be used on named fields",
+                    ));
+                }
+                Ok(this)
+            })
+            .collect()
+    }
+
+    /// The JSON key of the field, which is its name (without `r#`)
+    /// unless it's renamed. This isn't escaped.
+    pub fn key(&self, member: &Member) -> String {
+        match (&self.rename, member) {
+            (Some(rename), _) => rename.value(),
+            (None, Member::Named(ident)) => ident.unraw().to_string(),
+            (None, Member::Unnamed(index)) => index.index.to_string(),
+        }
+    }
+}
+
+impl VariantAttrs {
+    pub fn from_attrs(attrs: &[Attribute]) -> SynResult<Self> {
+        let mut this = Self::default();
+        for_each_meta(attrs, |meta| {
+            if meta.path.is_ident("rename") {
+                this.rename = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown variant attribute"))
+            }
+        })?;
+        Ok(this)
+    }
+
+    /// The name the variant is written as, which is its name (without
+    /// `r#`) unless it's renamed. This isn't escaped.
+    pub fn name(&self, variant: &Variant) -> String {
+        match &self.rename {
+            Some(rename) => rename.value(),
+            None => variant.ident.unraw().to_string(),
+        }
+    }
+}
+
+/// Finds the integer type in a `#[repr(..)]` attribute, if any.
+pub fn repr_type(attrs: &[Attribute]) -> SynResult<Option<Ident>> {
+    const INTS: [&str; 12] = [
+        "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+    ];
+    let mut ty = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("repr")) {
+        attr.parse_nested_meta(|meta| {
+            if let Some(ident) = meta.path.get_ident().filter(|ident| INTS.iter().any(|int| *ident == int)) {
+                ty = Some(ident.clone());
+            }
+            // Skip over things like `align(8)` that aren't integer types.
+            if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                let _ = meta.input.parse::<proc_macro2::Group>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(ty)
+}
+
+/// Whether `field` is a `PhantomData<..>` marker, which
+/// `#[json(transparent)]` looks past.
+pub fn is_phantom_data(field: &Field) -> bool {
+    match &field.ty {
+        Type::Path(path) => path.path.segments.last().is_some_and(|seg| {
+            seg.ident == "PhantomData"
+                && matches!(&seg.arguments, PathArguments::AngleBracketed(args)
+                    if args.args.iter().all(|arg| matches!(arg, GenericArgument::Type(_))))
+        }),
+        _ => false,
+    }
+}
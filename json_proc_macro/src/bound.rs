@@ -0,0 +1,157 @@
+//! Builds the `where` clause of derived `ToJson` and `FromJson` impls.
+//!
+//! Like serde, instead of requiring `T: ToJson` for every type parameter
+//! `T`, only the parameters that the serialized fields mention are
+//! bounded (and not ones only in a `PhantomData`), so parameters that
+//! are never serialized work. The bounds are on the parameters and not
+//! the field types, since a bound like `Vec<Tree<T>>: ToJson` on a
+//! recursive type would need the impl it's part of.
+//!
+//! Skipped fields need nothing to be written and `Default` to be read,
+//! and `as_string` fields need `Display` and `FromStr`, which are put on
+//! their types. `#[json(bound = "...")]` on the container or a field
+//! replaces what would be inferred.
+
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{quote, ToTokens};
+use syn::{Field, GenericArgument, Generics, Ident, PathArguments, Type, WhereClause, WherePredicate};
+
+use crate::{
+    attr::{ContainerAttrs, FieldAttrs},
+    util,
+};
+
+/// Whether any identifier in `tokens` is one of `params`.
+fn mentions_any(tokens: TokenStream2, params: &[&Ident]) -> bool {
+    tokens.into_iter().any(|tree| match tree {
+        TokenTree::Ident(ident) => params.contains(&&ident),
+        TokenTree::Group(group) => mentions_any(group.stream(), params),
+        _ => false,
+    })
+}
+
+/// Adds the type parameters that `ty` mentions to `found`, leaving out
+/// ones that are only in a `PhantomData` (which is `null` whatever it holds).
+fn collect_params<'p>(ty: &Type, params: &[&'p Ident], found: &mut Vec<&'p Ident>) {
+    let collect_tokens = |tokens: TokenStream2, found: &mut Vec<&'p Ident>| {
+        for &param in params {
+            if !found.contains(&param) && mentions_any(tokens.clone(), &[param]) {
+                found.push(param);
+            }
+        }
+    };
+    match ty {
+        Type::Path(path) => {
+            if let Some(qself) = &path.qself {
+                collect_params(&qself.ty, params, found);
+            }
+            for (i, segment) in path.path.segments.iter().enumerate() {
+                if i == 0 && path.qself.is_none() {
+                    if let Some(&param) = params.iter().find(|&&param| *param == segment.ident) {
+                        if !found.contains(&param) {
+                            found.push(param);
+                        }
+                    }
+                }
+                if segment.ident == "PhantomData" {
+                    continue;
+                }
+                match &segment.arguments {
+                    PathArguments::AngleBracketed(args) => {
+                        for arg in &args.args {
+                            match arg {
+                                GenericArgument::Type(ty) => collect_params(ty, params, found),
+                                arg => collect_tokens(arg.to_token_stream(), found),
+                            }
+                        }
+                    }
+                    arguments => collect_tokens(arguments.to_token_stream(), found),
+                }
+            }
+        }
+        Type::Reference(reference) => collect_params(&reference.elem, params, found),
+        Type::Array(array) => collect_params(&array.elem, params, found),
+        Type::Slice(slice) => collect_params(&slice.elem, params, found),
+        Type::Paren(paren) => collect_params(&paren.elem, params, found),
+        Type::Group(group) => collect_params(&group.elem, params, found),
+        Type::Tuple(tuple) => {
+            for elem in &tuple.elems {
+                collect_params(elem, params, found);
+            }
+        }
+        ty => collect_tokens(ty.to_token_stream(), found),
+    }
+}
+
+/// The trait being derived.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Derive {
+    ToJson,
+    /// `FromJson<'de>`.
+    FromJson,
+}
+
+/// Builds the `where` clause for an impl of `derive` over `generics`
+/// that serializes `fields`.
+pub fn where_clause<'a>(
+    generics: &Generics,
+    container: &ContainerAttrs,
+    fields: impl IntoIterator<Item = (&'a Field, &'a FieldAttrs)>,
+    derive: Derive,
+) -> WhereClause {
+    let mut where_clause = generics
+        .where_clause
+        .clone()
+        .unwrap_or_else(|| syn::parse_quote!(where));
+    let mut predicates: Vec<WherePredicate> = Vec::new();
+    let krate = util::krate();
+
+    if let Some(bound) = &container.bound {
+        predicates.extend(bound.iter().cloned());
+    } else {
+        let params = generics.type_params().map(|param| &param.ident).collect::<Vec<_>>();
+        for (field, attrs) in fields {
+            if let Some(bound) = &attrs.bound {
+                predicates.extend(bound.iter().cloned());
+                continue;
+            }
+            let ty = &field.ty;
+            if !mentions_any(ty.to_token_stream(), &params) {
+                // Concrete types don't need bounds; if they don't implement
+                // the trait, the generated impl will say so anyway.
+                continue;
+            }
+            match derive {
+                Derive::ToJson if attrs.skip => {}
+                Derive::ToJson if attrs.as_string => predicates.push(syn::parse_quote!(#ty: ::std::fmt::Display)),
+                Derive::FromJson if attrs.skip => predicates.push(syn::parse_quote!(#ty: ::std::default::Default)),
+                Derive::FromJson if attrs.as_string => predicates.push(syn::parse_quote!(#ty: ::std::str::FromStr)),
+                _ => {
+                    if derive == Derive::FromJson && matches!(attrs.default, Some(None)) {
+                        predicates.push(syn::parse_quote!(#ty: ::std::default::Default));
+                    }
+                    let mut found = Vec::new();
+                    collect_params(ty, &params, &mut found);
+                    predicates.extend(found.into_iter().map(|param| -> WherePredicate {
+                        match derive {
+                            Derive::ToJson => syn::parse_quote!(#param: #krate::ToJson),
+                            Derive::FromJson => syn::parse_quote!(#param: #krate::FromJson<'de>),
+                        }
+                    }));
+                }
+            }
+        }
+    }
+
+    for predicate in predicates {
+        let string = quote!(#predicate).to_string();
+        if !where_clause
+            .predicates
+            .iter()
+            .any(|existing| quote!(#existing).to_string() == string)
+        {
+            where_clause.predicates.push(predicate);
+        }
+    }
+    where_clause
+}
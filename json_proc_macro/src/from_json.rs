@@ -0,0 +1,505 @@
+//! `#[derive(FromJson)]`, which reads what `#[derive(ToJson)]` writes,
+//! and the impls of `FromJson` for tuples.
+//!
+//! The generated code calls the methods of `Deserializer` directly, so
+//! errors point at the value that was wrong, and unknown keys are skipped
+//! without being parsed into anything.
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    ext::IdentExt, spanned::Spanned, Error as SynError, Fields, Generics, Ident, Index, ItemEnum, ItemStruct,
+    Result as SynResult, WhereClause,
+};
+
+use crate::{
+    attr::{self, ContainerAttrs, FieldAttrs, VariantAttrs},
+    bound::{self, Derive},
+    util,
+};
+
+/// The `&mut Deserializer` the generated code reads from.
+fn de() -> Ident {
+    Ident::new("de", Span::mixed_site())
+}
+
+/// The offset of the value being read, for errors about all of it.
+fn start() -> Ident {
+    Ident::new("start", Span::mixed_site())
+}
+
+/// Wraps `body` in the impl of `FromJson<'de>` for `ident`, where `'de`
+/// outlives every lifetime of `generics` so fields can borrow from it.
+fn impl_from_json(
+    ident: &Ident,
+    generics: &Generics,
+    where_clause: WhereClause,
+    body: TokenStream2,
+) -> SynResult<TokenStream2> {
+    let krate = util::krate();
+    let de = de();
+    let lifetimes = generics.lifetimes().map(|param| &param.lifetime).collect::<Vec<_>>();
+    if let Some(lifetime) = lifetimes.iter().find(|lifetime| lifetime.ident == "de") {
+        return Err(SynError::new(
+            lifetime.span(),
+            "the lifetime `'de` is used by the generated impl, so it needs another name",
+        ));
+    }
+    let mut impl_generics = generics.clone();
+    impl_generics.params.insert(0, match lifetimes.is_empty() {
+        true => syn::parse_quote!('de),
+        false => syn::parse_quote!('de: #(#lifetimes)+*),
+    });
+    let (impl_generics, _, _) = impl_generics.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::FromJson<'de> for #ident #ty_generics #where_clause {
+            fn from_json(#de: &mut #krate::Deserializer<'de>) -> ::std::result::Result<Self, #krate::ParseError> {
+                #body
+            }
+        }
+    })
+}
+
+/// Generates the expression that reads one field.
+fn read_field(attrs: &FieldAttrs) -> TokenStream2 {
+    let krate = util::krate();
+    let de = de();
+    if attrs.as_string {
+        quote!(#krate::__private::from_str_field(#de)?)
+    } else {
+        quote!(#krate::FromJson::from_json(#de)?)
+    }
+}
+
+/// Generates the statements that read the `fields` of a struct or
+/// variant, and the expression that builds it from them afterwards.
+/// `path` is `Self` or `Self::Variant`, and `ignore` is keys that are
+/// skipped in objects (which the tags of an enum are).
+///
+/// Fieldless structs and variants are read as empty objects.
+fn read_fields(
+    path: TokenStream2,
+    fields: &Fields,
+    attrs: &[FieldAttrs],
+    container: &ContainerAttrs,
+    ignore: &[String],
+) -> SynResult<(TokenStream2, TokenStream2)> {
+    let krate = util::krate();
+    let de = de();
+    let start = start();
+    let mut statements = Vec::new();
+    let mut inits = Vec::new();
+
+    if let (Fields::Unnamed(_), 1) = (fields, fields.len()) {
+        if attrs[0].skip {
+            return Err(SynError::new(fields.span(), "#[json(skip)] can't be used on the only field"));
+        }
+        let read = read_field(&attrs[0]);
+        return Ok((quote!(), quote!(#path(#read))));
+    }
+
+    if let Fields::Unnamed(_) = fields {
+        // Read an array, with an element for every field that isn't skipped.
+        let len = attrs.iter().filter(|attrs| !attrs.skip).count();
+        statements.push(quote! {
+            let #start = #de.offset();
+            #de.begin_array()?;
+        });
+        let mut index = 0usize;
+        for ((i, member), attrs) in fields.members().enumerate().zip(attrs) {
+            if attrs.skip {
+                inits.push(quote!(#member: ::std::default::Default::default()));
+                continue;
+            }
+            let var = Ident::new(&format!("field{i}"), Span::mixed_site());
+            let read = read_field(attrs);
+            statements.push(quote! {
+                #krate::__private::next_element(#de, #start, #len, #index)?;
+                let #var = #read;
+            });
+            inits.push(quote!(#member: #var));
+            index += 1;
+        }
+        statements.push(quote!(#krate::__private::end_array(#de, #len)?;));
+        return Ok((quote!(#(#statements)*), quote!(#path { #(#inits),* })));
+    }
+
+    // Read an object, keeping each field in an `Option` until the end.
+    let key = Ident::new("key", Span::mixed_site());
+    let mut arms = Vec::new();
+    let mut keys: Vec<String> = Vec::new();
+    let mut uses_start = false;
+    for (((i, field), member), attrs) in fields.iter().enumerate().zip(fields.members()).zip(attrs) {
+        if attrs.skip {
+            inits.push(quote!(#member: ::std::default::Default::default()));
+            continue;
+        }
+        let name = attrs.key(&member);
+        if keys.contains(&name) || ignore.contains(&name) {
+            return Err(SynError::new(field.span(), format!("the key `{name}` is used more than once")));
+        }
+        let var = Ident::new(&format!("field{i}"), Span::mixed_site());
+        let ty = &field.ty;
+        let read = read_field(attrs);
+        let duplicate = format!("duplicate field `{name}`");
+        statements.push(quote!(let mut #var: ::std::option::Option<#ty> = ::std::option::Option::None;));
+        arms.push(quote! {
+            #name => {
+                if #var.is_some() {
+                    return ::std::result::Result::Err(#de.key_error(#duplicate));
+                }
+                #var = ::std::option::Option::Some(#read);
+            }
+        });
+        let missing = format!("missing field `{name}`");
+        let missing = match &attrs.default {
+            Some(Some(path)) => quote!(#path()),
+            Some(None) => quote!(::std::default::Default::default()),
+            None if attrs.as_string => {
+                uses_start = true;
+                quote!(return ::std::result::Result::Err(#de.error_at(#start, #missing)))
+            }
+            None => {
+                uses_start = true;
+                quote! {
+                    match <#ty as #krate::FromJson<'de>>::missing() {
+                        ::std::option::Option::Some(value) => value,
+                        ::std::option::Option::None => {
+                            return ::std::result::Result::Err(#de.error_at(#start, #missing))
+                        }
+                    }
+                }
+            }
+        };
+        inits.push(quote! {
+            #member: match #var {
+                ::std::option::Option::Some(value) => value,
+                ::std::option::Option::None => #missing,
+            }
+        });
+        keys.push(name);
+    }
+    let unknown = match container.deny_unknown_fields {
+        true => quote! {
+            return ::std::result::Result::Err(#de.key_error(::std::format!("unknown field `{}`", #key)))
+        },
+        false => quote!(#de.skip_value()?),
+    };
+    let start_statement = uses_start.then(|| quote!(let #start = #de.offset();));
+    let statements = quote! {
+        #start_statement
+        #de.begin_object()?;
+        #(#statements)*
+        while let ::std::option::Option::Some(#key) = #de.next_key()? {
+            match &*#key {
+                #(#arms)*
+                #(#ignore => #de.skip_value()?,)*
+                _ => {
+                    #unknown;
+                }
+            }
+        }
+    };
+    Ok((statements, quote!(#path { #(#inits),* })))
+}
+
+pub fn derive_struct(input: ItemStruct, container: ContainerAttrs) -> SynResult<TokenStream2> {
+    container.check_struct()?;
+    let de = de();
+    let attrs = FieldAttrs::from_fields(&input.fields)?;
+    let ident = &input.ident;
+    let mut read = input.fields.iter().zip(&attrs).collect::<Vec<_>>();
+
+    let body = if let Some(span) = container.transparent {
+        let mut fields = input
+            .fields
+            .iter()
+            .zip(input.fields.members())
+            .zip(&attrs)
+            .filter(|((field, _), _)| !attr::is_phantom_data(field));
+        match (fields.next(), fields.next()) {
+            (Some(((field, member), attrs)), None) => {
+                read = vec![(field, attrs)];
+                let value = read_field(attrs);
+                let others = input.fields.members().filter(|other| *other != member);
+                quote! {
+                    ::std::result::Result::Ok(Self {
+                        #member: #value,
+                        #(#others: ::std::default::Default::default()),*
+                    })
+                }
+            }
+            _ => {
+                return Err(SynError::new(
+                    span,
+                    "#[json(transparent)] requires exactly one field that is not `PhantomData`",
+                ))
+            }
+        }
+    } else if input.fields.is_empty() {
+        // `ToJson` writes these as their name.
+        let start = start();
+        let name = ident.unraw().to_string();
+        let expected = format!("expected `\"{name}\"`");
+        quote! {
+            let #start = #de.offset();
+            match &*#de.parse_str()? {
+                #name => ::std::result::Result::Ok(Self {}),
+                _ => ::std::result::Result::Err(#de.error_at(#start, #expected)),
+            }
+        }
+    } else {
+        let (statements, value) = read_fields(quote!(Self), &input.fields, &attrs, &container, &[])?;
+        quote! {
+            #statements
+            ::std::result::Result::Ok(#value)
+        }
+    };
+    let where_clause = bound::where_clause(&input.generics, &container, read, Derive::FromJson);
+    impl_from_json(ident, &input.generics, where_clause, body)
+}
+
+pub fn derive_enum(input: ItemEnum, container: ContainerAttrs) -> SynResult<TokenStream2> {
+    let krate = util::krate();
+    let de = de();
+    let start = start();
+    if let Some(span) = container.transparent {
+        return Err(SynError::new(span, "#[json(transparent)] is not supported on enums"));
+    }
+    let ident = &input.ident;
+    let attrs = input
+        .variants
+        .iter()
+        .map(|var| FieldAttrs::from_fields(&var.fields))
+        .collect::<SynResult<Vec<_>>>()?;
+    let names = input
+        .variants
+        .iter()
+        .map(|var| VariantAttrs::from_attrs(&var.attrs).map(|attrs| attrs.name(var)))
+        .collect::<SynResult<Vec<_>>>()?;
+    let fields = input.variants.iter().flat_map(|var| &var.fields);
+    let where_clause = bound::where_clause(
+        &input.generics,
+        &container,
+        fields.zip(attrs.iter().flatten()),
+        Derive::FromJson,
+    );
+
+    if container.repr.is_some() {
+        if let Some(var) = input.variants.iter().find(|var| !var.fields.is_empty()) {
+            return Err(SynError::new(
+                var.fields.span(),
+                "#[json(repr)] can only be used on enums without data-carrying variants",
+            ));
+        }
+        let repr = attr::repr_type(&input.attrs)?.unwrap_or_else(|| Ident::new("isize", Span::call_site()));
+        let variants = input.variants.iter().map(|var| &var.ident);
+        let unknown = format!("no variant of `{ident}` has the discriminant {{}}");
+        let body = quote! {
+            let #start = #de.offset();
+            let discriminant: #repr = #krate::FromJson::from_json(#de)?;
+            #(
+                if discriminant == Self::#variants as #repr {
+                    return ::std::result::Result::Ok(Self::#variants);
+                }
+            )*
+            ::std::result::Result::Err(#de.error_at(#start, ::std::format!(#unknown, discriminant)))
+        };
+        return impl_from_json(ident, &input.generics, where_clause, body);
+    }
+
+    let unknown = format!("unknown variant `{{}}` of `{ident}`");
+    let body = match (&container.tag, &container.content) {
+        (None, _) => {
+            // Untagged: fieldless variants are strings, and the others are
+            // tried in order until one of them reads without an error
+            // (without positioning the errors of the ones that don't).
+            let checkpoint = Ident::new("checkpoint", Span::mixed_site());
+            let name = Ident::new("name", Span::mixed_site());
+            let mut units = Vec::new();
+            let mut tries = Vec::new();
+            for ((var, attrs), name) in input.variants.iter().zip(&attrs).zip(&names) {
+                let varident = &var.ident;
+                if var.fields.is_empty() {
+                    units.push(quote!(#name => return ::std::result::Result::Ok(Self::#varident {}),));
+                    continue;
+                }
+                let (statements, value) = read_fields(quote!(Self::#varident), &var.fields, attrs, &container, &[])?;
+                tries.push(quote! {
+                    let read = |#de: &mut #krate::Deserializer<'de>| -> ::std::result::Result<Self, #krate::ParseError> {
+                        #statements
+                        ::std::result::Result::Ok(#value)
+                    };
+                    if let ::std::option::Option::Some(value) = #de.attempt(read) {
+                        return ::std::result::Result::Ok(value);
+                    }
+                });
+            }
+            let mismatch = format!("data did not match any variant of `{ident}`");
+            match (units.is_empty(), tries.is_empty()) {
+                (_, true) => quote! {
+                    let #start = #de.offset();
+                    let #name = #de.parse_str()?;
+                    match &*#name {
+                        #(#units)*
+                        _ => ::std::result::Result::Err(#de.error_at(#start, ::std::format!(#unknown, #name))),
+                    }
+                },
+                (units_empty, false) => {
+                    let units = (!units_empty).then(|| {
+                        quote! {
+                            if #de.peek_kind()? == #krate::ValueKind::String {
+                                let #checkpoint = #de.checkpoint();
+                                let #name = #de.parse_str()?;
+                                match &*#name {
+                                    #(#units)*
+                                    _ => {}
+                                }
+                                #de.restore(#checkpoint);
+                            }
+                        }
+                    });
+                    quote! {
+                        let #start = #de.offset();
+                        #units
+                        #(#tries)*
+                        ::std::result::Result::Err(#de.error_at(#start, #mismatch))
+                    }
+                }
+            }
+        }
+        (Some(tag), content) => {
+            // Tagged: the tag is found first, and then the object is read
+            // again as the variant it names.
+            let tag = tag.value();
+            let content = content.as_ref().map(|content| content.value());
+            let tag_value = Ident::new("tag", Span::mixed_site());
+            let mut arms = Vec::new();
+            for ((var, attrs), name) in input.variants.iter().zip(&attrs).zip(&names) {
+                let varident = &var.ident;
+                let path = quote!(Self::#varident);
+                let read = match &content {
+                    None if matches!(var.fields, Fields::Unnamed(_)) && !var.fields.is_empty() => {
+                        return Err(SynError::new(
+                            var.fields.span(),
+                            "#[json(tag)] without #[json(content)] can only be used on enums without tuple variants",
+                        ));
+                    }
+                    None => read_fields(path, &var.fields, attrs, &container, std::slice::from_ref(&tag))?,
+                    Some(_) if var.fields.is_empty() => {
+                        read_fields(path, &var.fields, attrs, &container, std::slice::from_ref(&tag))?
+                    }
+                    Some(content) => {
+                        let (statements, value) = read_fields(path, &var.fields, attrs, &container, &[])?;
+                        let read = quote!({
+                            #statements
+                            #value
+                        });
+                        read_content(&tag, content, read, &container)
+                    }
+                };
+                let (statements, value) = read;
+                arms.push(quote! {
+                    #name => {
+                        #statements
+                        ::std::result::Result::Ok(#value)
+                    }
+                });
+            }
+            quote! {
+                let #tag_value = #krate::__private::find_tag(#de, #tag)?;
+                match &*#tag_value {
+                    #(#arms)*
+                    _ => ::std::result::Result::Err(#de.error(::std::format!(#unknown, #tag_value))),
+                }
+            }
+        }
+    };
+    impl_from_json(ident, &input.generics, where_clause, body)
+}
+
+/// Reads an adjacently tagged object, where `read` is an expression that
+/// reads the value of `content`.
+fn read_content(tag: &str, content: &str, read: TokenStream2, container: &ContainerAttrs) -> (TokenStream2, TokenStream2) {
+    let de = de();
+    let start = start();
+    let key = Ident::new("key", Span::mixed_site());
+    let value = Ident::new("value", Span::mixed_site());
+    let duplicate = format!("duplicate field `{content}`");
+    let missing = format!("missing field `{content}`");
+    let unknown = match container.deny_unknown_fields {
+        true => quote! {
+            return ::std::result::Result::Err(#de.key_error(::std::format!("unknown field `{}`", #key)))
+        },
+        false => quote!(#de.skip_value()?),
+    };
+    let statements = quote! {
+        let #start = #de.offset();
+        #de.begin_object()?;
+        let mut #value = ::std::option::Option::None;
+        while let ::std::option::Option::Some(#key) = #de.next_key()? {
+            match &*#key {
+                #tag => #de.skip_value()?,
+                #content => {
+                    if #value.is_some() {
+                        return ::std::result::Result::Err(#de.key_error(#duplicate));
+                    }
+                    #value = ::std::option::Option::Some(#read);
+                }
+                _ => {
+                    #unknown;
+                }
+            }
+        }
+    };
+    let value = quote! {
+        match #value {
+            ::std::option::Option::Some(value) => value,
+            ::std::option::Option::None => return ::std::result::Result::Err(#de.error_at(#start, #missing)),
+        }
+    };
+    (statements, value)
+}
+
+/// Generates the impls of `FromJson` for tuples of up to 12 elements,
+/// which are read from arrays of exactly that many elements.
+pub fn tuple_impls() -> TokenStream2 {
+    const LETTERS: [char; 12] = ['A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L'];
+    let mut streams = Vec::with_capacity(12);
+    for len in 1..=12usize {
+        let letters = &LETTERS[..len]
+            .iter()
+            .map(|ch| Ident::new(&ch.to_string(), Span::call_site()))
+            .collect::<Vec<Ident>>();
+        let indices = (0..len).map(Index::from);
+        let doc_attr = if len == 1 {
+            quote!(#[doc = "`FromJson` is implemented for tuples up to size 12."])
+        } else {
+            quote!(#[doc(hidden)])
+        };
+        streams.push(quote! {
+            #doc_attr
+            impl<'de, #(#letters),*> crate::FromJson<'de> for (#(#letters,)*)
+            where
+                #(
+                    #letters: crate::FromJson<'de>
+                ),*
+            {
+                fn from_json(de: &mut crate::Deserializer<'de>) -> Result<Self, crate::ParseError> {
+                    let start = de.offset();
+                    de.begin_array()?;
+                    let tuple = (#(
+                        {
+                            crate::__private::next_element(de, start, #len, #indices)?;
+                            #letters::from_json(de)?
+                        },
+                    )*);
+                    crate::__private::end_array(de, #len)?;
+                    Ok(tuple)
+                }
+            }
+        });
+    }
+    quote!(#(#streams)*)
+}
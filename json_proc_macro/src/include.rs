@@ -0,0 +1,443 @@
+//! Parsing and validation of the files loaded by `include_json!`.
+//!
+//! This is a strict JSON parser that keeps nothing but what's needed to
+//! write the file back out minified, or to turn it into a [`JsonValue`]
+//! when it's used as a template.
+
+use std::collections::HashMap;
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{Error as SynError, Ident, LitStr, Result as SynResult};
+
+use crate::{util, JsonArray, JsonElement, JsonKey, JsonKeyValue, JsonMember, JsonObject, JsonValue};
+
+/// A value parsed from a JSON file.
+pub enum Node {
+    Null,
+    Bool(bool),
+    /// The number as it's written in the file.
+    Number(String),
+    String(String),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+/// An error in a JSON file, at a byte offset into it.
+pub struct ParseError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    /// Formats the error as `path:line:column: message`.
+    pub fn display(&self, path: &str, src: &str) -> String {
+        let before = &src[..self.offset.min(src.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().map_or(0, |line| line.chars().count()) + 1;
+        format!("{path}:{line}:{column}: {}", self.message)
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+/// Parses `src`, which has to be exactly one JSON value.
+pub fn parse(src: &str) -> Result<Node, ParseError> {
+    let mut parser = Parser { src, pos: 0 };
+    let node = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < src.len() {
+        return Err(parser.error("trailing characters after the value"));
+    }
+    Ok(node)
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            offset: self.pos,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`", byte as char)))
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, node: Node) -> Result<Node, ParseError> {
+        if self.src[self.pos..].starts_with(keyword) {
+            self.pos += keyword.len();
+            Ok(node)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Node, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => Ok(Node::String(self.string()?)),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b't') => self.keyword("true", Node::Bool(true)),
+            Some(b'f') => self.keyword("false", Node::Bool(false)),
+            Some(b'n') => self.keyword("null", Node::Null),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Node, ParseError> {
+        self.pos += 1;
+        let mut members: Vec<(String, Node)> = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Node::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a string key"));
+            }
+            let start = self.pos;
+            let key = self.string()?;
+            if members.iter().any(|(existing, _)| *existing == key) {
+                return Err(ParseError {
+                    offset: start,
+                    message: format!("duplicate key `{key}`"),
+                });
+            }
+            self.expect(b':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Node::Object(members));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Node, ParseError> {
+        self.pos += 1;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Node::Array(elements));
+        }
+        loop {
+            elements.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Node::Array(elements));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.pos += 1;
+        let mut string = String::new();
+        loop {
+            let Some(ch) = self.src[self.pos..].chars().next() else {
+                return Err(self.error("unterminated string"));
+            };
+            match ch {
+                '"' => {
+                    self.pos += 1;
+                    return Ok(string);
+                }
+                '\\' => {
+                    self.pos += 1;
+                    string.push(self.escape()?);
+                }
+                ch if (ch as u32) < 0x20 => return Err(self.error("control character in string")),
+                ch => {
+                    self.pos += ch.len_utf8();
+                    string.push(ch);
+                }
+            }
+        }
+    }
+
+    /// Parses what's after the `\` of an escape.
+    fn escape(&mut self) -> Result<char, ParseError> {
+        let ch = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{08}',
+            Some(b'f') => '\u{0C}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+                let high = self.hex()?;
+                if !(0xD800..0xDC00).contains(&high) {
+                    return char::from_u32(high).ok_or_else(|| self.error("invalid unicode escape"));
+                }
+                // A surrogate pair, which has to be followed by the low half.
+                if !self.src[self.pos..].starts_with("\\u") {
+                    return Err(self.error("unpaired surrogate in unicode escape"));
+                }
+                self.pos += 2;
+                let low = self.hex()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Err(self.error("unpaired surrogate in unicode escape"));
+                }
+                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                return char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"));
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+        self.pos += 1;
+        Ok(ch)
+    }
+
+    fn hex(&mut self) -> Result<u32, ParseError> {
+        let digits = self
+            .src
+            .get(self.pos..self.pos + 4)
+            .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("expected 4 hex digits"))?;
+        self.pos += 4;
+        Ok(u32::from_str_radix(digits, 16).expect("checked above"))
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Result<Node, ParseError> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => {
+                self.digits();
+            }
+            _ => return Err(self.error("expected a digit")),
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if self.digits() == 0 {
+                return Err(self.error("expected a digit after `.`"));
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                return Err(self.error("expected a digit in the exponent"));
+            }
+        }
+        Ok(Node::Number(self.src[start..self.pos].to_owned()))
+    }
+}
+
+/// A piece of a string in a template, which is either text or `${name}`.
+enum Piece<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+/// Splits `string` into text and placeholders.
+fn pieces(string: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut rest = string;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        if name.is_empty() || !name.chars().all(|ch| ch == '_' || ch.is_alphanumeric()) {
+            pieces.push(Piece::Text(&rest[..start + 2]));
+            rest = &rest[start + 2..];
+            continue;
+        }
+        if start > 0 {
+            pieces.push(Piece::Text(&rest[..start]));
+        }
+        pieces.push(Piece::Placeholder(name));
+        rest = &rest[start + 3 + len..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest));
+    }
+    pieces
+}
+
+/// Finds the variable bound to the placeholder `name`.
+fn lookup(bindings: &HashMap<String, Ident>, used: &mut Vec<String>, name: &str) -> Result<Ident, String> {
+    match bindings.get(name) {
+        Some(ident) => {
+            used.push(name.to_owned());
+            Ok(ident.clone())
+        }
+        None => Err(format!("no value was given for `${{{name}}}`")),
+    }
+}
+
+impl Node {
+    /// Writes this as minified JSON.
+    pub fn minify(&self, json: &mut String) {
+        match self {
+            Node::Null => json.push_str("null"),
+            Node::Bool(bool) => json.push_str(if *bool { "true" } else { "false" }),
+            Node::Number(number) => json.push_str(number),
+            Node::String(string) => {
+                json.push('"');
+                json.push_str(&util::escape_str(string));
+                json.push('"');
+            }
+            Node::Array(elements) => {
+                json.push('[');
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        json.push(',');
+                    }
+                    element.minify(json);
+                }
+                json.push(']');
+            }
+            Node::Object(members) => {
+                json.push('{');
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i != 0 {
+                        json.push(',');
+                    }
+                    json.push('"');
+                    json.push_str(&util::escape_str(key));
+                    json.push_str("\":");
+                    value.minify(json);
+                }
+                json.push('}');
+            }
+        }
+    }
+
+    /// Whether there are any placeholders in this.
+    fn has_placeholders(&self) -> bool {
+        match self {
+            Node::String(string) => pieces(string).iter().any(|piece| matches!(piece, Piece::Placeholder(_))),
+            Node::Array(elements) => elements.iter().any(Node::has_placeholders),
+            Node::Object(members) => members.iter().any(|(_, value)| value.has_placeholders()),
+            _ => false,
+        }
+    }
+
+    /// Turns this into a [`JsonValue`], where a string that's just
+    /// `"${name}"` becomes the value bound to `name`, and any other
+    /// string with placeholders is formatted with their `Display` impls.
+    ///
+    /// Every name that's used is added to `used`.
+    pub fn into_value(self, bindings: &HashMap<String, Ident>, used: &mut Vec<String>) -> Result<JsonValue, String> {
+        if !self.has_placeholders() {
+            let mut json = String::new();
+            self.minify(&mut json);
+            return Ok(JsonValue::Raw(json));
+        }
+        Ok(match self {
+            Node::String(string) => match pieces(&string)[..] {
+                [Piece::Placeholder(name)] => {
+                    let ident = lookup(bindings, used, name)?;
+                    JsonValue::Expr(syn::parse_quote!(#ident))
+                }
+                ref pieces => {
+                    let mut format = String::new();
+                    let mut args = Vec::new();
+                    for piece in pieces {
+                        match piece {
+                            Piece::Text(text) => format.push_str(&text.replace('{', "{{").replace('}', "}}")),
+                            Piece::Placeholder(name) => {
+                                format.push_str("{}");
+                                args.push(lookup(bindings, used, name)?);
+                            }
+                        }
+                    }
+                    let format = LitStr::new(&format, Span::call_site());
+                    JsonValue::Format(quote!(#format #(, #args)*))
+                }
+            },
+            Node::Array(elements) => JsonValue::Array(JsonArray {
+                elements: elements
+                    .into_iter()
+                    .map(|element| Ok((Vec::new(), JsonElement::Value(element.into_value(bindings, used)?))))
+                    .collect::<Result<_, String>>()?,
+                span: Span::call_site(),
+            }),
+            Node::Object(members) => JsonValue::Object(JsonObject {
+                members: members
+                    .into_iter()
+                    .map(|(key, value)| {
+                        Ok(JsonMember::Pair(JsonKeyValue {
+                            key: JsonKey::Lit(key),
+                            value: value.into_value(bindings, used)?,
+                            key_span: Span::call_site(),
+                            optional: false,
+                            guard: None,
+                            path: None,
+                            nested: false,
+                            attrs: Vec::new(),
+                        }))
+                    })
+                    .collect::<Result<_, String>>()?,
+                span: Span::call_site(),
+            }),
+            _ => unreachable!("only strings, arrays and objects have placeholders"),
+        })
+    }
+}
+
+/// Reads `path` (relative to the crate being compiled) for `include_json!`,
+/// returning its full path and contents.
+pub fn read(path: &LitStr) -> SynResult<(String, String)> {
+    let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| String::from("."));
+    let full = std::path::Path::new(&dir).join(path.value());
+    let src = std::fs::read_to_string(&full)
+        .map_err(|error| SynError::new(path.span(), format!("couldn't read {}: {error}", full.display())))?;
+    Ok((full.to_string_lossy().into_owned(), src))
+}
+
+/// Generates an item that makes the crate rebuild when `path` changes.
+pub fn track(path: &str) -> TokenStream2 {
+    quote!(const _: &[u8] = ::std::include_bytes!(#path);)
+}
@@ -2,32 +2,110 @@
 
 use proc_macro::TokenStream;
 use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::{quote, ToTokens};
+use quote::{quote, quote_spanned, ToTokens};
 use syn::{
-    braced, bracketed, parse,
+    braced, bracketed,
+    ext::IdentExt,
+    parse,
     parse::{Parse, ParseStream},
-    parse_macro_input,
     spanned::Spanned,
-    token, Error as SynError, Expr, Ident, Index, ItemEnum, ItemStruct, LitBool, LitInt, LitStr,
-    Member, Result as SynResult, Token,
+    token, Attribute, Error as SynError, Expr, ExprLit, ExprUnary, Fields, Ident, Index, ItemEnum, ItemStruct,
+    Lit, LitBool, LitChar, LitInt, LitStr, Member, Pat, Path, PathArguments, Result as SynResult, Token, UnOp,
 };
 
+mod attr;
+mod bound;
+mod from_json;
+mod include;
+mod schema;
+mod typed;
+mod value;
+
+use attr::{ContainerAttrs, FieldAttrs, VariantAttrs};
+use bound::Derive;
+
 #[cfg(lints_enabled)]
 // These only work on nightly because they are unstable
 use proc_macro::{Diagnostic, Level};
 
 mod util {
-    use std::iter::Iterator;
+    use std::{cell::RefCell, iter::Iterator};
+
+    use proc_macro2::{Span, TokenStream as TokenStream2};
+    use quote::{quote, ToTokens};
+    use syn::{Attribute, Ident, Path};
+
+    thread_local! {
+        static CRATE: RefCell<Option<Path>> = const { RefCell::new(None) };
+    }
+
+    /// The path to the `json_proc` crate that generated code should use.
+    ///
+    /// This is `::json_proc` unless it was overridden with
+    /// `crate = ...` for the current expansion (see [`with_crate`]).
+    pub fn krate() -> TokenStream2 {
+        CRATE.with_borrow(|path| match path {
+            Some(path) => quote!(#path),
+            None => quote!(::json_proc),
+        })
+    }
 
-    #[must_use]
-    #[inline(always)]
-    // This exists the way it does for two reasons:
-    // 1. utility
-    // 2. proc macros are expanded during build
-    //    so as long as this isn't done at runtime
-    //    it's more or less OK
-    pub fn iter_len<T, I: Iterator<Item = T> + Clone>(iter: &I) -> usize {
-        iter.clone().count()
+    /// Runs `f` with [`krate`] returning `path` (if there is one).
+    pub fn with_crate<R>(path: Option<Path>, f: impl FnOnce() -> R) -> R {
+        let old = CRATE.replace(path);
+        let result = f();
+        CRATE.set(old);
+        result
+    }
+
+    /// Generates `String::from(value)` without relying on the
+    /// prelude being in scope.
+    pub fn string_from(value: impl ToTokens) -> TokenStream2 {
+        quote!(<::std::string::String as ::std::convert::From<_>>::from(#value))
+    }
+
+    /// Whether `attrs` has a `#[cfg(...)]`, which can leave out what it's on.
+    pub fn has_cfg(attrs: &[Attribute]) -> bool {
+        attrs.iter().any(|attr| attr.path().is_ident("cfg"))
+    }
+
+    /// Wraps `statements` in a block with `attrs` (if there are any).
+    pub fn with_attrs(attrs: &[Attribute], statements: TokenStream2) -> TokenStream2 {
+        if attrs.is_empty() {
+            statements
+        } else {
+            quote!(#(#attrs)* { #statements })
+        }
+    }
+
+    /// The identifier of the buffer generated code pushes JSON onto.
+    ///
+    /// This has mixed-site hygiene so it can't shadow (or be shadowed
+    /// by) a variable named `string` in the caller's expressions.
+    pub fn buf() -> Ident {
+        Ident::new("string", Span::mixed_site())
+    }
+
+    /// Escapes `str` so it can be put between quotes in a JSON string.
+    ///
+    /// This mirrors what `ToJson for str` does at runtime, but
+    /// is used on literals at compile time.
+    pub fn escape_str(str: &str) -> String {
+        let mut json = String::with_capacity(str.len());
+        for ch in str.chars() {
+            match ch {
+                '"' => json.push_str("\\\""),
+                '\\' => json.push_str("\\\\"),
+                '\n' => json.push_str("\\n"),
+                '\r' => json.push_str("\\r"),
+                '\t' => json.push_str("\\t"),
+                '\u{08}' => json.push_str("\\b"),
+                '\u{0C}' => json.push_str("\\f"),
+                ch if (ch as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", ch as u32)),
+                ch => json.push(ch),
+            }
+        }
+        json
     }
 }
 
@@ -35,9 +113,16 @@ enum JsonValue {
     Object(JsonObject),
     Array(JsonArray),
     String(LitStr),
-    Bool(bool),
+    Bool(LitBool),
     Expr(Expr),
-    Null,
+    /// `fmt!("...", args)`, a string formatted like `format!`.
+    Format(TokenStream2),
+    /// JSON that's written as it is, from `include_json!`.
+    Raw(String),
+    Null(Span),
+    /// Like JavaScript, this removes the key it is the value of
+    /// in an object, and is `null` anywhere else.
+    Undefined,
 }
 
 #[derive(Clone)]
@@ -60,88 +145,405 @@ struct JsonKeyValue {
     key: JsonKey,
     value: JsonValue,
     key_span: Span,
+    /// `"key"?: value`, which leaves the pair out if `value` is `None`.
+    optional: bool,
+    /// `if guard => "key": value`
+    guard: Option<Expr>,
+    /// The segments of `@"dotted.path": value` until it's expanded
+    /// (see `insert_path`).
+    path: Option<Vec<String>>,
+    /// Whether this is an object made up to hold the rest of a path.
+    nested: bool,
+    /// Outer attributes like `#[cfg(...)]`.
+    attrs: Vec<Attribute>,
+}
+
+// These only live for the length of one expansion, so their size doesn't matter.
+#[allow(clippy::large_enum_variant)]
+enum JsonMember {
+    /// `"key": value`
+    Pair(JsonKeyValue),
+    /// `..expr`, which adds every member of `expr`.
+    Spread(Vec<Attribute>, Expr),
 }
 
 struct JsonObject {
-    pairs: Vec<JsonKeyValue>,
+    members: Vec<JsonMember>,
+    /// The span of the braces.
+    span: Span,
+}
+
+// See `JsonMember` for why this is allowed.
+#[allow(clippy::large_enum_variant)]
+enum JsonElement {
+    Value(JsonValue),
+    /// `..expr`, which adds every element of `expr`.
+    Spread(Expr),
+    /// `for pat in expr if filter => value`, which adds `value`
+    /// for every item of `expr` (that `filter` is true for).
+    For {
+        pat: Pat,
+        expr: Expr,
+        filter: Option<Expr>,
+        value: JsonValue,
+    },
 }
 
 struct JsonArray {
-    elements: Vec<JsonValue>,
+    /// Each element with its outer attributes.
+    elements: Vec<(Vec<Attribute>, JsonElement)>,
+    /// The span of the brackets.
+    span: Span,
+}
+
+/// The whole input of [`json!`], which is a value optionally
+/// preceded by `crate = path,` and `schema = "path",`, and by
+/// `as Type` if it's an object.
+struct JsonInput {
+    krate: Option<Path>,
+    schema: Option<LitStr>,
+    typed: Option<Path>,
+    value: JsonValue,
+}
+
+/// Parses the optional `crate = path,` at the start of a macro's input.
+fn parse_crate(input: ParseStream) -> SynResult<Option<Path>> {
+    if !(input.peek(Token![crate]) && input.peek2(Token![=])) {
+        return Ok(None);
+    }
+    input.parse::<Token![crate]>()?;
+    input.parse::<Token![=]>()?;
+    let krate = input.call(Path::parse_mod_style)?;
+    input.parse::<Token![,]>()?;
+    Ok(Some(krate))
+}
+
+impl Parse for JsonInput {
+    fn parse(input: ParseStream) -> SynResult<Self> {
+        let mut krate = None;
+        let mut schema = None;
+        loop {
+            if let Some(path) = parse_crate(input)? {
+                krate = Some(path);
+            } else if input.peek(Ident) && input.peek2(Token![=]) && input.fork().parse::<Ident>()? == "schema" {
+                input.parse::<Ident>()?;
+                input.parse::<Token![=]>()?;
+                schema = Some(input.parse()?);
+                input.parse::<Token![,]>()?;
+            } else {
+                break;
+            }
+        }
+
+        let mut typed = None;
+        if input.parse::<Option<Token![as]>>()?.is_some() {
+            let mut path = input.parse::<Path>()?;
+            // `Type<T> { ... }` isn't a valid expression, but `Type::<T> { ... }` is.
+            for segment in &mut path.segments {
+                if let PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                    args.colon2_token.get_or_insert_with(Default::default);
+                }
+            }
+            if !input.peek(token::Brace) {
+                return Err(input.error("expected an object after `as Type`"));
+            }
+            typed = Some(path);
+        }
+
+        Ok(JsonInput {
+            krate,
+            schema,
+            typed,
+            value: input.parse()?,
+        })
+    }
+}
+
+impl JsonInput {
+    /// Checks the value against the schema (if there is one),
+    /// returning an item to put before the generated code.
+    fn check_schema(&mut self) -> SynResult<TokenStream2> {
+        match &self.schema {
+            Some(path) => schema::check(&mut self.value, path),
+            None => Ok(TokenStream2::new()),
+        }
+    }
+
+    /// Generates `let value = /* the Type */;` for `as Type`, where
+    /// `value` is the returned identifier.
+    fn construct(&self) -> SynResult<Option<(Ident, TokenStream2)>> {
+        let (Some(ty), JsonValue::Object(obj)) = (&self.typed, &self.value) else {
+            return Ok(None);
+        };
+        let value = Ident::new("value", Span::mixed_site());
+        let construct = typed::construct(ty, obj)?;
+        let construct = quote!(let #value = #construct;);
+        Ok(Some((value, construct)))
+    }
+}
+
+/// The whole input of [`json_write!`], which is like [`JsonInput`]
+/// with the buffer or writer to write to before the value.
+struct JsonWriteInput {
+    krate: Option<Path>,
+    target: Expr,
+    value: JsonValue,
+}
+
+impl Parse for JsonWriteInput {
+    fn parse(input: ParseStream) -> SynResult<Self> {
+        let krate = parse_crate(input)?;
+        let target = input.parse()?;
+        input.parse::<Token![,]>()?;
+        Ok(JsonWriteInput {
+            krate,
+            target,
+            value: input.parse()?,
+        })
+    }
 }
 
 impl Parse for JsonKeyValue {
     fn parse(input: ParseStream) -> SynResult<Self> {
+        let mut path = None;
         let (key, span) = if input.peek(LitStr) {
             let item = input.parse::<LitStr>()?;
             (JsonKey::Lit(item.value()), item.span())
-        } else if cfg!(feature = "exprs-as-keys") {
+        } else if input.peek(Token![@]) {
+            // `@"a.b.c": value` is `"a": { "b": { "c": value } }`.
+            input.parse::<Token![@]>()?;
+            let item = input.parse::<LitStr>()?;
+            let segments = item.value().split('.').map(String::from).collect::<Vec<_>>();
+            if segments.iter().any(String::is_empty) {
+                return Err(SynError::new(item.span(), "empty segment in dotted key"));
+            }
+            path = Some(segments);
+            (JsonKey::Lit(item.value()), item.span())
+        } else if input.peek(LitInt) {
+            let item = input.parse::<LitInt>()?;
+            (JsonKey::Lit(item.base10_digits().to_string()), item.span())
+        } else if input.peek(LitChar) {
+            let item = input.parse::<LitChar>()?;
+            (JsonKey::Lit(item.value().to_string()), item.span())
+        } else if input.peek(token::Bracket) {
+            // `[expr]: value`, like JavaScript's computed keys.
+            let content;
+            let brackets = bracketed!(content in input);
+            (JsonKey::Expr(content.parse()?), brackets.span.join())
+        } else if input.peek(Ident::peek_any) && (!cfg!(feature = "exprs-as-keys") || {
+            let fork = input.fork();
+            let _ = fork.call(Ident::parse_any);
+            fork.is_empty() || fork.peek(Token![,]) || fork.peek(Token![:]) || fork.peek(Token![?])
+        }) {
+            let item = input.call(Ident::parse_any)?;
+            let key = if cfg!(feature = "exprs-as-keys") {
+                // `exprs-as-keys` has always meant that `name: value`
+                // uses the variable `name` as the key.
+                JsonKey::Expr(syn::parse_quote!(#item))
+            } else {
+                JsonKey::Lit(item.unraw().to_string())
+            };
+            if input.is_empty() || input.peek(Token![,]) {
+                let key = JsonKey::Lit(item.unraw().to_string());
+                // `{ name }` is short for `{ "name": name }`.
+                return Ok(JsonKeyValue {
+                    key,
+                    value: JsonValue::Expr(syn::parse_quote!(#item)),
+                    key_span: item.span(),
+                    optional: false,
+                    guard: None,
+                    path: None,
+                    nested: false,
+                    attrs: Vec::new(),
+                });
+            }
+            (key, item.span())
+        } else {
+            // Only reachable with `exprs-as-keys`, which is kept for
+            // compatibility now that `[expr]` works everywhere.
             let item = input.parse::<Expr>()?;
             (JsonKey::Expr(item.clone()), item.span())
-        } else {
-            let item = input.parse::<Ident>()?;
-            (JsonKey::Lit(item.to_string()), item.span())
         };
+        let optional = input.parse::<Option<Token![?]>>()?.is_some();
         input.parse::<Token![:]>()?;
+        let value = if optional {
+            JsonValue::Expr(input.parse()?)
+        } else {
+            input.parse()?
+        };
 
         Ok(JsonKeyValue {
             key,
-            value: input.parse()?,
+            value,
             key_span: span,
+            optional,
+            guard: None,
+            path,
+            nested: false,
+            attrs: Vec::new(),
         })
     }
 }
 
+/// Adds `pair`, whose key is the dotted `path`, to `members`, merging
+/// it into the objects made for earlier paths with the same prefix.
+fn insert_path(members: &mut Vec<JsonMember>, path: &[String], pair: JsonKeyValue) -> SynResult<()> {
+    let (first, rest) = path.split_first().expect("paths are never empty");
+    let key = JsonKey::Lit(first.clone());
+    let existing = members.iter_mut().find_map(|member| match member {
+        JsonMember::Pair(existing) if existing.key == key => Some(existing),
+        _ => None,
+    });
+    match existing {
+        Some(JsonKeyValue { nested: true, value: JsonValue::Object(object), .. }) if !rest.is_empty() => {
+            insert_path(&mut object.members, rest, pair)
+        }
+        // Like other keys, guarded ones can repeat (see `JsonObject::parse`).
+        Some(existing) if rest.is_empty() && !existing.nested && existing.guarded() && pair.guarded() => {
+            members.push(JsonMember::Pair(JsonKeyValue { key, ..pair }));
+            Ok(())
+        }
+        Some(existing) => {
+            let mut error = SynError::new(
+                pair.key_span,
+                format!("dotted key conflicts with an earlier key at `{first}`"),
+            );
+            error.combine(SynError::new(existing.key_span, "conflicting key defined here"));
+            Err(error)
+        }
+        None if rest.is_empty() => {
+            members.push(JsonMember::Pair(JsonKeyValue { key, ..pair }));
+            Ok(())
+        }
+        None => {
+            let key_span = pair.key_span;
+            let mut object = JsonObject {
+                members: Vec::new(),
+                span: key_span,
+            };
+            insert_path(&mut object.members, rest, pair)?;
+            members.push(JsonMember::Pair(JsonKeyValue {
+                key,
+                value: JsonValue::Object(object),
+                key_span,
+                optional: false,
+                guard: None,
+                path: None,
+                nested: true,
+                attrs: Vec::new(),
+            }));
+            Ok(())
+        }
+    }
+}
+
 impl Parse for JsonObject {
     fn parse(input: ParseStream) -> SynResult<Self> {
         let content;
-        let _ = braced!(content in input);
-        let mut pairs = Vec::new();
+        let braces = braced!(content in input);
+        let mut members = Vec::new();
 
         while !content.is_empty() {
-            let pair = content.parse()?;
-            pairs.push(pair);
+            let attrs = content.call(Attribute::parse_outer)?;
+            if content.peek(Token![..]) {
+                content.parse::<Token![..]>()?;
+                members.push(JsonMember::Spread(attrs, content.parse()?));
+            } else {
+                let guard = if content.parse::<Option<Token![if]>>()?.is_some() {
+                    let guard = content.parse()?;
+                    content.parse::<Token![=>]>()?;
+                    Some(guard)
+                } else {
+                    None
+                };
+                let mut pair = content.parse::<JsonKeyValue>()?;
+                pair.guard = guard;
+                pair.attrs = attrs;
+                match pair.path.take() {
+                    Some(path) => insert_path(&mut members, &path, pair)?,
+                    None => members.push(JsonMember::Pair(pair)),
+                }
+            }
             let _ = content.parse::<Token![,]>();
         }
 
         {
-            let mut map: Vec<(JsonKey, Span)> = Vec::new();
-            for JsonKeyValue { key, key_span, .. } in &pairs {
-                #[cfg(lints_enabled)]
-                if let Some((key, span2)) = map.iter().find(|(key2, _)| *key2 == *key) {
-                    Diagnostic::spanned(
-                        key_span.unwrap(),
-                        Level::Error,
-                        format!(
-                            "duplicate key {} in object",
-                            match key {
-                                JsonKey::Lit(str) => str.clone(),
-                                JsonKey::Expr(expr) => format!("expression `{}`", quote!(#expr)),
-                            }
-                        ),
-                    )
-                    .help("remove this repeated key")
-                    .span_note(span2.unwrap(), "key first defined here")
-                    .emit();
-                    continue;
+            let mut map: Vec<(JsonKey, Span, bool)> = Vec::new();
+            let pairs = members.iter().filter_map(|member| match member {
+                JsonMember::Pair(pair) => Some(pair),
+                JsonMember::Spread(..) => None,
+            });
+            for pair @ JsonKeyValue { key, key_span, .. } in pairs {
+                // Guarded (or `#[cfg]`'d) pairs can share a key (with
+                // conditions that exclude each other), but not with
+                // unguarded ones.
+                let guarded = pair.guarded();
+                if let Some((key, span2, _)) = map
+                    .iter()
+                    .find(|(key2, _, guarded2)| *key2 == *key && !(guarded && *guarded2))
+                {
+                    let message = format!(
+                        "duplicate key {} in object",
+                        match key {
+                            JsonKey::Lit(str) => format!("`{str}`"),
+                            JsonKey::Expr(expr) => format!("expression `{}`", quote!(#expr)),
+                        }
+                    );
+                    #[cfg(lints_enabled)]
+                    {
+                        Diagnostic::spanned(key_span.unwrap(), Level::Error, message)
+                            .help("remove this repeated key")
+                            .span_note(span2.unwrap(), "key first defined here")
+                            .emit();
+                        continue;
+                    }
+                    // Without diagnostics, the note becomes a second error.
+                    #[cfg(not(lints_enabled))]
+                    {
+                        let mut error = SynError::new(*key_span, message);
+                        error.combine(SynError::new(*span2, "key first defined here"));
+                        return Err(error);
+                    }
                 }
-                map.push((key.clone(), *key_span));
+                map.push((key.clone(), *key_span, guarded));
             }
         }
 
-        Ok(JsonObject { pairs })
+        Ok(JsonObject {
+            members,
+            span: braces.span.join(),
+        })
     }
 }
 
 impl Parse for JsonArray {
     fn parse(input: ParseStream) -> SynResult<Self> {
         let content;
-        let _brackets = bracketed!(content in input);
+        let brackets = bracketed!(content in input);
         let mut elements = Vec::new();
 
         while !content.is_empty() {
-            let elem = content.parse()?;
-            elements.push(elem);
+            let attrs = content.call(Attribute::parse_outer)?;
+            let element = if content.peek(Token![..]) {
+                content.parse::<Token![..]>()?;
+                JsonElement::Spread(content.parse()?)
+            } else if content.peek(Token![for]) {
+                content.parse::<Token![for]>()?;
+                let pat = content.call(Pat::parse_multi_with_leading_vert)?;
+                content.parse::<Token![in]>()?;
+                let expr = content.call(Expr::parse_without_eager_brace)?;
+                let filter = if content.parse::<Option<Token![if]>>()?.is_some() {
+                    Some(content.call(Expr::parse_without_eager_brace)?)
+                } else {
+                    None
+                };
+                content.parse::<Token![=>]>()?;
+                let value = content.parse()?;
+                JsonElement::For { pat, expr, filter, value }
+            } else {
+                JsonElement::Value(content.parse()?)
+            };
+            elements.push((attrs, element));
             if content.peek(Token![,]) {
                 content.parse::<Token![,]>()?;
             }
@@ -149,33 +551,42 @@ impl Parse for JsonArray {
 
         Ok(JsonArray {
             elements,
-            // span: input.span(),
+            span: brackets.span.join(),
         })
     }
 }
 
-/* // This is never used
 impl JsonValue {
-    fn span(&self) -> Option<Span> {
+    fn span(&self) -> Span {
         match self {
-            JsonValue::Object(json_object) => Some(json_object.span.clone()),
-            JsonValue::Array(json_array) => Some(json_array.span),
-            JsonValue::String(lit_str) => Some(lit_str.span()),
-            JsonValue::Number(expr) => Some(expr.span()),
-            JsonValue::Bool(_) => None,
-            JsonValue::Expr(expr) => Some(expr.span())
+            JsonValue::Object(obj) => obj.span,
+            JsonValue::Array(arr) => arr.span,
+            JsonValue::String(litstr) => litstr.span(),
+            JsonValue::Bool(b) => b.span,
+            JsonValue::Expr(expr) => expr.span(),
+            JsonValue::Format(args) => args.span(),
+            JsonValue::Null(span) => *span,
+            JsonValue::Raw(_) | JsonValue::Undefined => Span::call_site(),
         }
     }
 }
-*/
 
-fn check_int_overflow(int: LitInt) {
+/// Checks whether `int` fits in the range of integers that JavaScript
+/// (and most other languages) can represent exactly, returning `None`
+/// if it does and the value clamped to that range (or 0 if it is too
+/// big to parse) if it doesn't.
+fn clamp_int(int: &LitInt) -> Option<i64> {
     let upper = const { 2i64.pow(53) };
     let lower = -upper;
     let (clamped, fits) = int.base10_parse::<i64>().map_or((0, false), |v| {
         (v.clamp(lower, upper), (lower..upper).contains(&v))
     });
-    if !fits {
+    (!fits).then_some(clamped)
+}
+
+#[cfg(lints_enabled)]
+fn check_int_overflow(int: LitInt) {
+    if let Some(clamped) = clamp_int(&int) {
         let mut d = Diagnostic::spanned(
             int.span().unwrap(),
             Level::Note,
@@ -190,12 +601,44 @@ fn check_int_overflow(int: LitInt) {
     }
 }
 
+/// Stable Rust has no way for proc macros to emit warnings, so this
+/// generates a use of a deprecated constant whose note is the warning,
+/// spanned on the integer literal in `expr` (if it is out of range).
+#[cfg(not(lints_enabled))]
+fn int_overflow_warning(expr: &Expr) -> Option<TokenStream2> {
+    let int = match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) => int.clone(),
+        Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr, .. }) => match &**expr {
+            Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) => {
+                LitInt::new(&format!("-{}", int.base10_digits()), int.span())
+            }
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let clamped = clamp_int(&int)?;
+    let mut note = String::from(
+        "value may be outside safe integer range of most programming languages \
+        (-2^53 to 2^53 is the JavaScript safe integer range)",
+    );
+    if clamped != 0 {
+        note.push_str(&format!("; change this value or show it clamped: {clamped}"));
+    }
+    let name = Ident::new("integer_outside_safe_range", int.span());
+    Some(quote_spanned! {int.span()=>
+        #[deprecated(note = #note)]
+        #[allow(non_upper_case_globals)]
+        const #name: bool = false;
+        let _ = #name;
+    })
+}
+
 impl Parse for JsonValue {
     fn parse(input: ParseStream) -> SynResult<Self> {
         if input.peek(LitStr) {
             Ok(JsonValue::String(input.parse()?))
         } else if input.peek(LitBool) {
-            Ok(JsonValue::Bool(input.parse::<LitBool>()?.value))
+            Ok(JsonValue::Bool(input.parse()?))
         } else if input.peek(token::Brace) {
             Ok(JsonValue::Object(input.parse()?))
         } else if input.peek(token::Bracket) {
@@ -206,14 +649,63 @@ impl Parse for JsonValue {
             .map(|v| v.to_string())
             .is_ok_and(|v| v == "undefined" || v == "null")
         {
-            input.parse::<Ident>()?;
-            Ok(JsonValue::Null)
+            let ident = input.parse::<Ident>()?;
+            if ident == "undefined" {
+                Ok(JsonValue::Undefined)
+            } else {
+                Ok(JsonValue::Null(ident.span()))
+            }
         } else {
             #[cfg(lints_enabled)]
             if let Ok(int) = input.fork().parse::<LitInt>() {
                 check_int_overflow(int)
             }
-            Ok(JsonValue::Expr(input.parse()?))
+            match input.parse()? {
+                Expr::Macro(mac) if mac.mac.path.is_ident("fmt") => Ok(JsonValue::Format(mac.mac.tokens)),
+                expr => Ok(JsonValue::Expr(expr)),
+            }
+        }
+    }
+}
+
+impl JsonValue {
+    /// Generates statements that push this value onto the buffer
+    /// (see [`util::buf`]), so that nested objects and arrays are
+    /// written straight into their parent instead of their own `String`.
+    fn push_tokens(&self) -> TokenStream2 {
+        match self {
+            JsonValue::Object(obj) => obj.push_tokens(),
+            JsonValue::Array(arr) => arr.push_tokens(),
+            JsonValue::Format(args) => {
+                let krate = util::krate();
+                let string = util::buf();
+                // The string is escaped as it is formatted, so it's
+                // never in a `String` of its own.
+                quote! {
+                    #string.push('"');
+                    let _ = ::std::fmt::Write::write_fmt(
+                        &mut #krate::__private::Escaper(&mut #string),
+                        ::std::format_args!(#args),
+                    );
+                    #string.push('"');
+                }
+            }
+            JsonValue::Expr(expr) => {
+                let krate = util::krate();
+                let string = util::buf();
+                #[cfg(not(lints_enabled))]
+                let warning = int_overflow_warning(expr);
+                #[cfg(lints_enabled)]
+                let warning = None::<TokenStream2>;
+                quote_spanned!(expr.span()=> {
+                    #warning
+                    #krate::ToJson::write_json(&(#expr), &mut #string);
+                })
+            }
+            value => {
+                let string = util::buf();
+                quote!(#string.push_str(&#value);)
+            }
         }
     }
 }
@@ -223,21 +715,231 @@ impl ToTokens for JsonValue {
         match self {
             JsonValue::Object(obj) => obj.to_tokens(tokens),
             JsonValue::Array(arr) => arr.to_tokens(tokens),
-            JsonValue::String(litstr) => quote!(format!("\"{}\"", #litstr)).to_tokens(tokens),
-            JsonValue::Bool(b) => (*b).to_tokens(tokens),
+            JsonValue::String(litstr) => {
+                let json = format!("\"{}\"", util::escape_str(&litstr.value()));
+                quote!(#json).to_tokens(tokens)
+            }
+            JsonValue::Bool(b) => b.value.to_string().to_tokens(tokens),
             JsonValue::Expr(expr) => {
-                quote!((::json_proc::ToJson::to_json_string(&(#expr)))).to_tokens(tokens);
+                let krate = util::krate();
+                #[cfg(not(lints_enabled))]
+                let warning = int_overflow_warning(expr);
+                #[cfg(lints_enabled)]
+                let warning = None::<TokenStream2>;
+                // Spanned on the expression so that a missing `ToJson`
+                // impl is reported there instead of on the whole macro.
+                quote_spanned!(expr.span()=> ({
+                    #warning
+                    #krate::ToJson::to_json_string(&(#expr))
+                }))
+                .to_tokens(tokens);
             }
-            JsonValue::Null => quote!("null").to_tokens(tokens),
+            JsonValue::Format(_) => {
+                let string = util::buf();
+                let push = self.push_tokens();
+                quote! {{
+                    let mut #string = ::std::string::String::new();
+                    #push
+                    #string
+                }}
+                .to_tokens(tokens)
+            }
+            JsonValue::Raw(json) => json.to_tokens(tokens),
+            JsonValue::Null(_) | JsonValue::Undefined => quote!("null").to_tokens(tokens),
         }
     }
 }
 
-impl ToTokens for JsonKey {
-    fn to_tokens(&self, tokens: &mut TokenStream2) {
+/// Generates the JSON of a spread `..expr`.
+///
+/// A nested `json!` is expanded in place instead, so it isn't built
+/// into a `Json` of its own first.
+fn spread_json(expr: &Expr) -> TokenStream2 {
+    if let Expr::Macro(mac) = expr {
+        if mac.mac.path.segments.last().is_some_and(|seg| seg.ident == "json") {
+            return match mac.mac.parse_body::<JsonValue>() {
+                Ok(value) => value.into_token_stream(),
+                Err(error) => error.into_compile_error(),
+            };
+        }
+    }
+    let krate = util::krate();
+    quote_spanned!(expr.span()=> #krate::ToJson::to_json_string(&(#expr)))
+}
+
+impl JsonKeyValue {
+    /// Whether this pair has an `if` guard or a `#[cfg]`.
+    fn guarded(&self) -> bool {
+        self.guard.is_some() || util::has_cfg(&self.attrs)
+    }
+
+    /// Whether this pair is always in the object. An object made up for
+    /// a dotted key is only there if one of its members is, so one that
+    /// might be empty is left out when it is.
+    fn always_present(&self) -> bool {
+        !self.optional
+            && self.guard.is_none()
+            && !util::has_cfg(&self.attrs)
+            && match &self.value {
+                JsonValue::Undefined => false,
+                JsonValue::Object(object) if self.nested => object.members.iter().any(|member| match member {
+                    JsonMember::Pair(pair) => pair.always_present(),
+                    JsonMember::Spread(..) => true,
+                }),
+                _ => true,
+            }
+    }
+
+    /// Whether this is an object made up for dotted keys that might not
+    /// have any members, in which case it's left out.
+    fn may_be_empty(&self) -> bool {
+        self.nested && !self.always_present()
+    }
+
+    /// Wraps `push` (which generates code that adds this pair, given
+    /// its value) in the conditions this pair has.
+    fn conditional(&self, push: impl FnOnce(&JsonValue) -> TokenStream2) -> TokenStream2 {
+        let mut output = match &self.value {
+            JsonValue::Undefined => return TokenStream2::new(),
+            JsonValue::Expr(expr) if self.optional => {
+                let value = Ident::new("value", Span::mixed_site().located_at(expr.span()));
+                let push = push(&JsonValue::Expr(syn::parse_quote!(#value)));
+                quote_spanned! {expr.span()=>
+                    if let ::std::option::Option::Some(#value) = &(#expr) {
+                        #push
+                    }
+                }
+            }
+            value => push(value),
+        };
+        if let Some(guard) = &self.guard {
+            output = quote!(if #guard { #output });
+        }
+        util::with_attrs(&self.attrs, output)
+    }
+}
+
+impl JsonKey {
+    /// Generates the key as a quoted JSON string.
+    fn quoted(&self) -> TokenStream2 {
         match self {
-            Self::Lit(str) => quote!(#str).to_tokens(tokens),
-            Self::Expr(expr) => expr.to_tokens(tokens),
+            Self::Lit(str) => format!("\"{}\"", util::escape_str(str)).into_token_stream(),
+            Self::Expr(Expr::Macro(mac)) if mac.mac.path.is_ident("fmt") => {
+                let krate = util::krate();
+                let args = &mac.mac.tokens;
+                quote!(#krate::__private::quote_key(&::std::format_args!(#args)))
+            }
+            Self::Expr(expr) => {
+                let krate = util::krate();
+                quote_spanned!(expr.span()=> #krate::__private::quote_key(&(#expr)))
+            }
+        }
+    }
+
+    /// Generates the key as a `String`, without quotes or escapes.
+    fn unquoted(&self) -> TokenStream2 {
+        match self {
+            Self::Lit(str) => util::string_from(str),
+            Self::Expr(Expr::Macro(mac)) if mac.mac.path.is_ident("fmt") => {
+                let args = &mac.mac.tokens;
+                quote!(::std::fmt::format(::std::format_args!(#args)))
+            }
+            Self::Expr(expr) => quote_spanned!(expr.span()=> ::std::string::ToString::to_string(&(#expr))),
+        }
+    }
+
+    /// Generates statements that push `"key":` onto the buffer.
+    fn push_tokens(&self) -> TokenStream2 {
+        let string = util::buf();
+        match self {
+            Self::Lit(str) => {
+                let key = format!("\"{}\":", util::escape_str(str));
+                quote!(#string.push_str(#key);)
+            }
+            Self::Expr(_) => {
+                let key = self.quoted();
+                quote! {
+                    #string.push_str(&#key);
+                    #string.push(':');
+                }
+            }
+        }
+    }
+}
+
+impl JsonObject {
+    fn push_tokens(&self) -> TokenStream2 {
+        let krate = util::krate();
+        let string = util::buf();
+        if self.members.iter().any(|member| matches!(member, JsonMember::Spread(..))) {
+            // Spread members are only known at runtime, so build
+            // the object up in a way that lets later keys replace
+            // earlier ones.
+            let object = Ident::new("object", Span::mixed_site());
+            let members = self.members.iter().map(|member| match member {
+                JsonMember::Pair(pair) => pair.conditional(|value| {
+                    let key = pair.key.unquoted();
+                    let value = util::string_from(value);
+                    if pair.may_be_empty() {
+                        let nested = Ident::new("nested", Span::mixed_site());
+                        quote! {
+                            let #nested = #value;
+                            if #nested != "{}" {
+                                #object.insert(#key, #nested);
+                            }
+                        }
+                    } else {
+                        quote!(#object.insert(#key, #value);)
+                    }
+                }),
+                JsonMember::Spread(attrs, expr) => {
+                    let json = spread_json(expr);
+                    util::with_attrs(attrs, quote!(#object.spread(&#json);))
+                }
+            });
+            return quote! {{
+                let mut #object = #krate::__private::Object::new();
+                #(#members)*
+                #string.push_str(&#object.finish());
+            }};
+        }
+        let pairs = self.members.iter().filter_map(|member| match member {
+            JsonMember::Pair(pair) => Some(pair.conditional(|value| {
+                let key = pair.key.push_tokens();
+                let value = value.push_tokens();
+                if pair.may_be_empty() {
+                    // Take the key back off if the object is `{}`.
+                    let start = Ident::new("start", Span::mixed_site());
+                    let value_start = Ident::new("value_start", Span::mixed_site());
+                    quote! {
+                        let #start = #string.len();
+                        #key
+                        let #value_start = #string.len();
+                        { #value }
+                        if #string.len() == #value_start + 2 {
+                            #string.truncate(#start);
+                        } else {
+                            #string.push(',');
+                        }
+                    }
+                } else {
+                    quote! {
+                        #key
+                        #value
+                        #string.push(',');
+                    }
+                }
+            })),
+            JsonMember::Spread(..) => None,
+        });
+        quote! {
+            #string.push('{');
+            #(#pairs)*
+            // Pairs can be left out, so there may not be a trailing comma.
+            if #string.ends_with(',') {
+                let _ = #string.pop();
+            }
+            #string.push('}');
         }
     }
 }
@@ -245,63 +947,83 @@ impl ToTokens for JsonKey {
 // Implementing quote for JsonObject to generate valid Rust code
 impl ToTokens for JsonObject {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
-        if self.pairs.is_empty() {
-            return quote!("{}".to_string()).to_tokens(tokens);
-        }
-        let pairs = &self.pairs;
-        let mut pairs_tokens = Vec::new();
-        for pair in pairs {
-            let key = &pair.key;
-            let value = &pair.value;
-            pairs_tokens.push(quote!(format!("\"{}\":{}", #key, #value)));
+        if self.members.is_empty() {
+            return util::string_from("{}").to_tokens(tokens);
         }
+        let string = util::buf();
+        let push = self.push_tokens();
         let output = quote! {{
             // format!("{{{}}}", (vec![#(#pairs_tokens),*] as Vec<String>).join(","))
-            let mut string = String::with_capacity(2);
-            string.push('{');
-            #(
-                string.push_str(&#pairs_tokens);
-                string.push(',');
-            )*
-            let _ = string.pop();
-            string.push('}');
-            string
+            let mut #string = ::std::string::String::with_capacity(2);
+            #push
+            #string
         }};
         output.to_tokens(tokens);
     }
 }
 
+impl JsonArray {
+    fn push_tokens(&self) -> TokenStream2 {
+        let krate = util::krate();
+        let string = util::buf();
+        let elements = self.elements.iter().map(|(attrs, element)| {
+            let element = match element {
+                JsonElement::Value(value) => {
+                    let value = value.push_tokens();
+                    quote! {
+                        #value
+                        #string.push(',');
+                    }
+                }
+                JsonElement::Spread(expr) => {
+                    let json = spread_json(expr);
+                    quote!(#krate::__private::push_elements(&mut #string, &#json);)
+                }
+                JsonElement::For { pat, expr, filter, value } => {
+                    let value = value.push_tokens();
+                    let mut body = quote! {
+                        #value
+                        #string.push(',');
+                    };
+                    if let Some(filter) = filter {
+                        body = quote!(if #filter { #body });
+                    }
+                    quote!(for #pat in #expr { #body })
+                }
+            };
+            util::with_attrs(attrs, element)
+        });
+        quote! {
+            #string.push('[');
+            #(#elements)*
+            // Spread and comprehension elements can be empty, so
+            // there may not be a trailing comma.
+            if #string.ends_with(',') {
+                let _ = #string.pop();
+            }
+            #string.push(']');
+        }
+    }
+}
+
 impl ToTokens for JsonArray {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         if self.elements.is_empty() {
-            return quote!("[]").to_tokens(tokens);
-        }
-        let elements = &self.elements;
-        let mut elements_tokens = Vec::new();
-        for elem in elements {
-            elements_tokens.push(quote!(#elem));
+            return util::string_from("[]").to_tokens(tokens);
         }
+        let string = util::buf();
+        let push = self.push_tokens();
         let output = quote! {{
             // format!("[{}]", (vec![#(#elements_tokens),*] as Vec<String>).join(","))
-            let mut string = String::with_capacity(2);
-            string.push('[');
-            #(
-                string.push_str(&#elements_tokens);
-                string.push(',');
-            )*
-            let _ = string.pop();
-            string.push(']');
-            string
+            let mut #string = ::std::string::String::with_capacity(2);
+            #push
+            #string
         }};
         output.to_tokens(tokens);
     }
 }
 
-// Lints only happen on nightly since diagnostics are
-// unstable features, so change the docs to reflect that.
-#[cfg_attr(lints_enabled, doc = "Lints and properly ")]
-#[cfg_attr(not(lints_enabled), doc = "Properly ")]
-/// formats a JSON object, array, or value.
+/// Lints and properly formats a JSON object, array, or value.
 ///
 /// This macro supports:
 /// - all literals (integers, floats, [`&str`][strlit], [`char`])
@@ -310,22 +1032,45 @@ impl ToTokens for JsonArray {
 /// If you are looking for custom serialization traits, macros,
 /// and functions, use `serde_json` and `serde` instead.
 ///
-#[cfg_attr(
-    not(lints_enabled),
-    doc = "Keep in mind, lints are only enabled on the Nightly channel of Rust."
-)]
+/// The result is a [`Json`][Json], which derefs to the JSON text and
+/// converts into a `String`. Unlike a `String`, it's written as-is when
+/// nested in another value.
+///
+/// Duplicate keys in an object are an error, and integer literals
+/// outside of the range JavaScript can represent exactly (-2^53 to 2^53)
+/// produce a warning.
+///
+/// ```compile_fail
+/// # use json_proc::json;
+/// let value = json!({ "a": 1, "a": 2 });
+/// ```
+/// The same goes for dotted keys that conflict:
+/// ```compile_fail
+/// # use json_proc::json;
+/// let value = json!({ @"a.b": 1, @"a.b.c": 2 });
+/// ```
+/// Keys (dotted or not) with a `#[cfg(...)]` or an `if` guard can be
+/// repeated, but other attributes don't make a key conditional:
+/// ```compile_fail
+/// # use json_proc::json;
+/// let value = json!({ "a": 1, #[allow(unused)] "a": 2 });
+/// ```
 #[cfg_attr(
     lints_enabled,
-    doc = "Lints are currently enabled because you are using the Nightly channel of Rust."
+    doc = "These are reported with richer diagnostics because you are using the Nightly channel of Rust."
 )]
+///
 /// ## Examples:
 ///
 /// Serializing an object:
-/// ```no_run
+// Bare keys are variables with `exprs-as-keys`, which the tests use.
+#[cfg_attr(feature = "exprs-as-keys", doc = "```ignore")]
+#[cfg_attr(not(feature = "exprs-as-keys"), doc = "```no_run")]
+/// # use json_proc::json;
 /// // You have to have the `ToJson` trait restriction since
 /// // the json! macro uses ToJson. Should a struct not
 /// // implement ToJson, you can use the derive macro.
-/// fn obj<J: json_proc::ToJson>(input: J) -> String {
+/// fn obj<J: json_proc::ToJson>(input: J) -> json_proc::Json {
 ///     json!({
 ///         "hello": "world!",
 ///         thisDidntNeedQuotes: "wow!",
@@ -337,7 +1082,8 @@ impl ToTokens for JsonArray {
 ///
 /// Serializing an array:
 /// ```no_run
-/// fn arr<J: json_proc::ToJson>(input: J) -> String {
+/// # use json_proc::json;
+/// fn arr<J: json_proc::ToJson>(input: J) -> json_proc::Json {
 ///     json!([
 ///         input,
 ///         (2 + 11) as f32 / 2.0,
@@ -346,13 +1092,528 @@ impl ToTokens for JsonArray {
 /// }
 /// ```
 ///
+/// Strings can be formatted with `fmt!`, which takes the same arguments
+/// as [`format!`] and escapes the result as it's written:
+/// ```
+/// # use json_proc::json;
+/// let id = 5;
+/// assert_eq!(
+///     json!({ "message": fmt!("user {id} said \"{}\"", "hi"), [fmt!("id-{id}")]: id }),
+///     r#"{"message":"user 5 said \"hi\"","id-5":5}"#
+/// );
+/// ```
+///
+/// Keys can be string, integer or char literals, identifiers (which
+/// are taken literally, like in JavaScript, unless `exprs-as-keys` is
+/// on) or `[expr]` for a key computed from anything that implements
+/// [`Display`][std::fmt::Display].
+/// `{ name }` is short for `{ "name": name }`.
+/// ```
+/// # use json_proc::json;
+/// let id = 12;
+/// let name = "n";
+/// assert_eq!(
+///     json!({ [format!("user-{id}")]: true, 1: 'x', name }),
+///     r#"{"user-12":true,"1":"x","name":"n"}"#
+/// );
+/// ```
+/// With the `exprs-as-keys` feature, any expression works as a key
+/// without the brackets, including a variable (so `{ name: 1 }` is
+/// `{ [name]: 1 }` there), but that is only kept for compatibility.
+///
+/// Keys starting with `@` are dotted paths into nested objects, and
+/// paths with the same prefix are merged. An object made up for a path
+/// is left out if every key in it is (like `@"a.b"?: None::<u8>`):
+/// ```
+/// # use json_proc::json;
+/// let host = "localhost";
+/// assert_eq!(
+///     json!({ @"server.http.port": 8080, @"server.http.host": host, "a.b": 1 }),
+///     r#"{"server":{"http":{"port":8080,"host":"localhost"}},"a.b":1}"#
+/// );
+/// ```
+///
+/// Pairs and elements can have outer attributes like `#[cfg(...)]`:
+/// ```
+/// # use json_proc::json;
+/// let value = json!({
+///     #[cfg(debug_assertions)]
+///     "build": "debug",
+///     #[cfg(not(debug_assertions))]
+///     "build": "release",
+///     "features": [#[cfg(feature = "never")] "never"],
+/// });
+/// # assert!(value.as_str().ends_with(r#""features":[]}"#));
+/// ```
+///
+/// Spreading the members of another object (anything whose `ToJson`
+/// impl produces an object) or the elements of another array, where
+/// later keys replace earlier ones:
+/// ```
+/// # use json_proc::json;
+/// let defaults = std::collections::BTreeMap::from([("retries", 3), ("timeout", 30)]);
+/// assert_eq!(
+///     json!({ ..defaults, "timeout": 60, ..json!({ "verbose": true }) }),
+///     r#"{"retries":3,"timeout":60,"verbose":true}"#
+/// );
+///
+/// let items = vec![1, 2, 3];
+/// assert_eq!(json!([0, ..items, 4]), "[0,1,2,3,4]");
+/// ```
+/// Spreading a value that isn't an object (or array) panics.
+///
+/// Building an array from an iterator, optionally filtering it, with
+/// each element written straight into the array:
+/// ```
+/// # use json_proc::json;
+/// let users = [(1, "ann", true), (2, "bob", false)];
+/// assert_eq!(
+///     json!([for (id, name, active) in users if active => { "id": id, "name": name }]),
+///     r#"[{"id":1,"name":"ann"}]"#
+/// );
+/// ```
+///
+/// Leaving out pairs of an object, with `"key"?: option` (left out if
+/// the option is `None`), `if condition => "key": value`, or a value
+/// of `undefined`:
+/// ```
+/// # use json_proc::json;
+/// let name = Some("new name");
+/// let email = None::<String>;
+/// let admin = false;
+/// assert_eq!(
+///     json!({
+///         "name"?: name,
+///         "email"?: email,
+///         if admin => "role": "admin",
+///         "deleted": undefined,
+///     }),
+///     r#"{"name":"new name"}"#
+/// );
+/// ```
+///
+/// A value can be checked against a [JSON Schema](https://json-schema.org)
+/// (relative to the crate's `Cargo.toml`) with `schema = "path",`.
+/// Literal keys are checked against `properties`, `required` (so
+/// required keys can't be optional, guarded or `#[cfg]`'d) and
+/// `additionalProperties`, and literal values against `type`, `enum`
+/// and `const`, at compile time. Other values have their type and
+/// `enum` checked when they're serialized if `debug_assertions` are on.
+/// Like in JSON Schema, any number with no fractional part (like `1.0`)
+/// is an `integer`, whether it comes from an integer or a float.
+/// Local `$ref`s are followed, and anything else in the schema is ignored.
+/// ```compile_fail
+/// # use json_proc::json;
+/// // `id` is required, and `name` isn't in `properties`.
+/// let value = json!(schema = "../tests/fixtures/event.schema.json", {
+///     "kind": "created",
+///     "name": "x",
+/// });
+/// ```
+/// ```compile_fail
+/// # use json_proc::json;
+/// // `kind` has to be "created" or "deleted".
+/// let value = json!(schema = "../tests/fixtures/event.schema.json", { "kind": "moved", "id": 1 });
+/// ```
+/// ```compile_fail
+/// # use json_proc::json;
+/// // Required keys can't be left out at runtime either.
+/// let value = json!(schema = "../tests/fixtures/event.schema.json", { "kind": "created", "id"?: None::<u8> });
+/// ```
+///
+/// Starting an object with `as Type` builds a `Type` (which has to
+/// derive `ToJson` with `#[json(typed)]`) out of it instead, so the
+/// compiler checks that every key is one of its fields (by the name
+/// it's written with, so after `#[json(rename)]`), that none are
+/// missing, and that every value has the type of its field.
+/// `Enum::Variant` works too, as does `Self` or an alias of the type.
+/// Literal strings and arrays are converted with [`Into`] and `null`
+/// is `None`.
+/// ```
+/// # use json_proc::{json, ToJson};
+/// #[derive(ToJson)]
+/// #[json(typed)]
+/// struct OrderEvent {
+///     id: u64,
+///     total: f64,
+///     note: Option<String>,
+///     tags: Vec<String>,
+/// }
+///
+/// let id = 3;
+/// assert_eq!(
+///     json!(as OrderEvent { "id": id, "total": 9.5, "note": null, "tags": ["new"] }),
+///     r#"{"id":3,"total":9.5,"note":null,"tags":["new"]}"#
+/// );
+/// ```
+/// ```compile_fail
+/// # use json_proc::{json, ToJson};
+/// # #[derive(ToJson)]
+/// # #[json(typed)]
+/// # struct OrderEvent { id: u64, total: f64 }
+/// // `totl` isn't a field of `OrderEvent`.
+/// let value = json!(as OrderEvent { "id": 1, "totl": 2.0 });
+/// ```
+/// ```compile_fail
+/// # use json_proc::{json, ToJson};
+/// # #[derive(ToJson)]
+/// # #[json(typed)]
+/// # struct OrderEvent { id: u64, total: f64 }
+/// // `total` is missing (which is reported as the object having fewer
+/// // keys than `OrderEvent` has fields).
+/// let value = json!(as OrderEvent { "id": 1 });
+/// ```
+/// ```compile_fail
+/// # use json_proc::{json, ToJson};
+/// # #[derive(ToJson)]
+/// # #[json(typed)]
+/// # struct User { #[json(rename = "userId")] user_id: u64 }
+/// // `user_id` is written as `userId`.
+/// let value = json!(as User { "user_id": 1 });
+/// ```
+///
+/// If `json_proc` isn't available as `::json_proc` (because it was
+/// renamed or re-exported from another crate), pass its path first:
+/// ```no_run
+/// # extern crate json_proc as renamed;
+/// # use renamed::json;
+/// let value = json!(crate = ::renamed, [1, 2, 3]);
+/// ```
+///
 /// [strlit]: str
 /// [ToJson]: https://docs.rs/json_proc/latest/json_proc/trait.ToJson.html
+/// [Json]: https://docs.rs/json_proc/latest/json_proc/struct.Json.html
 #[proc_macro]
 pub fn json(input: TokenStream) -> TokenStream {
-    let json_value = parse_macro_input!(input as JsonValue);
+    let result = parse::<JsonInput>(input).and_then(|mut input| {
+        util::with_crate(input.krate.clone(), || {
+            let track = input.check_schema()?;
+            let krate = util::krate();
+            if let Some((value, construct)) = input.construct()? {
+                return Ok(quote!({
+                    #track
+                    #construct
+                    #krate::ToJson::to_json(&#value)
+                }));
+            }
+            let string = util::string_from(&input.value);
+            Ok(quote!({
+                #track
+                #krate::Json::from_string_unchecked(#string)
+            }))
+        })
+    });
+    match result {
+        Ok(tokens) => tokens.into(),
+        // Put the errors in a block since there may be more than one
+        // `compile_error!` and this is used as an expression.
+        Err(error) => {
+            let error = error.into_compile_error();
+            quote!({ #error }).into()
+        }
+    }
+}
+
+/// The whole input of [`include_json!`]: an optional `crate = path,`,
+/// the path of the file and then `name = value` for each placeholder.
+struct IncludeInput {
+    krate: Option<Path>,
+    path: LitStr,
+    bindings: Vec<(Ident, Expr)>,
+}
+
+impl Parse for IncludeInput {
+    fn parse(input: ParseStream) -> SynResult<Self> {
+        let krate = parse_crate(input)?;
+        let path = input.parse()?;
+        let mut bindings = Vec::new();
+        while input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let name = input.call(Ident::parse_any)?;
+            input.parse::<Token![=]>()?;
+            bindings.push((name, input.parse()?));
+        }
+        if !input.is_empty() {
+            return Err(input.error("expected `,`"));
+        }
+        Ok(IncludeInput { krate, path, bindings })
+    }
+}
+
+/// Like [`json!`], but appends the JSON to an existing buffer or writer
+/// instead of returning a new `String`.
+///
+/// The first argument is a mutable reference to one of:
+/// - a `String`, which the JSON is written straight into
+///   (returns [`fmt::Result`][std::fmt::Result], which is always `Ok`)
+/// - a `Vec<u8>` (returns [`io::Result<()>`][std::io::Result], which is always `Ok`)
+/// - anything else that implements [`fmt::Write`][std::fmt::Write]
+///   (returns [`fmt::Result`][std::fmt::Result])
+/// - anything else that implements [`io::Write`][std::io::Write]
+///   (returns [`io::Result<()>`][std::io::Result])
+///
+/// Anything but a `String` gets the whole value in a single write.
+///
+/// ```
+/// # use json_proc::json_write;
+/// let mut buf = String::from("data: ");
+/// json_write!(&mut buf, { "id": 1, "tags": ["a", "b"] }).unwrap();
+/// assert_eq!(buf, r#"data: {"id":1,"tags":["a","b"]}"#);
+///
+/// let mut bytes = 7u32.to_be_bytes().to_vec();
+/// json_write!(&mut bytes, [true, null])?;
+/// assert_eq!(bytes, b"\0\0\0\x07[true,null]");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// Like [`json!`], the path to `json_proc` can be given first with
+/// `crate = path,`.
+#[proc_macro]
+pub fn json_write(input: TokenStream) -> TokenStream {
+    let JsonWriteInput { krate, target, value } = match parse::<JsonWriteInput>(input) {
+        Ok(input) => input,
+        Err(error) => {
+            let error = error.into_compile_error();
+            return quote!({ #error }).into();
+        }
+    };
 
-    quote!(#json_value).into()
+    util::with_crate(krate, || {
+        let krate = util::krate();
+        let string = util::buf();
+        let push = value.push_tokens();
+        quote! {{
+            // Which `write_json` gets called depends on the type of the
+            // target, see `__private::Writer`.
+            #[allow(unused_imports)]
+            use #krate::__private::{WriteFmt as _, WriteIo as _};
+            #krate::__private::Writer(#target).write_json(
+                |mut #string: ::std::string::String| -> ::std::string::String {
+                    #push
+                    #string
+                },
+            )
+        }}
+    })
+    .into()
+}
+
+/// Like [`json!`], but evaluates to a value that implements `ToJson`
+/// and [`Display`][std::fmt::Display] instead of a `String`.
+///
+/// Nothing is serialized until the value is: the expressions in it are
+/// borrowed, and evaluated every time it's written (which is never if
+/// it's only passed to a disabled log level). When it's nested in
+/// [`json!`] (or another `json_lazy!`), it's written straight into the
+/// parent as JSON, not as a string.
+///
+/// ```
+/// # use json_proc::{json, json_lazy};
+/// let id = 4;
+/// let names = vec!["a", "b"];
+/// let user = json_lazy!({ "id": id, "names": [for name in &names => name] });
+/// assert_eq!(user.to_string(), r#"{"id":4,"names":["a","b"]}"#);
+/// assert_eq!(json!({ "user": user }), r#"{"user":{"id":4,"names":["a","b"]}}"#);
+/// ```
+///
+/// Since the value can be written more than once, anything moved into
+/// it (like an iterator being looped over) is an error. That includes
+/// the fields of `as Type { ... }`, since the `Type` is built again
+/// every time too, so they have to be things like `name.clone()`:
+/// ```
+/// # use json_proc::{json_lazy, ToJson};
+/// #[derive(ToJson)]
+/// #[json(typed)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// let name = String::from("ann");
+/// let user = json_lazy!(as User { name: name.clone() });
+/// assert_eq!(user.to_string(), r#"{"name":"ann"}"#);
+/// ```
+/// ```compile_fail
+/// # use json_proc::{json_lazy, ToJson};
+/// # #[derive(ToJson)]
+/// # #[json(typed)]
+/// # struct User {
+/// #     name: String,
+/// # }
+/// let name = String::from("ann");
+/// let user = json_lazy!(as User { name: name });
+/// ```
+///
+/// Like [`json!`], the path to `json_proc` can be given first with
+/// `crate = path,`.
+#[proc_macro]
+pub fn json_lazy(input: TokenStream) -> TokenStream {
+    let result = parse::<JsonInput>(input).and_then(|mut input| {
+        util::with_crate(input.krate.clone(), || {
+            let track = input.check_schema()?;
+            let krate = util::krate();
+            let string = util::buf();
+            if let Some((value, construct)) = input.construct()? {
+                return Ok(quote!({
+                    #track
+                    #krate::__private::lazy(|mut #string: ::std::string::String| -> ::std::string::String {
+                        #construct
+                        #krate::ToJson::write_json(&#value, &mut #string);
+                        #string
+                    })
+                }));
+            }
+            let push = input.value.push_tokens();
+            Ok(quote!({
+                #track
+                #krate::__private::lazy(|mut #string: ::std::string::String| -> ::std::string::String {
+                    #push
+                    #string
+                })
+            }))
+        })
+    });
+    match result {
+        Ok(tokens) => tokens.into(),
+        Err(error) => {
+            let error = error.into_compile_error();
+            quote!({ #error }).into()
+        }
+    }
+}
+
+/// Like [`json!`], but builds a [`Value`][Value] instead of a string,
+/// so the result can be inspected and changed before it's serialized.
+///
+/// Everything [`json!`] takes works the same way. Expressions are
+/// converted with `ToJson::to_value`, and `..spread` members and
+/// elements have to convert to an object or array.
+///
+/// ```
+/// # use json_proc::json_value;
+/// let ids = [1, 2, 3];
+/// let mut value = json_value!({
+///     "name": "ann",
+///     "ids": [for id in ids if id != 2 => id],
+/// });
+/// value["ids"][1] = 4.into();
+/// assert_eq!(value["name"], "ann");
+/// assert_eq!(value.to_string(), r#"{"name":"ann","ids":[1,4]}"#);
+/// ```
+///
+/// Like [`json!`], the path to `json_proc` can be given first with
+/// `crate = path,`.
+///
+/// [Value]: https://docs.rs/json_proc/latest/json_proc/enum.Value.html
+#[proc_macro]
+pub fn json_value(input: TokenStream) -> TokenStream {
+    let result = parse::<JsonInput>(input).and_then(|mut input| {
+        util::with_crate(input.krate.clone(), || {
+            let track = input.check_schema()?;
+            let krate = util::krate();
+            if let Some((value, construct)) = input.construct()? {
+                return Ok(quote!({
+                    #track
+                    #construct
+                    #krate::ToJson::to_value(&#value)
+                }));
+            }
+            let value = value::build(&input.value);
+            Ok(quote!({
+                #track
+                #value
+            }))
+        })
+    });
+    match result {
+        Ok(tokens) => tokens.into(),
+        Err(error) => {
+            let error = error.into_compile_error();
+            quote!({ #error }).into()
+        }
+    }
+}
+
+/// Includes a JSON file as a `&'static str`, which is checked to be
+/// valid JSON and minified at compile time.
+///
+/// The path is relative to the crate's `Cargo.toml` (not to the
+/// current file like [`include_str!`]), and the crate is rebuilt
+/// when the file changes.
+///
+/// ```ignore
+/// const DEFAULTS: &str = include_json!("defaults.json");
+/// ```
+///
+/// The file can also be a template, with `name = value` after the path
+/// for each `${name}` in it. A string that's just `"${name}"` is replaced
+/// by the JSON of `value` (which has to implement `ToJson`), and any
+/// other string with placeholders in it is formatted with their
+/// [`Display`][std::fmt::Display] impls. This evaluates to a
+/// [`Json`][Json] instead, like [`json!`].
+///
+/// ```ignore
+/// // { "user": "${user}", "greeting": "Hello, ${name}!" }
+/// let json = include_json!("template.json", user = user, name = user.name);
+/// ```
+///
+/// Like [`json!`], the path to `json_proc` can be given first with
+/// `crate = path,`.
+///
+/// [Json]: https://docs.rs/json_proc/latest/json_proc/struct.Json.html
+#[proc_macro]
+pub fn include_json(input: TokenStream) -> TokenStream {
+    let result = parse::<IncludeInput>(input).and_then(|input| {
+        let (full_path, src) = include::read(&input.path)?;
+        let node = include::parse(&src)
+            .map_err(|error| SynError::new(input.path.span(), error.display(&full_path, &src)))?;
+        let track = include::track(&full_path);
+
+        if input.bindings.is_empty() {
+            let mut json = String::with_capacity(src.len());
+            node.minify(&mut json);
+            return Ok(quote!({
+                #track
+                #json
+            }));
+        }
+
+        let mut names = std::collections::HashMap::new();
+        let mut lets = Vec::new();
+        for (name, expr) in &input.bindings {
+            // `r#type = x` is for `${type}`.
+            let name = name.unraw();
+            // Each value is evaluated once, even if it's used more than once.
+            let ident = Ident::new(&format!("__{name}"), Span::mixed_site());
+            if names.insert(name.to_string(), ident.clone()).is_some() {
+                return Err(SynError::new(name.span(), format!("`{name}` is given more than once")));
+            }
+            lets.push(quote!(let #ident = &(#expr);));
+        }
+        let mut used = Vec::new();
+        let value = node
+            .into_value(&names, &mut used)
+            .map_err(|error| SynError::new(input.path.span(), format!("{full_path}: {error}")))?;
+        let mut unused = input.bindings.iter().map(|(name, _)| name.unraw());
+        if let Some(name) = unused.find(|name| !used.contains(&name.to_string())) {
+            return Err(SynError::new(name.span(), format!("`${{{name}}}` isn't in {full_path}")));
+        }
+
+        Ok(util::with_crate(input.krate, || {
+            let krate = util::krate();
+            let string = util::string_from(value);
+            quote!({
+                #track
+                #(#lets)*
+                #krate::Json::from_string_unchecked(#string)
+            })
+        }))
+    });
+    match result {
+        Ok(tokens) => tokens.into(),
+        Err(error) => {
+            let error = error.into_compile_error();
+            quote!({ #error }).into()
+        }
+    }
 }
 
 /// Derive the ToJson trait for a struct or enum.
@@ -377,169 +1638,439 @@ pub fn json(input: TokenStream) -> TokenStream {
 ///     }))
 /// }
 /// ```
-#[proc_macro_derive(ToJson)]
-// TODO: add enum support
+///
+/// ## Attributes:
+///
+/// On the struct or enum:
+/// - `#[json(crate = "path")]`: the path to `json_proc` to use in the
+///   generated impl, in case it isn't available as `::json_proc`.
+/// - `#[json(typed)]`: let [`json!(as Type { ... })`][json!] build the
+///   type (or its variants). This generates hidden items next to it, so
+///   it's opt-in.
+///
+/// On the struct:
+/// - `#[json(transparent)]`: serialize a struct with a single field
+///   (not counting `PhantomData` markers) exactly like that field.
+///
+/// On the enum:
+/// - `#[json(repr)]`: serialize a fieldless enum as its integer
+///   discriminant (using the type from `#[repr(..)]` if there is one)
+///   instead of the variant name.
+/// - `#[json(tag = "type")]`: serialize each variant as an object with
+///   its name under `"type"`, next to its fields. Tuple variants need
+///   `content` too.
+/// - `#[json(tag = "t", content = "c")]`: serialize each variant as
+///   `{"t": "Name", "c": ...}`, with its fields under `"c"`.
+///
+/// Without either, unit variants are their name and the others are
+/// just their fields, so they can only be told apart by their shape.
+///
+/// ```compile_fail
+/// # use json_proc::ToJson;
+/// // This fails since `Text` carries data.
+/// #[derive(ToJson)]
+/// #[json(repr)]
+/// enum Message {
+///     Quit,
+///     Text(String),
+/// }
+/// ```
+/// ```compile_fail
+/// # use json_proc::ToJson;
+/// // So does using it on a struct.
+/// #[derive(ToJson)]
+/// #[json(repr)]
+/// struct Code(u16);
+/// ```
+///
+/// On variants:
+/// - `#[json(rename = "name")]`: use this name instead of the variant's.
+///
+/// On fields:
+/// - `#[json(as_string)]` or `#[json(display)]`: serialize the field
+///   as a JSON string using its [`Display`][std::fmt::Display] impl.
+/// - `#[json(rename = "key")]`: use this key instead of the field's name.
+/// - `#[json(skip)]`: leave the field out.
+///
+/// On either:
+/// - `#[json(bound = "T: Trait")]`: use these `where` predicates instead
+///   of the inferred ones. By default, every type parameter that a
+///   serialized field uses (outside of a `PhantomData`) gets a
+///   `T: ToJson` bound, so recursive types like `Tree<T>` with a
+///   `Vec<Tree<T>>` field work.
+///
+/// These are shared with [`FromJson`][derive@FromJson], which reads
+/// back what this writes (and ignores the attributes it doesn't use).
+#[proc_macro_derive(ToJson, attributes(json))]
 pub fn json_derive(item: TokenStream) -> TokenStream {
-    if let Ok(mut input) = parse::<ItemStruct>(item.clone()) {
-        let ident = &input.ident;
-        let mut members = input.fields.members().peekable();
-        input.generics.make_where_clause();
-        let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
-        let mut where_clause = input.generics.where_clause.clone().unwrap();
-        let type_generics = input.generics.type_params().map(|v| v.ident.clone());
-        let path = quote!(::json_proc::ToJson).into();
-        let path = parse_macro_input!(path as syn::Path);
-        for ty in type_generics {
-            let mut bounds = syn::punctuated::Punctuated::new();
-            bounds.push(syn::TypeParamBound::Trait(syn::TraitBound { paren_token: None, modifier: syn::TraitBoundModifier::None, lifetimes: None, path: path.clone() }));
-            where_clause.predicates.push(syn::WherePredicate::Type(syn::PredicateType { lifetimes: None, bounded_ty: syn::Type::Verbatim(quote!(#ty)), colon_token: Token![:](Span::call_site()), bounds }))
-        }
-
-        let fn_impl = if members
-            .peek()
-            .is_some_and(|v| matches!(v, Member::Unnamed(_)))
-        {
-            if util::iter_len(&members) == 1 {
-                // Generate an impl that uses the first (and only) element in the tuple.
-                quote!(::json_proc::ToJson::to_json_string(&self.0))
-            } else {
-                // Generate an array-like impl.
-                quote! {{
-                    // format!("[{}]", (vec![#(#elements_tokens),*] as Vec<String>).join(","))
-                    let mut string = String::with_capacity(2);
-                    string.push('[');
-                    #(
-                        string.push_str(&(::json_proc::ToJson::to_json_string(&self.#members)));
-                        string.push(',');
-                    )*
-                    let _ = string.pop();
-                    string.push(']');
-                    string
-                }}
+    let result = if let Ok(input) = parse::<ItemStruct>(item.clone()) {
+        ContainerAttrs::from_attrs(&input.attrs).and_then(|container| {
+            util::with_crate(container.krate.clone(), || derive_struct(input, container))
+        })
+    } else if let Ok(input) = parse::<ItemEnum>(item.clone()) {
+        ContainerAttrs::from_attrs(&input.attrs).and_then(|container| {
+            util::with_crate(container.krate.clone(), || derive_enum(input, container))
+        })
+    } else {
+        Err(SynError::new(
+            TokenStream2::from(item).span(),
+            "expected struct or enum for deriving ToJson",
+        ))
+    };
+    result.unwrap_or_else(SynError::into_compile_error).into()
+}
+
+/// Derive the FromJson trait for a struct or enum, reading what
+/// [`ToJson`][derive@ToJson] writes.
+///
+/// ## Example:
+///
+/// ```
+/// use json_proc::{FromJson, ToJson};
+///
+/// #[derive(ToJson, FromJson, Debug, PartialEq)]
+/// #[json(tag = "type")]
+/// enum Shape {
+///     Circle { radius: f64 },
+///     #[json(rename = "rect")]
+///     Rectangle {
+///         width: f64,
+///         #[json(default)]
+///         height: f64,
+///     },
+/// }
+///
+/// let shape = Shape::from_json_str(r#"{"width": 2.5, "type": "rect"}"#)?;
+/// assert_eq!(shape, Shape::Rectangle { width: 2.5, height: 0.0 });
+/// assert_eq!(Shape::from_json_str(&shape.to_json_string())?, shape);
+///
+/// let error = Shape::from_json_str(r#"{"type": "Circle", "radius": "1"}"#).unwrap_err();
+/// assert_eq!(error.to_string(), "expected a number, found a string at line 1, column 30 in `/radius`");
+/// # Ok::<(), json_proc::ParseError>(())
+/// ```
+///
+/// ## Attributes:
+///
+/// This takes the same attributes as `#[derive(ToJson)]`, plus a few
+/// that only matter when reading.
+///
+/// On the struct or enum:
+/// - `#[json(deny_unknown_fields)]`: fail on keys that aren't fields,
+///   instead of skipping them.
+///
+/// On fields:
+/// - `#[json(default)]`: use [`Default::default()`] if the key is missing.
+/// - `#[json(default = "path")]`: call `path()` if the key is missing.
+/// - `#[json(skip)]`: don't read the field, using `Default::default()`.
+/// - `#[json(as_string)]`: read the field from a JSON string using its
+///   [`FromStr`][std::str::FromStr] impl.
+///
+/// `Option` fields can be missing without `#[json(default)]`, and are
+/// `None` if they are. Other missing fields, duplicate keys and values
+/// of the wrong type are errors.
+///
+/// By default, every type parameter that a field uses (outside of a
+/// `PhantomData`) gets a `T: FromJson<'de>` bound, and skipped fields
+/// get a `FieldType: Default` one, which `#[json(bound = "...")]` replaces.
+///
+/// ## Borrowing:
+///
+/// The impl is for `FromJson<'de>` where `'de` outlives the lifetimes
+/// of the type, so `&'a str` and `Cow<'a, str>` fields can borrow from
+/// the JSON instead of allocating:
+///
+/// ```
+/// use std::borrow::Cow;
+/// use json_proc::FromJson;
+///
+/// #[derive(FromJson)]
+/// struct Message<'a> {
+///     id: &'a str,
+///     text: Cow<'a, str>,
+/// }
+///
+/// let message = Message::from_json_str(r#"{"id": "m1", "text": "line\nbreak"}"#)?;
+/// assert_eq!(message.id, "m1");
+/// // Escaped strings can't be borrowed, so `Cow` allocates for them
+/// // (and `&str` fails).
+/// assert!(matches!(message.text, Cow::Owned(_)));
+/// # Ok::<(), json_proc::ParseError>(())
+/// ```
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn from_json_derive(item: TokenStream) -> TokenStream {
+    let result = if let Ok(input) = parse::<ItemStruct>(item.clone()) {
+        ContainerAttrs::from_attrs(&input.attrs).and_then(|container| {
+            util::with_crate(container.krate.clone(), || from_json::derive_struct(input, container))
+        })
+    } else if let Ok(input) = parse::<ItemEnum>(item.clone()) {
+        ContainerAttrs::from_attrs(&input.attrs).and_then(|container| {
+            util::with_crate(container.krate.clone(), || from_json::derive_enum(input, container))
+        })
+    } else {
+        Err(SynError::new(
+            TokenStream2::from(item).span(),
+            "expected struct or enum for deriving FromJson",
+        ))
+    };
+    result.unwrap_or_else(SynError::into_compile_error).into()
+}
+
+/// Generates the expression that serializes one field, where
+/// `access` evaluates to a reference to that field.
+fn field_to_json(access: TokenStream2, attrs: &FieldAttrs) -> TokenStream2 {
+    let krate = util::krate();
+    if attrs.as_string {
+        quote!(#krate::ToJson::to_json_string(&::std::string::ToString::to_string(#access)))
+    } else {
+        quote!(#krate::ToJson::to_json_string(#access))
+    }
+}
+
+/// Generates the expression that serializes the `fields` of a struct
+/// or variant, given as `(access, member, attrs)`, or `None` if it has
+/// no fields. `prefix` is JSON members (each followed by a comma) to
+/// write before the fields of an object, for tags.
+fn fields_to_json(
+    fields: &Fields,
+    members: &[(TokenStream2, Member, &FieldAttrs)],
+    prefix: &str,
+) -> SynResult<Option<TokenStream2>> {
+    let string = util::buf();
+    let serialized = members.iter().filter(|(_, _, attrs)| !attrs.skip);
+    Ok(Some(match fields {
+        _ if fields.is_empty() => return Ok(None),
+        Fields::Unnamed(_) if fields.len() == 1 => {
+            // Generate an impl that uses the first (and only) element in the tuple.
+            let (access, _, attrs) = &members[0];
+            if attrs.skip {
+                return Err(SynError::new(fields.span(), "#[json(skip)] can't be used on the only field"));
             }
-        } else if members.peek().is_some() {
-            // Generate an object-like impl.
+            field_to_json(access.clone(), attrs)
+        }
+        Fields::Unnamed(_) => {
+            // Generate an array-like impl.
+            let values = serialized.map(|(access, _, attrs)| field_to_json(access.clone(), attrs));
             quote! {{
-                // format!("{{{}}}", (vec![#(#pairs_tokens),*] as Vec<String>).join(","))
-                let mut string = String::with_capacity(2);
-                string.push('{');
+                let mut #string = ::std::string::String::with_capacity(2);
+                #string.push('[');
                 #(
-                    string.push('"');
-                    string.push_str(stringify!(#members));
-                    string.push('"');
-                    string.push(':');
-                    string.push_str(&(::json_proc::ToJson::to_json_string(&self.#members)));
-                    string.push(',');
+                    #string.push_str(&(#values));
+                    #string.push(',');
                 )*
-                let _ = string.pop();
-                string.push('}');
-                string
+                if #string.ends_with(',') {
+                    let _ = #string.pop();
+                }
+                #string.push(']');
+                #string
             }}
-        } else {
-            quote!(stringify!(#ident).to_string())
-        };
-        quote! {
-            impl #impl_generics ToJson for #ident #ty_generics #where_clause {
-                fn to_json_string(&self) -> String {
-                    #fn_impl
+        }
+        _ => {
+            // Generate an object-like impl.
+            let (keys, values): (Vec<_>, Vec<_>) = serialized
+                .map(|(access, member, attrs)| {
+                    let key = format!("\"{}\":", util::escape_str(&attrs.key(member)));
+                    (key, field_to_json(access.clone(), attrs))
+                })
+                .unzip();
+            quote! {{
+                let mut #string = ::std::string::String::with_capacity(2);
+                #string.push('{');
+                #string.push_str(#prefix);
+                #(
+                    #string.push_str(#keys);
+                    #string.push_str(&(#values));
+                    #string.push(',');
+                )*
+                if #string.ends_with(',') {
+                    let _ = #string.pop();
                 }
+                #string.push('}');
+                #string
+            }}
+        }
+    }))
+}
+
+fn derive_struct(input: ItemStruct, container: ContainerAttrs) -> SynResult<TokenStream2> {
+    container.check_struct()?;
+    let krate = util::krate();
+    let attrs = FieldAttrs::from_fields(&input.fields)?;
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+    let mut serialized = input.fields.iter().zip(&attrs).collect::<Vec<_>>();
+
+    let fn_impl = if let Some(span) = container.transparent {
+        let mut fields = input
+            .fields
+            .iter()
+            .zip(input.fields.members())
+            .zip(&attrs)
+            .filter(|((field, _), _)| !attr::is_phantom_data(field));
+        match (fields.next(), fields.next()) {
+            (Some(((field, member), attrs)), None) => {
+                serialized = vec![(field, attrs)];
+                field_to_json(quote!(&self.#member), attrs)
+            }
+            _ => {
+                return Err(SynError::new(
+                    span,
+                    "#[json(transparent)] requires exactly one field that is not `PhantomData`",
+                ))
             }
         }
-        .into()
-    } else if let Ok(mut input) = parse::<ItemEnum>(item.clone()) {
-        let ident = input.ident;
-        let variants = input.variants.iter().peekable();
-        input.generics.make_where_clause();
-        let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
-        let mut where_clause = input.generics.where_clause.clone().unwrap();
-        let type_generics = input.generics.type_params().map(|v| v.ident.clone());
-        let path = quote!(::json_proc::ToJson).into();
-        let path = parse_macro_input!(path as syn::Path);
-        for ty in type_generics {
-            let mut bounds = syn::punctuated::Punctuated::new();
-            bounds.push(syn::TypeParamBound::Trait(syn::TraitBound { paren_token: None, modifier: syn::TraitBoundModifier::None, lifetimes: None, path: path.clone() }));
-            where_clause.predicates.push(syn::WherePredicate::Type(syn::PredicateType { lifetimes: None, bounded_ty: syn::Type::Verbatim(quote!(#ty)), colon_token: Token![:](Span::call_site()), bounds }))
-        }
-
-        let mut streams: Vec<TokenStream2> = Vec::new();
-        for var in variants {
-            // Handle like a struct.
-            let varident = &var.ident;
-            let mut members = var.fields.members().peekable();
-            let iter_len = util::iter_len(&members);
-            let this_impl = if iter_len == 0 {
-                quote!(Self::#varident => stringify!(#ident).to_string())
-            } else if members
-                .peek()
-                .is_some_and(|v| matches!(v, Member::Unnamed(_)))
-            {
-                if iter_len == 1 {
-                    // Generate an impl that uses the first (and only) element in the tuple.
-                    quote!(Self::#varident(a) => ::json_proc::ToJson::to_json_string(a))
-                } else {
-                    // Generate an array-like impl.
-                    let members = members.map(|v| match v {
-                        Member::Unnamed(i) => {
-                            Ident::new(format!("arg{}", i.index).as_str(), i.span)
-                        }
-                        Member::Named(_) => unreachable!(),
-                    });
-                    let members2 = members.clone();
-                    quote!(Self::#varident( #(#members2),* ) => {
-                        // format!("[{}]", (vec![#(#elements_tokens),*] as Vec<String>).join(","))
-                        let mut string = String::with_capacity(2);
-                        string.push('[');
-                        #(
-                            string.push_str(&(::json_proc::ToJson::to_json_string(#members)));
-                            string.push(',');
-                        )*
-                        let _ = string.pop();
-                        string.push(']');
-                        string
-                    })
-                }
-            } else {
-                // Generate an object-like impl.
-                let members2 = members.clone();
-                quote!(Self::#varident { #(#members2),* } => {
-                    // format!("{{{}}}", (vec![#(#pairs_tokens),*] as Vec<String>).join(","))
-                    let mut string = String::with_capacity(2);
-                    string.push('{');
-                    #(
-                        string.push('"');
-                        string.push_str(stringify!(#members));
-                        string.push('"');
-                        string.push(':');
-                        string.push_str(&(::json_proc::ToJson::to_json_string(#members)));
-                        string.push(',');
-                    )*
-                    let _ = string.pop();
-                    string.push('}');
-                    string
-                })
-            };
+    } else {
+        let members = input
+            .fields
+            .members()
+            .zip(&attrs)
+            .map(|(member, attrs)| (quote!(&self.#member), member, attrs))
+            .collect::<Vec<_>>();
+        // Structs without fields are written as their name, like unit variants.
+        let name = format!("\"{}\"", ident.unraw());
+        fields_to_json(&input.fields, &members, "")?.unwrap_or_else(|| util::string_from(name))
+    };
+    let where_clause = bound::where_clause(&input.generics, &container, serialized, Derive::ToJson);
+    let typed = container
+        .typed
+        .then(|| typed::struct_items(&input.vis, ident, &input.generics, &input.fields, &attrs));
+    Ok(quote! {
+        impl #impl_generics #krate::ToJson for #ident #ty_generics #where_clause {
+            fn to_json_string(&self) -> ::std::string::String {
+                #fn_impl
+            }
+        }
+
+        #typed
+    })
+}
+
+fn derive_enum(input: ItemEnum, container: ContainerAttrs) -> SynResult<TokenStream2> {
+    let krate = util::krate();
+    let string = util::buf();
+    if let Some(span) = container.transparent {
+        return Err(SynError::new(span, "#[json(transparent)] is not supported on enums"));
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+    let attrs = input
+        .variants
+        .iter()
+        .map(|var| FieldAttrs::from_fields(&var.fields))
+        .collect::<SynResult<Vec<_>>>()?;
+    let fields = input.variants.iter().flat_map(|var| &var.fields);
+    let where_clause = bound::where_clause(
+        &input.generics,
+        &container,
+        fields.zip(attrs.iter().flatten()),
+        Derive::ToJson,
+    );
+    let typed = container.typed.then(|| {
+        let variants = input.variants.iter().zip(&attrs);
+        let variants = variants.map(|(var, attrs)| (&var.ident, &var.fields, attrs.as_slice()));
+        typed::enum_items(&input.vis, ident, &input.generics, variants)
+    });
 
-            streams.push(this_impl)
+    if container.repr.is_some() {
+        if let Some(var) = input.variants.iter().find(|var| !var.fields.is_empty()) {
+            return Err(SynError::new(
+                var.fields.span(),
+                "#[json(repr)] can only be used on enums without data-carrying variants",
+            ));
         }
+        // Casting a fieldless variant gives its discriminant, whether
+        // it was given explicitly (`= 404`) or not.
+        let repr = attr::repr_type(&input.attrs)?.unwrap_or_else(|| Ident::new("isize", Span::call_site()));
+        let variants = input.variants.iter().map(|var| &var.ident);
+        return Ok(quote! {
+            impl #impl_generics #krate::ToJson for #ident #ty_generics #where_clause {
+                fn to_json_string(&self) -> ::std::string::String {
+                    let discriminant = match self {
+                        #(Self::#variants => Self::#variants as #repr),*
+                    };
+                    #krate::ToJson::to_json_string(&discriminant)
+                }
+            }
 
-        quote! {
-            impl #impl_generics ToJson for #ident #ty_generics #where_clause {
-                fn to_json_string(&self) -> String {
-                    match self {
-                        #(#streams),*
-                    }
+            #typed
+        });
+    }
+
+    let tag = container.tag.as_ref().map(|tag| util::escape_str(&tag.value()));
+    let content = container.content.as_ref().map(|content| util::escape_str(&content.value()));
+    let mut streams: Vec<TokenStream2> = Vec::new();
+    for (var, attrs) in input.variants.iter().zip(&attrs) {
+        // Handle like a struct, matching every field by its member
+        // (`Self::Variant { 0: field0, .. }` works for tuple variants too).
+        let varident = &var.ident;
+        let name = util::escape_str(&VariantAttrs::from_attrs(&var.attrs)?.name(var));
+        let members = var
+            .fields
+            .members()
+            .zip(attrs)
+            .enumerate()
+            .map(|(i, (member, attrs))| (Ident::new(&format!("field{i}"), Span::mixed_site()), member, attrs))
+            .map(|(binding, member, attrs)| (quote!(#binding), member, attrs))
+            .collect::<Vec<_>>();
+        let patterns = members.iter().map(|(binding, member, attrs)| match attrs.skip {
+            true => quote!(#member: _),
+            false => quote!(#member: #binding),
+        });
+        let pattern = quote!(Self::#varident { #(#patterns),* });
+
+        let this_impl = match (&tag, &content) {
+            (None, _) => fields_to_json(&var.fields, &members, "")?
+                .unwrap_or_else(|| util::string_from(format!("\"{name}\""))),
+            (Some(tag), None) => {
+                if matches!(var.fields, Fields::Unnamed(_)) && !var.fields.is_empty() {
+                    return Err(SynError::new(
+                        var.fields.span(),
+                        "#[json(tag)] without #[json(content)] can only be used on enums without tuple variants",
+                    ));
+                }
+                if let Some((_, member, _)) = members.iter().find(|(_, member, attrs)| {
+                    !attrs.skip && util::escape_str(&attrs.key(member)) == *tag
+                }) {
+                    return Err(SynError::new(member.span(), "this field has the same key as the tag"));
                 }
+                let prefix = format!("\"{tag}\":\"{name}\",");
+                fields_to_json(&var.fields, &members, &prefix)?
+                    .unwrap_or_else(|| util::string_from(format!("{{\"{tag}\":\"{name}\"}}")))
             }
-        }
-        .into()
-    } else {
-        SynError::new(
-            TokenStream2::from(item).span(),
-            "expected struct or enum for deriving ToJson",
-        )
-        .into_compile_error()
-        .into()
+            (Some(tag), Some(content)) => match fields_to_json(&var.fields, &members, "")? {
+                Some(value) => {
+                    let prefix = format!("{{\"{tag}\":\"{name}\",\"{content}\":");
+                    quote! {{
+                        let mut #string = ::std::string::String::from(#prefix);
+                        #string.push_str(&(#value));
+                        #string.push('}');
+                        #string
+                    }}
+                }
+                None => util::string_from(format!("{{\"{tag}\":\"{name}\"}}")),
+            },
+        };
+
+        streams.push(quote!(#pattern => #this_impl));
     }
+
+    Ok(quote! {
+        impl #impl_generics #krate::ToJson for #ident #ty_generics #where_clause {
+            fn to_json_string(&self) -> ::std::string::String {
+                match self {
+                    #(#streams),*
+                }
+            }
+        }
+
+        #typed
+    })
+}
+
+#[doc(hidden)]
+#[proc_macro]
+/// Private macro for generating impls of FromJson for tuples.
+pub fn from_json_tuple_impl(_: TokenStream) -> TokenStream {
+    from_json::tuple_impls().into()
 }
 
 #[doc(hidden)]
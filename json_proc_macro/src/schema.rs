@@ -0,0 +1,277 @@
+//! Checking `json!(schema = "...", value)` against a JSON Schema.
+//!
+//! Only the parts of the value that are known at compile time are
+//! checked here: literal keys (against `properties`, `required` and
+//! `additionalProperties`, where required keys have to always be
+//! there) and the types (`type`, `enum` and `const`)
+//! of literal values. Other values are wrapped in a
+//! `__private::Checked`, which checks their type and `enum` at runtime
+//! when `debug_assertions` are on.
+//!
+//! `$ref`s are followed if they point into the same file, and anything
+//! else in the schema (like `anyOf` or `pattern`) is ignored.
+
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{Error as SynError, Expr, ExprLit, ExprUnary, Lit, LitStr, Result as SynResult, UnOp};
+
+use crate::{
+    include::{self, Node},
+    util, JsonElement, JsonKey, JsonMember, JsonValue,
+};
+
+impl Node {
+    /// Gets the value of `key` if this is an object.
+    fn get(&self, key: &str) -> Option<&Node> {
+        match self {
+            Node::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Node::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+/// A schema and the file it's from (for resolving `$ref`s).
+struct Schema<'a> {
+    root: &'a Node,
+}
+
+impl<'a> Schema<'a> {
+    /// Follows `$ref`s in `schema`, giving up (with an empty schema) on
+    /// ones that can't be resolved.
+    fn resolve(&self, mut schema: &'a Node) -> Option<&'a Node> {
+        // Stop on cycles instead of following them forever.
+        for _ in 0..32 {
+            let Some(reference) = schema.get("$ref") else {
+                return Some(schema);
+            };
+            let pointer = reference.as_str()?.strip_prefix('#')?;
+            schema = pointer
+                .split('/')
+                .skip(1)
+                .map(|token| token.replace("~1", "/").replace("~0", "~"))
+                .try_fold(self.root, |node, token| match node {
+                    Node::Array(elements) => elements.get(token.parse::<usize>().ok()?),
+                    node => node.get(&token),
+                })?;
+        }
+        None
+    }
+
+    /// Checks `value` against `schema`, where `pointer` is the JSON
+    /// pointer to `value` (used in runtime errors).
+    fn check(&self, value: &mut JsonValue, schema: &'a Node, pointer: &str, errors: &mut Vec<SynError>) {
+        let Some(schema) = self.resolve(schema) else {
+            return;
+        };
+        let types = schema.get("type").map_or_else(Vec::new, |ty| match ty {
+            Node::String(ty) => vec![ty.as_str()],
+            Node::Array(types) => types.iter().filter_map(Node::as_str).collect(),
+            _ => Vec::new(),
+        });
+        let mut values = Vec::new();
+        if let Some(Node::Array(options)) = schema.get("enum") {
+            values.extend(options.iter().map(minify));
+        }
+        if let Some(value) = schema.get("const") {
+            values.push(minify(value));
+        }
+
+        match static_json(value) {
+            Some((ty, json)) => {
+                if !types.is_empty() && !type_matches(&types, ty) {
+                    errors.push(SynError::new(
+                        value.span(),
+                        format!("expected {} here, found {ty}", types.join(" or ")),
+                    ));
+                } else if let Some(json) = json.filter(|_| !values.is_empty()) {
+                    if !values.contains(&json) {
+                        errors.push(SynError::new(
+                            value.span(),
+                            format!("expected one of {} here", values.join(", ")),
+                        ));
+                    }
+                }
+            }
+            None => {
+                if let JsonValue::Expr(expr) = value {
+                    if !types.is_empty() || !values.is_empty() {
+                        *expr = checked(expr, &types, &values, pointer);
+                    }
+                }
+            }
+        }
+
+        match value {
+            JsonValue::Object(obj) => {
+                let properties = schema.get("properties");
+                let additional = schema.get("additionalProperties");
+                // Spreads and computed keys could add anything.
+                let mut open = false;
+                let mut keys = Vec::new();
+                // Keys that might be left out, and where they are.
+                let mut conditional = Vec::new();
+                for member in &mut obj.members {
+                    let pair = match member {
+                        JsonMember::Pair(pair) => pair,
+                        JsonMember::Spread(..) => {
+                            open = true;
+                            continue;
+                        }
+                    };
+                    let JsonKey::Lit(key) = &pair.key else {
+                        open = true;
+                        continue;
+                    };
+                    if pair.always_present() {
+                        keys.push(key.clone());
+                    } else if !matches!(pair.value, JsonValue::Undefined) {
+                        conditional.push((key.clone(), pair.key_span));
+                    }
+                    let schema = match (properties.and_then(|props| props.get(key)), additional) {
+                        (Some(schema), _) => schema,
+                        (None, Some(Node::Bool(false))) => {
+                            errors.push(SynError::new(
+                                pair.key_span,
+                                format!("property `{key}` isn't allowed by the schema"),
+                            ));
+                            continue;
+                        }
+                        (None, Some(schema @ Node::Object(_))) => schema,
+                        (None, _) => continue,
+                    };
+                    if pair.optional {
+                        // The value is an `Option`, so it can't be checked.
+                        continue;
+                    }
+                    let pointer = format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"));
+                    self.check(&mut pair.value, schema, &pointer, errors);
+                }
+                if !open {
+                    if let Some(Node::Array(required)) = schema.get("required") {
+                        let mut missing = Vec::new();
+                        for key in required.iter().filter_map(Node::as_str) {
+                            if keys.iter().any(|k| k == key) {
+                                continue;
+                            }
+                            match conditional.iter().find(|(k, _)| k == key) {
+                                Some((_, span)) => errors.push(SynError::new(
+                                    *span,
+                                    format!("`{key}` is required by the schema, so it can't be optional, guarded or `#[cfg]`'d"),
+                                )),
+                                None => missing.push(format!("`{key}`")),
+                            }
+                        }
+                        if !missing.is_empty() {
+                            errors.push(SynError::new(
+                                obj.span,
+                                format!(
+                                    "missing required {} {}",
+                                    if missing.len() == 1 { "property" } else { "properties" },
+                                    missing.join(", "),
+                                ),
+                            ));
+                        }
+                    }
+                }
+            }
+            JsonValue::Array(arr) => {
+                let Some(items @ Node::Object(_)) = schema.get("items") else {
+                    return;
+                };
+                for (i, (_, element)) in arr.elements.iter_mut().enumerate() {
+                    match element {
+                        JsonElement::Value(value) => self.check(value, items, &format!("{pointer}/{i}"), errors),
+                        JsonElement::For { value, .. } => self.check(value, items, &format!("{pointer}/-"), errors),
+                        JsonElement::Spread(_) => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn minify(node: &Node) -> String {
+    let mut json = String::new();
+    node.minify(&mut json);
+    json
+}
+
+/// Whether a value of type `ty` is allowed by `types`.
+fn type_matches(types: &[&str], ty: &str) -> bool {
+    types.contains(&ty) || (ty == "integer" && types.contains(&"number"))
+}
+
+/// The type of `value` if it's known at compile time, and its JSON if
+/// that is too.
+fn static_json(value: &JsonValue) -> Option<(&'static str, Option<String>)> {
+    let string = |value: &str| Some(format!("\"{}\"", util::escape_str(value)));
+    Some(match value {
+        JsonValue::Object(_) => ("object", None),
+        JsonValue::Array(_) => ("array", None),
+        JsonValue::String(litstr) => ("string", string(&litstr.value())),
+        JsonValue::Format(_) => ("string", None),
+        JsonValue::Bool(b) => ("boolean", Some(b.value.to_string())),
+        JsonValue::Null(_) => ("null", Some(String::from("null"))),
+        JsonValue::Expr(expr) => {
+            let (negative, lit) = match expr {
+                Expr::Lit(ExprLit { lit, .. }) => (false, lit),
+                Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr, .. }) => match &**expr {
+                    Expr::Lit(ExprLit { lit, .. }) => (true, lit),
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            let sign = if negative { "-" } else { "" };
+            match lit {
+                Lit::Int(int) => ("integer", Some(format!("{sign}{}", int.base10_digits()))),
+                Lit::Float(float) => {
+                    // `1.0` is an integer to JSON Schema (see `Checked::check`).
+                    let integer = float.base10_digits().parse::<f64>().is_ok_and(|float| float.fract() == 0.0);
+                    let ty = if integer { "integer" } else { "number" };
+                    (ty, Some(format!("{sign}{}", float.base10_digits())))
+                }
+                Lit::Str(litstr) if !negative => ("string", string(&litstr.value())),
+                Lit::Char(litchar) if !negative => ("string", string(&litchar.value().to_string())),
+                Lit::Bool(b) if !negative => ("boolean", Some(b.value.to_string())),
+                _ => return None,
+            }
+        }
+        JsonValue::Raw(_) | JsonValue::Undefined => return None,
+    })
+}
+
+/// Wraps `expr` so its type and value are checked when it's serialized.
+fn checked(expr: &Expr, types: &[&str], values: &[String], pointer: &str) -> Expr {
+    let krate = util::krate();
+    syn::parse_quote! {
+        #krate::__private::Checked {
+            value: &(#expr),
+            types: &[#(#types),*],
+            values: &[#(#values),*],
+            pointer: #pointer,
+            enabled: ::std::cfg!(debug_assertions),
+        }
+    }
+}
+
+/// Checks `value` against the schema at `path`, returning an item that
+/// makes the crate rebuild when the schema changes.
+pub fn check(value: &mut JsonValue, path: &LitStr) -> SynResult<TokenStream2> {
+    let (full_path, src) = include::read(path)?;
+    let root = include::parse(&src).map_err(|error| SynError::new(path.span(), error.display(&full_path, &src)))?;
+    let mut errors = Vec::new();
+    Schema { root: &root }.check(value, &root, "", &mut errors);
+    let mut errors = errors.into_iter();
+    if let Some(mut error) = errors.next() {
+        errors.for_each(|other| error.combine(other));
+        return Err(error);
+    }
+    Ok(include::track(&full_path))
+}
@@ -0,0 +1,287 @@
+//! `json!(as Type { ... })`, which builds a `Type` out of the object
+//! so the compiler checks its keys and values against the fields.
+//!
+//! Keys can be renamed with `#[json(rename)]`, which the macro can't
+//! see, so `#[derive(ToJson)]` on a type with `#[json(typed)]` also
+//! generates a hidden struct for it (and one for each variant of an
+//! enum) with a field named after each key, and a function that builds
+//! the type after a closure has set every field of that struct:
+//! `__json_typed_Type` next to a struct (and `Type::__json_typed`), and
+//! `Enum::__json_typed_Variant` for variants. So `a::B::C` turns into
+//! `a::B::__json_typed_C` whether `B` is a module or an enum (or an
+//! alias of one), and the compiler works out which.
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+    ext::IdentExt, spanned::Spanned, Attribute, Error as SynError, Expr, Fields, Generics, Ident, Path, PathSegment,
+    Result as SynResult, Visibility,
+};
+
+use crate::{attr::FieldAttrs, JsonElement, JsonKey, JsonMember, JsonObject, JsonValue};
+
+/// Turns `key` into the name of a field, using a raw identifier for
+/// keywords like `type`. Keys that can't be identifiers are turned into
+/// ones made of their bytes.
+fn field_name(key: &str, span: Span) -> Ident {
+    if let Ok(mut ident) = syn::parse_str::<Ident>(key) {
+        ident.set_span(span);
+        return ident;
+    }
+    let raw = !key.is_empty()
+        && !key.starts_with(|ch: char| ch.is_ascii_digit())
+        && key.chars().all(|ch| ch == '_' || ch.is_alphanumeric())
+        && !matches!(key, "_" | "self" | "Self" | "super" | "crate");
+    if raw {
+        Ident::new_raw(key, span)
+    } else {
+        let bytes = key.bytes().map(|byte| format!("{byte:02x}")).collect::<String>();
+        Ident::new(&format!("__json_{bytes}"), span)
+    }
+}
+
+/// The `#[cfg]` attributes in `attrs`, which are the only ones that
+/// are copied from fields and entries.
+fn cfgs(attrs: &[Attribute]) -> Vec<&Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("cfg")).collect()
+}
+
+/// The items `json!(as Type { ... })` uses to build the struct `ty` or
+/// its `variant`, which has `fields` with `attrs`: the hidden struct,
+/// and the signature and body of the function that builds `ty` from it.
+///
+/// The function takes `[(); N]`, where `N` is how many keys the object
+/// has, so that leaving a field out is an error too.
+fn builder(
+    vis: &Visibility,
+    ty: &Ident,
+    variant: Option<&Ident>,
+    generics: &Generics,
+    fields: &Fields,
+    attrs: &[FieldAttrs],
+) -> (TokenStream2, TokenStream2, TokenStream2) {
+    let name = match variant {
+        Some(variant) => format_ident!("__json_fields_{}__{}", ty.unraw(), variant.unraw()),
+        None => format_ident!("__json_fields_{}", ty.unraw()),
+    };
+    let members = fields.members().collect::<Vec<_>>();
+    let names = members
+        .iter()
+        .zip(attrs)
+        .map(|(member, attrs)| field_name(&attrs.key(member), member.span()))
+        .collect::<Vec<_>>();
+    let cfgs = fields.iter().map(|field| cfgs(&field.attrs)).collect::<Vec<_>>();
+    // A variant's fields are as visible as the enum.
+    let field_vis = fields
+        .iter()
+        .map(|field| if variant.is_some() { vis } else { &field.vis })
+        .collect::<Vec<_>>();
+    let types = fields.iter().map(|field| &field.ty);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let path = match variant {
+        Some(variant) => quote!(#ty::#variant),
+        None => quote!(#ty),
+    };
+
+    let definition = quote! {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types, non_snake_case, dead_code)]
+        #vis struct #name #impl_generics #where_clause {
+            #(#(#cfgs)* #field_vis #names: ::std::option::Option<#types>,)*
+            #vis __json_marker: ::std::marker::PhantomData<fn() -> #ty #ty_generics>,
+        }
+    };
+    let signature = quote! {
+        (
+            _keys: [(); { let mut count = 0usize; #(#(#cfgs)* { count += 1; })* count }],
+            build: impl ::std::ops::FnOnce(&mut #name #ty_generics),
+        ) -> #ty #ty_generics
+    };
+    let body = quote! {
+        let mut fields = #name {
+            #(#(#cfgs)* #names: ::std::option::Option::None,)*
+            __json_marker: ::std::marker::PhantomData,
+        };
+        build(&mut fields);
+        // The keys are all different fields, and there are as many as
+        // there are fields, so they're all set.
+        #path { #(#(#cfgs)* #members: ::std::option::Option::unwrap(fields.#names),)* }
+    };
+    (definition, signature, body)
+}
+
+/// Generates the items `json!(as Type { ... })` uses for the struct
+/// `ty` (see the module docs).
+pub fn struct_items(vis: &Visibility, ty: &Ident, generics: &Generics, fields: &Fields, attrs: &[FieldAttrs]) -> TokenStream2 {
+    let (definition, signature, body) = builder(vis, ty, None, generics, fields, attrs);
+    let function = format_ident!("__json_typed_{}", ty.unraw());
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // Lifetimes that are only in the arguments can't be given
+    // explicitly (`a::__json_typed_T::<'a>`) unless they're bounded.
+    let mut early = generics.clone();
+    let lifetimes = generics.lifetimes().map(|param| &param.lifetime);
+    early.make_where_clause().predicates.extend(lifetimes.map(|lifetime| -> syn::WherePredicate {
+        syn::parse_quote!(#lifetime: #lifetime)
+    }));
+    let early_where_clause = &early.where_clause;
+    quote! {
+        #definition
+
+        #[doc(hidden)]
+        #[allow(non_snake_case, dead_code, clippy::all)]
+        #vis fn #function #impl_generics #signature #early_where_clause {
+            #body
+        }
+
+        #[allow(clippy::all)]
+        impl #impl_generics #ty #ty_generics #where_clause {
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            #vis fn __json_typed #signature {
+                #function(_keys, build)
+            }
+        }
+    }
+}
+
+/// Generates the items `json!(as Enum::Variant { ... })` uses for
+/// every variant of the enum `ty` (see the module docs).
+pub fn enum_items<'a>(
+    vis: &Visibility,
+    ty: &Ident,
+    generics: &Generics,
+    variants: impl IntoIterator<Item = (&'a Ident, &'a Fields, &'a [FieldAttrs])>,
+) -> TokenStream2 {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut definitions = Vec::new();
+    let mut functions = Vec::new();
+    for (variant, fields, attrs) in variants {
+        let (definition, signature, body) = builder(vis, ty, Some(variant), generics, fields, attrs);
+        let function = format_ident!("__json_typed_{}", variant.unraw());
+        definitions.push(definition);
+        functions.push(quote! {
+            #[doc(hidden)]
+            #[allow(non_snake_case, dead_code)]
+            #vis fn #function #signature {
+                #body
+            }
+        });
+    }
+    quote! {
+        #(#definitions)*
+
+        #[allow(clippy::all)]
+        impl #impl_generics #ty #ty_generics #where_clause {
+            #(#functions)*
+        }
+    }
+}
+
+/// The path of the function that builds the struct or variant at `ty`
+/// (see the module docs).
+fn builder_path(ty: &Path) -> Path {
+    let mut path = ty.clone();
+    if path.segments.len() == 1 {
+        path.segments.push(PathSegment::from(Ident::new("__json_typed", Span::call_site())));
+    } else {
+        let last = path.segments.last_mut().unwrap();
+        last.ident = format_ident!("__json_typed_{}", last.ident.unraw(), span = last.ident.span());
+    }
+    path
+}
+
+/// Generates the Rust value of `value`, for a field whose type is only
+/// known to the compiler.
+///
+/// Expressions are used as they are, so they have to have the field's
+/// type, but literal strings and arrays are converted with `Into`.
+fn field_value(value: &JsonValue) -> SynResult<TokenStream2> {
+    Ok(match value {
+        JsonValue::Expr(expr) => quote!(#expr),
+        JsonValue::String(litstr) => quote_spanned!(litstr.span()=> ::std::convert::Into::into(#litstr)),
+        JsonValue::Bool(b) => quote!(#b),
+        JsonValue::Null(span) => quote_spanned!(*span=> ::std::option::Option::None),
+        JsonValue::Format(args) => quote!(::std::format!(#args)),
+        JsonValue::Array(arr) => {
+            let elements = arr
+                .elements
+                .iter()
+                .map(|(attrs, element)| match element {
+                    JsonElement::Value(value) if attrs.is_empty() => field_value(value),
+                    _ => Err(SynError::new(
+                        arr.span,
+                        "only plain elements can be used in arrays in `json!(as Type { ... })`",
+                    )),
+                })
+                .collect::<SynResult<Vec<_>>>()?;
+            quote_spanned!(arr.span=> ::std::convert::Into::into([#(#elements),*]))
+        }
+        JsonValue::Object(obj) => {
+            return Err(SynError::new(
+                obj.span,
+                "nested objects can't be checked against a type; use an expression of the field's type",
+            ))
+        }
+        JsonValue::Raw(_) | JsonValue::Undefined => {
+            return Err(SynError::new(value.span(), "`undefined` can't be the value of a field"))
+        }
+    })
+}
+
+/// Generates the expression that builds `ty` out of `obj`.
+///
+/// The values are evaluated first, in order, so that `?` and `return`
+/// in them work like in a struct expression, and then moved into the
+/// fields by the closure.
+pub fn construct(ty: &Path, obj: &JsonObject) -> SynResult<TokenStream2> {
+    let path = builder_path(ty);
+    let fields = Ident::new("fields", Span::mixed_site());
+    let mut lets = Vec::new();
+    let mut sets = Vec::new();
+    let mut counts = Vec::new();
+    for (i, member) in obj.members.iter().enumerate() {
+        let pair = match member {
+            JsonMember::Pair(pair) => pair,
+            JsonMember::Spread(_, expr) => {
+                return Err(SynError::new(expr.span(), "`..` can't be used in `json!(as Type { ... })`"));
+            }
+        };
+        // With `exprs-as-keys`, `name: value` is a variable as a key,
+        // but here it can only be a field.
+        let key = match &pair.key {
+            JsonKey::Lit(key) => key.clone(),
+            JsonKey::Expr(Expr::Path(path)) if path.qself.is_none() && path.path.get_ident().is_some() => {
+                path.path.get_ident().unwrap().unraw().to_string()
+            }
+            JsonKey::Expr(_) => return Err(SynError::new(pair.key_span, "computed keys can't be fields")),
+        };
+        if pair.optional || pair.guard.is_some() || pair.nested {
+            return Err(SynError::new(
+                pair.key_span,
+                "optional, guarded and dotted keys can't be used in `json!(as Type { ... })`",
+            ));
+        }
+        let attrs = cfgs(&pair.attrs);
+        let name = field_name(&key, pair.key_span);
+        let value = field_value(&pair.value)?;
+        let local = Ident::new(&format!("value{i}"), Span::mixed_site().located_at(pair.value.span()));
+        // Two keys for the same field are two items with the same name.
+        let unique = format_ident!("__json_key_{}", name.unraw(), span = pair.key_span);
+        lets.push(quote! {
+            #(#attrs)*
+            #[allow(non_upper_case_globals)]
+            const #unique: () = ();
+            #(#attrs)*
+            let #local = #value;
+        });
+        sets.push(quote!(#(#attrs)* { #fields.#name = ::std::option::Option::Some(#local); }));
+        counts.push(quote!(#(#attrs)* { count += 1; }));
+    }
+    // Without any keys, the closure doesn't use its argument.
+    let fields = if sets.is_empty() { Ident::new("_", Span::mixed_site()) } else { fields };
+    let count = quote_spanned!(obj.span=> [(); { let mut count = 0usize; #(#counts)* count }]);
+    Ok(quote_spanned!(obj.span=> {
+        #(#lets)*
+        #path(#count, |#fields| { #(#sets)* })
+    }))
+}
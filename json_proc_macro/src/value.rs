@@ -0,0 +1,113 @@
+//! `json_value!`, which builds a `json_proc::Value` out of the same
+//! syntax as `json!` instead of a string.
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, Expr, Ident};
+
+use crate::{util, JsonArray, JsonElement, JsonKey, JsonMember, JsonObject, JsonValue};
+
+/// Generates an expression that evaluates to the `Value` of `value`.
+pub fn build(value: &JsonValue) -> TokenStream2 {
+    let krate = util::krate();
+    match value {
+        JsonValue::Object(obj) => object(obj),
+        JsonValue::Array(arr) => array(arr),
+        JsonValue::String(litstr) => {
+            let string = util::string_from(litstr);
+            quote!(#krate::Value::String(#string))
+        }
+        JsonValue::Bool(b) => quote!(#krate::Value::Bool(#b)),
+        JsonValue::Expr(expr) => {
+            #[cfg(not(lints_enabled))]
+            let warning = crate::int_overflow_warning(expr);
+            #[cfg(lints_enabled)]
+            let warning = None::<TokenStream2>;
+            quote_spanned!(expr.span()=> ({
+                #warning
+                #krate::ToJson::to_value(&(#expr))
+            }))
+        }
+        JsonValue::Format(args) => quote!(#krate::Value::String(::std::format!(#args))),
+        JsonValue::Raw(json) => quote! {
+            #krate::ToJson::to_value(&#krate::Json::from_string_unchecked(#json))
+        },
+        JsonValue::Null(_) | JsonValue::Undefined => quote!(#krate::Value::Null),
+    }
+}
+
+/// Generates the key of a pair as a `String`.
+fn key(key: &JsonKey) -> TokenStream2 {
+    match key {
+        JsonKey::Lit(str) => util::string_from(str),
+        JsonKey::Expr(Expr::Macro(mac)) if mac.mac.path.is_ident("fmt") => {
+            let args = &mac.mac.tokens;
+            quote!(::std::format!(#args))
+        }
+        JsonKey::Expr(expr) => quote_spanned!(expr.span()=> ::std::string::ToString::to_string(&(#expr))),
+    }
+}
+
+fn object(obj: &JsonObject) -> TokenStream2 {
+    let krate = util::krate();
+    let map = Ident::new("map", Span::mixed_site());
+    let members = obj.members.iter().map(|member| match member {
+        JsonMember::Pair(pair) => pair.conditional(|value| {
+            let key = key(&pair.key);
+            let value = build(value);
+            if pair.may_be_empty() {
+                let nested = Ident::new("nested", Span::mixed_site());
+                quote! {
+                    let #nested = #value;
+                    if !#nested.as_object().is_some_and(#krate::Map::is_empty) {
+                        #map.insert(#key, #nested);
+                    }
+                }
+            } else {
+                quote!(#map.insert(#key, #value);)
+            }
+        }),
+        JsonMember::Spread(attrs, expr) => {
+            let spread = quote_spanned!(expr.span()=> #krate::ToJson::to_value(&(#expr)));
+            util::with_attrs(attrs, quote!(#krate::__private::spread_value(&mut #map, #spread);))
+        }
+    });
+    quote! {{
+        #[allow(unused_mut)]
+        let mut #map = #krate::Map::new();
+        #(#members)*
+        #krate::Value::Object(#map)
+    }}
+}
+
+fn array(arr: &JsonArray) -> TokenStream2 {
+    let krate = util::krate();
+    let array = Ident::new("array", Span::mixed_site());
+    let elements = arr.elements.iter().map(|(attrs, element)| {
+        let element = match element {
+            JsonElement::Value(value) => {
+                let value = build(value);
+                quote!(#array.push(#value);)
+            }
+            JsonElement::Spread(expr) => {
+                let spread = quote_spanned!(expr.span()=> #krate::ToJson::to_value(&(#expr)));
+                quote!(#krate::__private::extend_value(&mut #array, #spread);)
+            }
+            JsonElement::For { pat, expr, filter, value } => {
+                let value = build(value);
+                let mut body = quote!(#array.push(#value););
+                if let Some(filter) = filter {
+                    body = quote!(if #filter { #body });
+                }
+                quote!(for #pat in #expr { #body })
+            }
+        };
+        util::with_attrs(attrs, element)
+    });
+    quote! {{
+        #[allow(unused_mut)]
+        let mut #array = ::std::vec::Vec::new();
+        #(#elements)*
+        #krate::Value::Array(#array)
+    }}
+}
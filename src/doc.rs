@@ -0,0 +1,401 @@
+//! [`JsonDoc`], which indexes JSON so a few values can be read out
+//! of it without parsing all of it.
+
+use std::{borrow::Cow, collections::HashSet, fmt};
+
+use crate::{parse::is_digits, Deserializer, DuplicateKeys, FromJson, ParseError, ParseOptions, Value, ValueKind};
+
+/// One value in the tape of a [`JsonDoc`].
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    kind: ValueKind,
+    /// The byte offsets of the value in the JSON.
+    start: usize,
+    end: usize,
+    /// The index of the node after this value (and everything in it).
+    next: usize,
+    /// How many elements or members an array or object has.
+    len: usize,
+}
+
+/// JSON that's been checked and indexed, but not parsed.
+///
+/// Parsing a `JsonDoc` builds a tape: one small node for every value
+/// (and key), saying where it is in the JSON and where the value after
+/// it starts, so finding a key or an element skips over everything
+/// else without looking at it again. Strings and numbers aren't decoded
+/// until they're read, and strings are borrowed unless they have escapes.
+/// This makes it much faster than [`from_str`][crate::from_str] when
+/// only a few values of a big document are needed.
+///
+/// The JSON is fully checked while it's indexed, with the same errors
+/// as `from_str`.
+///
+/// ```
+/// # use json_proc::JsonDoc;
+/// let doc = JsonDoc::parse(r#"{ "route": { "hops": ["a", "b", "c", "d"] }, "size": 3 }"#)?;
+/// assert_eq!(doc.get("route").and_then(|route| route.get("hops")?.get(3)?.as_str()).as_deref(), Some("d"));
+/// assert_eq!(doc.get("size").and_then(|size| size.as_u64()), Some(3));
+/// assert!(doc.get("missing").is_none());
+///
+/// // Any value can be parsed as a whole when it's needed.
+/// let hops: Vec<&str> = doc.get("route").unwrap().get("hops").unwrap().parse()?;
+/// assert_eq!(hops, ["a", "b", "c", "d"]);
+/// # Ok::<(), json_proc::ParseError>(())
+/// ```
+#[derive(Clone)]
+pub struct JsonDoc<'a> {
+    json: &'a str,
+    tape: Vec<Node>,
+    options: ParseOptions,
+}
+
+impl<'a> JsonDoc<'a> {
+    /// Checks and indexes `json` with the default [`ParseOptions`].
+    #[inline]
+    pub fn parse(json: &'a str) -> Result<Self, ParseError> {
+        ParseOptions::new().parse_doc(json)
+    }
+
+    pub(crate) fn with_options(json: &'a str, options: ParseOptions) -> Result<Self, ParseError> {
+        let mut de = Deserializer::new(json).with_options(options);
+        let mut tape = Vec::new();
+        index(&mut de, &mut tape)?;
+        de.finish()?;
+        Ok(Self { json, tape, options })
+    }
+
+    /// The JSON this indexes.
+    #[inline]
+    pub fn json(&self) -> &'a str {
+        self.json
+    }
+
+    /// The outermost value.
+    #[inline]
+    pub fn root(&self) -> JsonRef<'_, 'a> {
+        JsonRef { doc: self, index: 0 }
+    }
+
+    /// Gets an element of the outermost array or a member of the
+    /// outermost object, like [`JsonRef::get`].
+    #[inline]
+    pub fn get<I: DocIndex>(&self, index: I) -> Option<JsonRef<'_, 'a>> {
+        self.root().get(index)
+    }
+}
+
+impl fmt::Debug for JsonDoc<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonDoc")
+            .field("json", &self.json)
+            .field("nodes", &self.tape.len())
+            .finish()
+    }
+}
+
+/// Adds the value `de` is at (and everything in it) to `tape`.
+fn index(de: &mut Deserializer<'_>, tape: &mut Vec<Node>) -> Result<(), ParseError> {
+    let kind = de.peek_kind()?;
+    let start = de.offset();
+    let node = tape.len();
+    tape.push(Node {
+        kind,
+        start,
+        end: start,
+        next: 0,
+        len: 0,
+    });
+    let mut len = 0;
+    match kind {
+        ValueKind::Null => de.parse_null()?,
+        ValueKind::Bool => drop(de.parse_bool()?),
+        ValueKind::Number => {
+            // Like `from_str`, reject numbers too big for an `f64`, which
+            // only ones with exponents (or hundreds of digits) can be.
+            let text = de.parse_number()?;
+            if !is_digits(&text) {
+                return Err(de.error_at(start, format!("`{text}` isn't a JSON number")));
+            }
+            if (text.len() > 300 || text.contains(['e', 'E'])) && !text.parse().is_ok_and(f64::is_finite) {
+                return Err(de.error_at(start, "number out of range"));
+            }
+        }
+        ValueKind::String => de.skip_str()?,
+        ValueKind::Array => {
+            de.begin_array()?;
+            while de.next_element()? {
+                index(de, tape)?;
+                len += 1;
+            }
+        }
+        ValueKind::Object => {
+            // Keys are only unescaped here if duplicates have to be found.
+            let find_duplicates = de.duplicate_keys() == DuplicateKeys::Error;
+            let mut keys = HashSet::new();
+            de.begin_object()?;
+            while let Some((key, span)) = de.read_key(find_duplicates)? {
+                if find_duplicates && !keys.insert(key) {
+                    return Err(de.key_error("duplicate key"));
+                }
+                tape.push(Node {
+                    kind: ValueKind::String,
+                    start: span.start,
+                    end: span.end,
+                    next: tape.len() + 1,
+                    len: 0,
+                });
+                index(de, tape)?;
+                len += 1;
+            }
+        }
+    }
+    let next = tape.len();
+    tape[node] = Node {
+        end: de.position(),
+        next,
+        len,
+        ..tape[node]
+    };
+    Ok(())
+}
+
+/// A value in a [`JsonDoc`], which is only parsed when it's read.
+///
+/// `'d` is the lifetime of the document and `'a` is the lifetime of
+/// the JSON it indexes, which strings are borrowed from.
+#[derive(Clone, Copy)]
+pub struct JsonRef<'d, 'a> {
+    doc: &'d JsonDoc<'a>,
+    index: usize,
+}
+
+impl<'d, 'a> JsonRef<'d, 'a> {
+    #[inline]
+    fn node(&self) -> Node {
+        self.doc.tape[self.index]
+    }
+
+    /// A reader for this value on its own (so paths in errors start here).
+    #[inline]
+    fn deserializer(&self) -> Deserializer<'a> {
+        Deserializer::at(self.doc.json, self.node().start).with_options(self.doc.options)
+    }
+
+    /// What type of value this is.
+    #[inline]
+    pub fn kind(&self) -> ValueKind {
+        self.node().kind
+    }
+
+    /// The JSON of this value, as it's written.
+    #[inline]
+    pub fn raw(&self) -> &'a str {
+        let node = self.node();
+        &self.doc.json[node.start..node.end]
+    }
+
+    /// How many elements or members this has, if it's an array or an object
+    /// (and `0` if it isn't either).
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.node().len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets an element of an array with a `usize`, or a member of an
+    /// object with a string, like indexing a [`Value`].
+    ///
+    /// If an object has a key more than once, this gets the first or
+    /// the last value depending on its [`DuplicateKeys`] option.
+    #[inline]
+    pub fn get<I: DocIndex>(&self, index: I) -> Option<JsonRef<'d, 'a>> {
+        index.index_into(*self)
+    }
+
+    /// The elements of an array (or nothing if this isn't an array).
+    pub fn elements(&self) -> impl Iterator<Item = JsonRef<'d, 'a>> + 'd {
+        let doc = self.doc;
+        let mut index = self.index + 1;
+        let end = match self.kind() {
+            ValueKind::Array => self.node().next,
+            _ => index,
+        };
+        std::iter::from_fn(move || {
+            let element = (index < end).then_some(JsonRef { doc, index })?;
+            index = doc.tape[index].next;
+            Some(element)
+        })
+    }
+
+    /// The keys and values of an object (or nothing if this isn't an
+    /// object), including every value of a duplicate key.
+    pub fn members(&self) -> impl Iterator<Item = (Cow<'a, str>, JsonRef<'d, 'a>)> + 'd {
+        self.raw_members().map(|(key, value)| (key.str(), value))
+    }
+
+    /// [`members`][Self::members] without unescaping the keys.
+    fn raw_members(&self) -> impl Iterator<Item = (JsonRef<'d, 'a>, JsonRef<'d, 'a>)> + 'd {
+        let doc = self.doc;
+        let mut index = self.index + 1;
+        let end = match self.kind() {
+            ValueKind::Object => self.node().next,
+            _ => index,
+        };
+        std::iter::from_fn(move || {
+            let key = (index < end).then_some(JsonRef { doc, index })?;
+            let value = JsonRef { doc, index: index + 1 };
+            index = value.node().next;
+            Some((key, value))
+        })
+    }
+
+    /// What's in the quotes of this string or key (or all of it, for a
+    /// JSON5 key without quotes).
+    fn contents(&self) -> &'a str {
+        let raw = self.raw();
+        match raw.starts_with(['"', '\'']) {
+            true => &raw[1..raw.len() - 1],
+            false => raw,
+        }
+    }
+
+    /// The string this is, which is borrowed unless it has escapes.
+    fn str(&self) -> Cow<'a, str> {
+        let contents = self.contents();
+        match contents.contains('\\') {
+            // Keys can be JSON5 identifiers, which this reads too.
+            true => self.deserializer().read_name(true).expect("checked while indexing"),
+            false => Cow::Borrowed(contents),
+        }
+    }
+
+    /// Whether a key is `key`, without unescaping it if it doesn't have to.
+    fn is_key(&self, key: &str) -> bool {
+        let contents = self.contents();
+        match contents.contains('\\') {
+            true => self.str() == key,
+            false => contents == key,
+        }
+    }
+
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        self.kind() == ValueKind::Null
+    }
+
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.kind() {
+            ValueKind::Bool => Some(self.raw() == "true"),
+            _ => None,
+        }
+    }
+
+    /// The string this is, if it's a string, which is borrowed from
+    /// the JSON unless it has escapes.
+    #[inline]
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
+        match self.kind() {
+            ValueKind::String => Some(self.str()),
+            _ => None,
+        }
+    }
+
+    /// The number this is, if it's an integer that fits in an `i64`.
+    #[inline]
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.parse().ok()
+    }
+
+    /// The number this is, if it's an integer that fits in a `u64`.
+    #[inline]
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number()?.parse().ok()
+    }
+
+    /// The number this is, if it's a number that fits in an `f64`.
+    #[inline]
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number()?.parse().ok().filter(|float: &f64| float.is_finite())
+    }
+
+    #[inline]
+    fn as_number(&self) -> Option<Cow<'a, str>> {
+        match self.kind() {
+            // This is only not the raw number for JSON5.
+            ValueKind::Number => Some(self.deserializer().parse_number().expect("checked while indexing")),
+            _ => None,
+        }
+    }
+
+    /// Parses this value (and everything in it) as a `T`.
+    ///
+    /// The paths in errors start from this value, but their offsets,
+    /// lines and columns are in the whole document.
+    pub fn parse<T: FromJson<'a>>(&self) -> Result<T, ParseError> {
+        let mut de = self.deserializer();
+        T::from_json(&mut de)
+    }
+
+    /// Parses this value into a [`Value`].
+    pub fn to_value(&self) -> Value {
+        self.parse().expect("checked while indexing")
+    }
+}
+
+impl fmt::Debug for JsonRef<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("JsonRef").field(&self.raw()).finish()
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for usize {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl<T: Sealed + ?Sized> Sealed for &T {}
+}
+
+/// Something a [`JsonRef`] can be indexed with: a `usize` for arrays,
+/// or a string for objects.
+pub trait DocIndex: private::Sealed {
+    #[doc(hidden)]
+    fn index_into<'d, 'a>(&self, value: JsonRef<'d, 'a>) -> Option<JsonRef<'d, 'a>>;
+}
+
+impl DocIndex for usize {
+    fn index_into<'d, 'a>(&self, value: JsonRef<'d, 'a>) -> Option<JsonRef<'d, 'a>> {
+        value.elements().nth(*self)
+    }
+}
+
+impl DocIndex for str {
+    fn index_into<'d, 'a>(&self, value: JsonRef<'d, 'a>) -> Option<JsonRef<'d, 'a>> {
+        let mut matches = value.raw_members().filter(|(key, _)| key.is_key(self));
+        let found = match value.doc.options.duplicate_keys {
+            DuplicateKeys::Last => matches.last(),
+            DuplicateKeys::First | DuplicateKeys::Error => matches.next(),
+        };
+        found.map(|(_, value)| value)
+    }
+}
+
+impl DocIndex for String {
+    #[inline]
+    fn index_into<'d, 'a>(&self, value: JsonRef<'d, 'a>) -> Option<JsonRef<'d, 'a>> {
+        self.as_str().index_into(value)
+    }
+}
+
+impl<T: DocIndex + ?Sized> DocIndex for &T {
+    #[inline]
+    fn index_into<'d, 'a>(&self, value: JsonRef<'d, 'a>) -> Option<JsonRef<'d, 'a>> {
+        (**self).index_into(value)
+    }
+}
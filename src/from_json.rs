@@ -0,0 +1,367 @@
+//! The [`FromJson`] trait, which is the reading side of [`ToJson`][crate::ToJson].
+//!
+//! The impls here mirror the ones in `json_trait.rs`, so anything
+//! that can be written as JSON can be read back.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    ffi::{CString, OsString},
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+    rc::Rc,
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{
+    parse::{is_digits, Deserializer, ParseError, ParseOptions, ValueKind},
+    DuplicateKeys, Json, Map, Number, Value,
+};
+
+/// Trait that parses a type from JSON.
+///
+/// This trait has a [derive macro], which reads what
+/// `#[derive(ToJson)]` writes.
+///
+/// `'de` is the lifetime of the JSON being parsed, which `&'de str`
+/// and `Cow<'de, str>` borrow from instead of allocating.
+///
+/// ```
+/// # use json_proc::FromJson;
+/// let pairs = Vec::<(String, Option<u8>)>::from_json_str(r#"[["a", 1], ["b", null]]"#)?;
+/// assert_eq!(pairs, [("a".to_owned(), Some(1)), ("b".to_owned(), None)]);
+///
+/// let error = Vec::<u8>::from_json_str("[1, 256]").unwrap_err();
+/// assert_eq!(error.to_string(), "`256` is out of range for u8 at line 1, column 5 in `/1`");
+/// # Ok::<(), json_proc::ParseError>(())
+/// ```
+///
+/// [derive macro]: https://docs.rs/json_proc/latest/json_proc/derive.FromJson.html
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be parsed from JSON",
+    label = "`{Self}` does not implement `FromJson`",
+    note = "use `#[derive(FromJson)]` on `{Self}` or implement `FromJson` for it manually"
+)]
+pub trait FromJson<'de>: Sized {
+    /// Reads one value from `de`.
+    fn from_json(de: &mut Deserializer<'de>) -> Result<Self, ParseError>;
+
+    /// The value of a field that's left out of an object, if this
+    /// type can be left out. Only `Option` can be (as `None`).
+    #[inline]
+    fn missing() -> Option<Self> {
+        None
+    }
+
+    /// Parses `json`, which has to be exactly one value.
+    #[inline]
+    fn from_json_str(json: &'de str) -> Result<Self, ParseError> {
+        ParseOptions::new().parse_as(json)
+    }
+
+    /// Parses `json` (which has to be UTF-8), which has to be exactly one value.
+    #[inline]
+    fn from_json_slice(json: &'de [u8]) -> Result<Self, ParseError> {
+        ParseOptions::new().parse_slice_as(json)
+    }
+}
+
+/// [`FromJson`] for types that don't borrow from the JSON, so they can be
+/// parsed from JSON that doesn't outlive them, like what a
+/// [`JsonReader`][crate::JsonReader] reads.
+///
+/// This is implemented for every type that implements `FromJson<'de>`
+/// for any `'de`.
+pub trait FromJsonOwned: for<'de> FromJson<'de> {}
+
+impl<T: for<'de> FromJson<'de>> FromJsonOwned for T {}
+
+macro_rules! int_from_json_impl {
+    { $($ty:ty)* } => {
+        $(
+            impl FromJson<'_> for $ty {
+                fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+                    let start = de.offset();
+                    let text = de.parse_number()?;
+                    text.parse().map_err(|_| {
+                        if text.contains(['.', 'e', 'E']) || !is_digits(&text) {
+                            de.error_at(start, format!("expected an integer, found `{text}`"))
+                        } else {
+                            de.error_at(start, format!("`{text}` is out of range for {}", stringify!($ty)))
+                        }
+                    })
+                }
+            }
+        )*
+    };
+}
+
+int_from_json_impl! {
+    u8 u16 u32 u64 u128 usize
+    i8 i16 i32 i64 i128 isize
+}
+
+macro_rules! float_from_json_impl {
+    { $($ty:ty)* } => {
+        $(
+            impl FromJson<'_> for $ty {
+                fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+                    let start = de.offset();
+                    let text = de.parse_number()?;
+                    match text.parse::<$ty>() {
+                        // Only `Infinity` and `NaN` can be infinite or NaN, not numbers too big.
+                        Ok(float) if float.is_finite() || !is_digits(&text) => Ok(float),
+                        _ => Err(de.error_at(start, format!("`{text}` is out of range for {}", stringify!($ty)))),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+float_from_json_impl! { f32 f64 }
+
+impl FromJson<'_> for bool {
+    #[inline]
+    fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+        de.parse_bool()
+    }
+}
+
+impl FromJson<'_> for String {
+    #[inline]
+    fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+        de.parse_str().map(Cow::into_owned)
+    }
+}
+
+impl FromJson<'_> for char {
+    fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+        let start = de.offset();
+        let string = de.parse_str()?;
+        let mut chars = string.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Ok(ch),
+            _ => Err(de.error_at(start, "expected a single character")),
+        }
+    }
+}
+
+impl FromJson<'_> for CString {
+    fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+        let start = de.offset();
+        let string = de.parse_str()?;
+        CString::new(string.into_owned()).map_err(|_| de.error_at(start, "string contains a nul character"))
+    }
+}
+
+impl FromJson<'_> for OsString {
+    #[inline]
+    fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+        String::from_json(de).map(OsString::from)
+    }
+}
+
+impl<'a, 'de: 'a> FromJson<'de> for &'a str {
+    /// Borrows the string from the JSON, which fails if it has escapes
+    /// since then it isn't in the JSON as it is.
+    fn from_json(de: &mut Deserializer<'de>) -> Result<Self, ParseError> {
+        let start = de.offset();
+        match de.parse_str()? {
+            Cow::Borrowed(string) => Ok(string),
+            Cow::Owned(_) => Err(de.error_at(start, "can't borrow a string with escapes (use `Cow<str>` instead)")),
+        }
+    }
+}
+
+impl<'a, 'de: 'a> FromJson<'de> for Cow<'a, str> {
+    /// Borrows the string from the JSON, unless it has escapes.
+    #[inline]
+    fn from_json(de: &mut Deserializer<'de>) -> Result<Self, ParseError> {
+        de.parse_str()
+    }
+}
+
+impl<'de, T: FromJson<'de>> FromJson<'de> for Option<T> {
+    fn from_json(de: &mut Deserializer<'de>) -> Result<Self, ParseError> {
+        match de.peek_kind()? {
+            ValueKind::Null => de.parse_null().map(|()| None),
+            _ => T::from_json(de).map(Some),
+        }
+    }
+
+    #[inline]
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl FromJson<'_> for () {
+    #[inline]
+    fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+        de.parse_null()
+    }
+}
+
+impl<T: ?Sized> FromJson<'_> for PhantomData<T> {
+    #[inline]
+    fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+        de.parse_null().map(|()| PhantomData)
+    }
+}
+
+macro_rules! wrapper_from_json_impl {
+    { $($ty:ident)* } => {
+        $(
+            impl<'de, T: FromJson<'de>> FromJson<'de> for $ty<T> {
+                #[inline]
+                fn from_json(de: &mut Deserializer<'de>) -> Result<Self, ParseError> {
+                    T::from_json(de).map($ty::new)
+                }
+            }
+        )*
+    };
+}
+
+wrapper_from_json_impl! { Box Rc Arc }
+
+macro_rules! seq_from_json_impl {
+    { $($ty:ident<T $(, $s:ident)?> where $($bound:path),* $(,)?);* $(;)? } => {
+        $(
+            impl<'de, T: FromJson<'de> $(+ $bound)*, $($s: BuildHasher + Default)?> FromJson<'de> for $ty<T $(, $s)?> {
+                fn from_json(de: &mut Deserializer<'de>) -> Result<Self, ParseError> {
+                    let mut seq = Self::default();
+                    de.begin_array()?;
+                    while de.next_element()? {
+                        seq.extend([T::from_json(de)?]);
+                    }
+                    Ok(seq)
+                }
+            }
+        )*
+    };
+}
+
+seq_from_json_impl! {
+    Vec<T> where;
+    VecDeque<T> where;
+    BTreeSet<T> where Ord;
+    HashSet<T, S> where Eq, Hash;
+}
+
+impl<'de, T: FromJson<'de>, const N: usize> FromJson<'de> for [T; N] {
+    fn from_json(de: &mut Deserializer<'de>) -> Result<Self, ParseError> {
+        let start = de.offset();
+        let elements = Vec::<T>::from_json(de)?;
+        let len = elements.len();
+        elements
+            .try_into()
+            .map_err(|_| de.error_at(start, format!("expected an array of {N} elements, found {len}")))
+    }
+}
+
+macro_rules! map_from_json_impl {
+    { $($ty:ident<K, V $(, $s:ident)?> where $($bound:path),*);* $(;)? } => {
+        $(
+            impl<'de, K, V, $($s)?> FromJson<'de> for $ty<K, V $(, $s)?>
+            where
+                K: FromStr $(+ $bound)*,
+                V: FromJson<'de>,
+                $($s: BuildHasher + Default,)?
+            {
+                fn from_json(de: &mut Deserializer<'de>) -> Result<Self, ParseError> {
+                    let mut map = Self::default();
+                    de.begin_object()?;
+                    while let Some(key) = de.next_key()? {
+                        let key = K::from_str(&key).map_err(|_| de.key_error(format!("invalid key `{key}`")))?;
+                        if map.contains_key(&key) {
+                            match de.duplicate_keys() {
+                                DuplicateKeys::Error => return Err(de.key_error("duplicate key")),
+                                DuplicateKeys::First => {
+                                    de.skip_value()?;
+                                    continue;
+                                }
+                                DuplicateKeys::Last => {}
+                            }
+                        }
+                        map.insert(key, V::from_json(de)?);
+                    }
+                    Ok(map)
+                }
+            }
+        )*
+    };
+}
+
+map_from_json_impl! {
+    BTreeMap<K, V> where Ord;
+    HashMap<K, V, S> where Eq, Hash;
+}
+
+impl FromJson<'_> for Number {
+    fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+        let start = de.offset();
+        let text = de.parse_number()?;
+        if !is_digits(&text) {
+            return Err(de.error_at(start, format!("`{text}` isn't a JSON number")));
+        }
+        if !text.contains(['.', 'e', 'E']) {
+            if let Ok(int) = text.parse::<u64>() {
+                return Ok(Number::from(int));
+            }
+            if let Ok(int) = text.parse::<i64>() {
+                return Ok(Number::from(int));
+            }
+        }
+        // Integers that don't fit in 64 bits become floats too.
+        let float = text.parse::<f64>().expect("checked while parsing");
+        Number::from_f64(float).ok_or_else(|| de.error_at(start, "number out of range"))
+    }
+}
+
+impl FromJson<'_> for Map {
+    fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+        let mut map = Map::new();
+        de.begin_object()?;
+        while let Some(key) = de.next_key()? {
+            if map.contains_key(&key) {
+                match de.duplicate_keys() {
+                    DuplicateKeys::Error => return Err(de.key_error("duplicate key")),
+                    DuplicateKeys::First => {
+                        de.skip_value()?;
+                        continue;
+                    }
+                    DuplicateKeys::Last => {}
+                }
+            }
+            let value = Value::from_json(de)?;
+            map.insert(key.into_owned(), value);
+        }
+        Ok(map)
+    }
+}
+
+impl FromJson<'_> for Value {
+    fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+        match de.peek_kind()? {
+            ValueKind::Null => de.parse_null().map(|()| Value::Null),
+            ValueKind::Bool => de.parse_bool().map(Value::Bool),
+            ValueKind::Number => Number::from_json(de).map(Value::Number),
+            ValueKind::String => String::from_json(de).map(Value::String),
+            ValueKind::Array => Vec::from_json(de).map(Value::Array),
+            ValueKind::Object => Map::from_json(de).map(Value::Object),
+        }
+    }
+}
+
+impl FromJson<'_> for Json {
+    /// Reads any value, keeping it as it's written (or normalizing it, in
+    /// a lenient [`Dialect`][crate::Dialect]).
+    #[inline]
+    fn from_json(de: &mut Deserializer<'_>) -> Result<Self, ParseError> {
+        de.json_value().map(|json| Json::from_string_unchecked(json.into_owned()))
+    }
+}
+
+json_proc_macro::from_json_tuple_impl!();
@@ -0,0 +1,331 @@
+//! The [`Json`] and [`JsonStr`] types, which are strings that are known
+//! to hold JSON.
+
+use std::{
+    borrow::{Borrow, Cow},
+    fmt,
+    ops::Deref,
+};
+
+use crate::{Deserializer, ParseError, ToJson};
+
+/// An owned string of JSON, as made by [`json!`][crate::json] or
+/// [`ToJson::to_json`].
+///
+/// Unlike a `String`, this is written verbatim when it's nested in
+/// another value instead of being serialized as a JSON string:
+/// ```
+/// # use json_proc::json;
+/// let inner = json!({ "a": 1 });
+/// assert_eq!(json!({ "inner": inner }), r#"{"inner":{"a":1}}"#);
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Json(String);
+
+/// A borrowed string of JSON, which is to [`Json`] what `str` is to `String`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct JsonStr(str);
+
+impl Json {
+    /// Checks that `json` is one JSON value (with any whitespace around
+    /// it) and wraps it, keeping it as it's written.
+    ///
+    /// ```
+    /// # use json_proc::Json;
+    /// let json = Json::parse(String::from("[1, 2]"))?;
+    /// assert_eq!(json, "[1, 2]");
+    ///
+    /// let error = Json::parse(String::from("[1, 2")).unwrap_err();
+    /// assert_eq!(error.to_string(), "expected `,` or `]` at line 1, column 6");
+    /// # Ok::<(), json_proc::ParseError>(())
+    /// ```
+    pub fn parse(json: String) -> Result<Self, ParseError> {
+        check(&json)?;
+        Ok(Self(json))
+    }
+
+    /// Wraps `json` without checking that it's JSON.
+    ///
+    /// Nothing unsafe happens if it isn't, but anything it's
+    /// written into won't be valid JSON either.
+    #[inline]
+    pub fn from_string_unchecked(json: String) -> Self {
+        Self(json)
+    }
+
+    #[inline]
+    pub fn as_json_str(&self) -> &JsonStr {
+        JsonStr::from_str_unchecked(&self.0)
+    }
+
+    #[inline]
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    #[inline]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0.into_bytes()
+    }
+}
+
+impl JsonStr {
+    /// Checks that `json` is one JSON value, like [`Json::parse`], and wraps it.
+    pub fn parse(json: &str) -> Result<&Self, ParseError> {
+        check(json)?;
+        Ok(Self::from_str_unchecked(json))
+    }
+
+    /// Wraps `json` without checking that it's JSON (see [`Json::from_string_unchecked`]).
+    #[inline]
+    pub fn from_str_unchecked(json: &str) -> &Self {
+        // SAFETY: `JsonStr` is a `repr(transparent)` wrapper around `str`.
+        unsafe { &*(json as *const str as *const Self) }
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+
+    /// Removes all whitespace outside of strings.
+    ///
+    /// This writes the JSON again the way [`normalize`][crate::normalize]
+    /// does, so strings are escaped like [`json!`][crate::json] escapes
+    /// them. Text that isn't JSON (from [`Json::from_string_unchecked`])
+    /// is returned as it is.
+    #[inline]
+    pub fn minify(&self) -> Json {
+        self.rewrite(None)
+    }
+
+    /// Formats with each member and element on its own line,
+    /// indented by two spaces per level.
+    ///
+    /// Like [`minify`][Self::minify], text that isn't JSON is
+    /// returned as it is.
+    #[inline]
+    pub fn pretty(&self) -> Json {
+        self.rewrite(Some(0))
+    }
+
+    /// Writes the JSON again, with `depth` for [`Deserializer::normalize_value`].
+    fn rewrite(&self, depth: Option<usize>) -> Json {
+        let mut de = Deserializer::new(&self.0);
+        let mut json = String::with_capacity(self.0.len());
+        match de.normalize_value(&mut json, depth).and_then(|()| de.finish()) {
+            Ok(()) => Json(json),
+            Err(_) => self.to_owned(),
+        }
+    }
+}
+
+/// Checks that `json` is one JSON value, with any whitespace around it.
+fn check(json: &str) -> Result<(), ParseError> {
+    let mut de = Deserializer::new(json);
+    de.skip_value()?;
+    de.finish()
+}
+
+impl Deref for Json {
+    type Target = JsonStr;
+
+    #[inline]
+    fn deref(&self) -> &JsonStr {
+        self.as_json_str()
+    }
+}
+
+impl Borrow<JsonStr> for Json {
+    #[inline]
+    fn borrow(&self) -> &JsonStr {
+        self
+    }
+}
+
+impl ToOwned for JsonStr {
+    type Owned = Json;
+
+    #[inline]
+    fn to_owned(&self) -> Json {
+        Json(self.0.to_owned())
+    }
+}
+
+impl AsRef<str> for Json {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for JsonStr {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<[u8]> for Json {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<[u8]> for JsonStr {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl From<Json> for String {
+    #[inline]
+    fn from(json: Json) -> Self {
+        json.0
+    }
+}
+
+impl From<&JsonStr> for Json {
+    #[inline]
+    fn from(json: &JsonStr) -> Self {
+        json.to_owned()
+    }
+}
+
+impl TryFrom<String> for Json {
+    type Error = ParseError;
+
+    #[inline]
+    fn try_from(json: String) -> Result<Self, ParseError> {
+        Self::parse(json)
+    }
+}
+
+impl TryFrom<&str> for Json {
+    type Error = ParseError;
+
+    #[inline]
+    fn try_from(json: &str) -> Result<Self, ParseError> {
+        JsonStr::parse(json).map(ToOwned::to_owned)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for &'a JsonStr {
+    type Error = ParseError;
+
+    #[inline]
+    fn try_from(json: &'a str) -> Result<Self, ParseError> {
+        JsonStr::parse(json)
+    }
+}
+
+impl<'a> From<&'a JsonStr> for Cow<'a, JsonStr> {
+    #[inline]
+    fn from(json: &'a JsonStr) -> Self {
+        Cow::Borrowed(json)
+    }
+}
+
+impl From<Json> for Cow<'_, JsonStr> {
+    #[inline]
+    fn from(json: Json) -> Self {
+        Cow::Owned(json)
+    }
+}
+
+impl fmt::Display for Json {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for JsonStr {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Json {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for JsonStr {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl ToJson for Json {
+    #[inline]
+    fn to_json_string(&self) -> String {
+        self.0.clone()
+    }
+
+    #[inline]
+    fn write_json(&self, json: &mut String) {
+        json.push_str(&self.0)
+    }
+
+    #[inline]
+    fn to_json(&self) -> Json {
+        self.clone()
+    }
+}
+
+impl ToJson for JsonStr {
+    #[inline]
+    fn to_json_string(&self) -> String {
+        self.0.to_owned()
+    }
+
+    #[inline]
+    fn write_json(&self, json: &mut String) {
+        json.push_str(&self.0)
+    }
+}
+
+/// Compares the JSON text of `$lhs` to `$rhs`.
+macro_rules! str_eq_impl {
+    { $($lhs:ty => $rhs:ty),* $(,)? } => {
+        $(
+            impl PartialEq<$rhs> for $lhs {
+                #[inline]
+                fn eq(&self, other: &$rhs) -> bool {
+                    AsRef::<str>::as_ref(self) == AsRef::<str>::as_ref(other)
+                }
+            }
+
+            impl PartialEq<$lhs> for $rhs {
+                #[inline]
+                fn eq(&self, other: &$lhs) -> bool {
+                    AsRef::<str>::as_ref(self) == AsRef::<str>::as_ref(other)
+                }
+            }
+        )*
+    };
+}
+
+str_eq_impl! {
+    Json => str,
+    Json => &str,
+    Json => String,
+    Json => JsonStr,
+    Json => &JsonStr,
+    JsonStr => str,
+    JsonStr => String,
+    &JsonStr => String,
+}
@@ -5,19 +5,56 @@
 //! [ToJson]: crate::ToJson
 //! [`json_proc`]: https://docs.rs/json_proc/latest/json_proc
 
-use std::{collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque}, ffi::{CStr, CString, OsStr, OsString}, hash::BuildHasher};
+use std::{borrow::Cow, collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque}, ffi::{CStr, CString, OsStr, OsString}, hash::BuildHasher, marker::PhantomData, rc::Rc, sync::Arc};
+
+use crate::{Json, Value};
 
 /// Trait that converts a type to a JSON string.
 ///
 /// This trait has a [derive macro].
 ///
 /// [derive macro]: https://docs.rs/json_proc/latest/json_proc/derive.ToJson.html
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be converted to JSON",
+    label = "`{Self}` does not implement `ToJson`",
+    note = "use `#[derive(ToJson)]` on `{Self}` or implement `ToJson` for it manually"
+)]
 pub trait ToJson {
     /// Converts self to a JSON string.
     ///
     /// Implementations of this should not fail.
     #[must_use = "converting to a JSON string is often expensive and is not expected to have side effects"]
     fn to_json_string(&self) -> String;
+
+    /// Pushes the JSON of self onto `json`.
+    ///
+    /// The default implementation pushes [`to_json_string`][Self::to_json_string],
+    /// but this can be overridden to write straight into `json` instead.
+    #[inline]
+    fn write_json(&self, json: &mut String) {
+        json.push_str(&self.to_json_string())
+    }
+
+    /// Converts self to [`Json`], which unlike a `String` is
+    /// written verbatim when it's nested in another value.
+    #[must_use = "converting to JSON is often expensive and is not expected to have side effects"]
+    #[inline]
+    fn to_json(&self) -> Json {
+        Json::from_string_unchecked(self.to_json_string())
+    }
+
+    /// Converts self to a [`Value`].
+    ///
+    /// The default implementation parses [`to_json_string`][Self::to_json_string].
+    ///
+    /// # Panics
+    /// The default implementation panics if `to_json_string` isn't valid JSON
+    /// (or is nested more than 128 arrays and objects deep).
+    #[must_use = "converting to a JSON value is often expensive and is not expected to have side effects"]
+    fn to_value(&self) -> Value {
+        let json = self.to_json_string();
+        crate::from_str(&json).unwrap_or_else(|error| panic!("`ToJson` produced invalid JSON ({error}): {json}"))
+    }
 }
 
 macro_rules! display_json_impl {
@@ -28,6 +65,11 @@ macro_rules! display_json_impl {
                 fn to_json_string(&self) -> String {
                     self.to_string()
                 }
+
+                #[inline]
+                fn to_value(&self) -> Value {
+                    Value::from(*self)
+                }
             }
         )*
     };
@@ -36,22 +78,73 @@ macro_rules! display_json_impl {
 display_json_impl! {
     u8 u16 u32 u64 u128 usize,
     i8 i16 i32 i64 i128 isize,
-    f32 f64,
     bool,
 }
 
-/// FIXME: this doesn't correctly handle newlines
-/// and other escaped characters like `"`
+macro_rules! float_json_impl {
+    { $($ty:ty)* } => {
+        $(
+            impl ToJson for $ty {
+                /// Writes the number, or `null` if it's infinite or NaN,
+                /// which JSON can't represent (like [`Value::from`]).
+                #[inline]
+                fn to_json_string(&self) -> String {
+                    if self.is_finite() {
+                        self.to_string()
+                    } else {
+                        String::from("null")
+                    }
+                }
+
+                #[inline]
+                fn to_value(&self) -> Value {
+                    Value::from(*self)
+                }
+            }
+        )*
+    };
+}
+
+float_json_impl! { f32 f64 }
+
+/// Pushes `str` onto `json` as a quoted JSON string, escaping
+/// quotes, backslashes and control characters as needed.
+pub(crate) fn push_escaped(json: &mut String, str: &str) {
+    json.push('"');
+    push_escaped_contents(json, str);
+    json.push('"');
+}
+
+/// [`push_escaped`] without the quotes.
+pub(crate) fn push_escaped_contents(json: &mut String, str: &str) {
+    for ch in str.chars() {
+        match ch {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            '\u{08}' => json.push_str("\\b"),
+            '\u{0C}' => json.push_str("\\f"),
+            ch if (ch as u32) < 0x20 => {
+                json.push_str(&format!("\\u{:04x}", ch as u32))
+            }
+            ch => json.push(ch),
+        }
+    }
+}
+
 impl ToJson for str {
     fn to_json_string(&self) -> String {
         let mut json = String::with_capacity(self.len() + 2);
-
-        json.push('"');
-        json.push_str(self);
-        json.push('"');
-
+        push_escaped(&mut json, self);
         json
     }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        Value::from(self)
+    }
 }
 
 impl ToJson for String {
@@ -59,17 +152,24 @@ impl ToJson for String {
     fn to_json_string(&self) -> String {
         self.as_str().to_json_string()
     }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        Value::String(self.clone())
+    }
 }
 
 impl ToJson for char {
     fn to_json_string(&self) -> String {
         let mut json = String::with_capacity(3);
-        json.push('"');
-        json.push(*self);
-        json.push('"');
-
+        push_escaped(&mut json, self.encode_utf8(&mut [0; 4]));
         json
     }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        Value::from(*self)
+    }
 }
 
 impl ToJson for CStr {
@@ -101,6 +201,19 @@ impl<T: ToJson> ToJson for Option<T> {
             None => String::from("null"),
         }
     }
+
+    #[inline]
+    fn write_json(&self, json: &mut String) {
+        match self {
+            Some(t) => t.write_json(json),
+            None => json.push_str("null"),
+        }
+    }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        self.as_ref().map_or(Value::Null, ToJson::to_value)
+    }
 }
 
 impl ToJson for () {
@@ -108,17 +221,90 @@ impl ToJson for () {
     fn to_json_string(&self) -> String {
         String::from("null")
     }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        Value::Null
+    }
 }
 
-impl<T: ToJson> ToJson for &[T] {
+impl<T: ?Sized> ToJson for PhantomData<T> {
+    #[inline]
     fn to_json_string(&self) -> String {
-        format!(
-            "[{}]",
-            self.iter()
-                .map(ToJson::to_json_string)
-                .collect::<Vec<String>>()
-                .join(",")
-        )
+        String::from("null")
+    }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        Value::Null
+    }
+}
+
+macro_rules! deref_json_impl {
+    { $($ty:ty $(,)?)* } => {
+        $(
+            impl<T: ToJson + ?Sized> ToJson for $ty {
+                #[inline]
+                fn to_json_string(&self) -> String {
+                    (**self).to_json_string()
+                }
+
+                #[inline]
+                fn write_json(&self, json: &mut String) {
+                    (**self).write_json(json)
+                }
+
+                #[inline]
+                fn to_value(&self) -> Value {
+                    (**self).to_value()
+                }
+            }
+        )*
+    };
+}
+
+deref_json_impl! {
+    &T, &mut T,
+    Box<T>, Rc<T>, Arc<T>,
+}
+
+impl<T: ToJson + ToOwned + ?Sized> ToJson for Cow<'_, T> {
+    #[inline]
+    fn to_json_string(&self) -> String {
+        (**self).to_json_string()
+    }
+
+    #[inline]
+    fn write_json(&self, json: &mut String) {
+        (**self).write_json(json)
+    }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        (**self).to_value()
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json_string(&self) -> String {
+        let mut json = String::with_capacity(2);
+        self.write_json(&mut json);
+        json
+    }
+
+    fn write_json(&self, json: &mut String) {
+        json.push('[');
+        for (i, item) in self.iter().enumerate() {
+            if i != 0 {
+                json.push(',');
+            }
+            item.write_json(json);
+        }
+        json.push(']');
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Array(self.iter().map(ToJson::to_value).collect())
     }
 }
 impl<T: ToJson, const N: usize> ToJson for [T; N] {
@@ -126,12 +312,30 @@ impl<T: ToJson, const N: usize> ToJson for [T; N] {
     fn to_json_string(&self) -> String {
         self.as_slice().to_json_string()
     }
+    #[inline]
+    fn write_json(&self, json: &mut String) {
+        self.as_slice().write_json(json)
+    }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        self.as_slice().to_value()
+    }
 }
 impl<T: ToJson> ToJson for Vec<T> {
     #[inline]
     fn to_json_string(&self) -> String {
         self.as_slice().to_json_string()
     }
+    #[inline]
+    fn write_json(&self, json: &mut String) {
+        self.as_slice().write_json(json)
+    }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        self.as_slice().to_value()
+    }
 }
 
 impl<K, V> ToJson for BTreeMap<K, V>
@@ -143,7 +347,7 @@ where
         format!(
             "{{{}}}",
             self.iter()
-                .map(|(key, value)| format!(r#""{}":{}"#, key.to_string(), value.to_json_string()))
+                .map(|(key, value)| format!("{}:{}", key.to_string().to_json_string(), value.to_json_string()))
                 .collect::<Vec<String>>()
                 .join(",")
         )
@@ -160,7 +364,7 @@ where
         format!(
             "{{{}}}",
             self.iter()
-                .map(|(key, value)| format!(r#""{}":{}"#, key.to_string(), value.to_json_string()))
+                .map(|(key, value)| format!("{}:{}", key.to_string().to_json_string(), value.to_json_string()))
                 .collect::<Vec<String>>()
                 .join(",")
         )
@@ -3,7 +3,25 @@
 
 extern crate json_proc_macro;
 
+mod doc;
+mod from_json;
+mod json;
 mod json_trait;
+mod parse;
+mod reader;
+mod value;
+#[doc(hidden)]
+#[path = "private.rs"]
+pub mod __private;
 
-pub use json_proc_macro::{json, ToJson};
+pub use doc::{DocIndex, JsonDoc, JsonRef};
+pub use from_json::{FromJson, FromJsonOwned};
+pub use json_proc_macro::{include_json, json, json_lazy, json_value, json_write, FromJson, ToJson};
+pub use json::{Json, JsonStr};
 pub use json_trait::ToJson;
+pub use parse::{
+    from_slice, from_str, normalize, Checkpoint, Deserializer, Dialect, DuplicateKeys, ParseError, ParseOptions,
+    ValueKind,
+};
+pub use reader::{ArrayElements, Event, JsonReader};
+pub use value::{Map, Number, Value, ValueIndex};
@@ -0,0 +1,1240 @@
+//! Parsing JSON, into a [`Value`] or anything else that implements
+//! [`FromJson`].
+
+use std::{borrow::Cow, error::Error, fmt, io, ops::Range};
+
+use crate::{json_trait::push_escaped, FromJson, JsonDoc, Value};
+
+/// What to do when an object has the same key more than once.
+///
+/// RFC 8259 only says that the names in an object *should* be unique,
+/// so parsers disagree on what to do with duplicates. This applies to
+/// [`Value`]s and maps; derived structs always fail on a duplicate field.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Fail with an error at the second key.
+    Error,
+    /// Keep the value of the first key.
+    First,
+    /// Keep the value of the last key (in the position of the first),
+    /// like later keys do in [`json!`][crate::json].
+    #[default]
+    Last,
+}
+
+/// Which relatives of JSON to accept, on top of JSON itself.
+///
+/// Everything in a dialect is also in the ones after it. A
+/// [`JsonReader`][crate::JsonReader] reads JSON and JSONC, but not JSON5.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// Only JSON, as RFC 8259 has it.
+    #[default]
+    Json,
+    /// JSON with comments (`//` to the end of the line and `/* */`) and
+    /// trailing commas in arrays and objects, like in VS Code's settings.
+    Jsonc,
+    /// JSON5 (<https://json5.org>): JSONC, plus keys that are identifiers
+    /// (in any script) without quotes, strings in single quotes, JSON5's
+    /// other escapes (like `\x41`, `\v` and a `\` before a line break),
+    /// hexadecimal numbers, `+` before numbers, decimal points with no
+    /// digits on one side, `Infinity` and `NaN` (which only floats can
+    /// hold), and Unicode whitespace (like no-break spaces and BOMs).
+    Json5,
+}
+
+/// Options for parsing JSON.
+///
+/// ```
+/// # use json_proc::{Dialect, DuplicateKeys, ParseOptions};
+/// let options = ParseOptions::new().duplicate_keys(DuplicateKeys::Error);
+/// let error = options.parse(r#"{ "a": 1, "a": 2 }"#).unwrap_err();
+/// assert_eq!(error.to_string(), "duplicate key at line 1, column 11 in `/a`");
+///
+/// let error = ParseOptions::new().max_depth(2).parse("[[[1]]]").unwrap_err();
+/// assert_eq!(error.to_string(), "nesting too deep at line 1, column 3 in `/0/0`");
+///
+/// let config = "{\n  // Where to listen.\n  \"port\": 8080,\n}";
+/// assert!(json_proc::from_str(config).is_err());
+/// let value = ParseOptions::new().dialect(Dialect::Jsonc).parse(config)?;
+/// assert_eq!(value["port"], 8080);
+/// # Ok::<(), json_proc::ParseError>(())
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub(crate) max_depth: usize,
+    pub(crate) duplicate_keys: DuplicateKeys,
+    pub(crate) dialect: Dialect,
+}
+
+impl Default for ParseOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParseOptions {
+    /// The default options: a maximum depth of 128, the last value
+    /// of duplicate keys, and only JSON.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            max_depth: 128,
+            duplicate_keys: DuplicateKeys::Last,
+            dialect: Dialect::Json,
+        }
+    }
+
+    /// Sets how many arrays and objects can be nested in each other,
+    /// which stops deeply nested input from overflowing the stack.
+    #[inline]
+    pub const fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets what to do when an object has the same key more than once.
+    #[inline]
+    pub const fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    /// Sets which relatives of JSON to accept as well.
+    #[inline]
+    pub const fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Parses `json`, which has to be exactly one JSON value
+    /// (with any whitespace around it).
+    #[inline]
+    pub fn parse(&self, json: &str) -> Result<Value, ParseError> {
+        self.parse_as(json)
+    }
+
+    /// Like [`parse`][Self::parse], but for bytes that are only
+    /// valid JSON if they're UTF-8.
+    #[inline]
+    pub fn parse_slice(&self, json: &[u8]) -> Result<Value, ParseError> {
+        self.parse_slice_as(json)
+    }
+
+    /// Checks and indexes `json` into a [`JsonDoc`], which parses
+    /// its values only when they're read.
+    #[inline]
+    pub fn parse_doc<'a>(&self, json: &'a str) -> Result<JsonDoc<'a>, ParseError> {
+        JsonDoc::with_options(json, *self)
+    }
+
+    /// Turns `json` (in the options' dialect) into JSON with no
+    /// whitespace, like [`normalize`] does for any dialect.
+    pub fn normalize(&self, json: &str) -> Result<String, ParseError> {
+        let mut de = Deserializer::new(json).with_options(*self);
+        let mut normalized = String::with_capacity(json.len());
+        de.normalize_value(&mut normalized, None)?;
+        de.finish()?;
+        Ok(normalized)
+    }
+
+    /// Parses `json` into a `T`.
+    #[inline]
+    pub fn parse_as<'de, T: FromJson<'de>>(&self, json: &'de str) -> Result<T, ParseError> {
+        self.parse_slice_as(json.as_bytes())
+    }
+
+    /// Parses `json` into a `T`, like [`parse_slice`][Self::parse_slice].
+    pub fn parse_slice_as<'de, T: FromJson<'de>>(&self, json: &'de [u8]) -> Result<T, ParseError> {
+        let mut de = Deserializer::from_slice(json).with_options(*self);
+        let value = T::from_json(&mut de)?;
+        de.finish()?;
+        Ok(value)
+    }
+}
+
+/// Parses `json` with the default [`ParseOptions`].
+///
+/// ```
+/// let value = json_proc::from_str(r#"{ "users": [{ "name": "ann" }] }"#)?;
+/// assert_eq!(value["users"][0]["name"], "ann");
+///
+/// let error = json_proc::from_str("{\n  \"users\": [{ \"name\": ann }]\n}").unwrap_err();
+/// assert_eq!((error.line(), error.column(), error.offset()), (2, 23, 24));
+/// assert_eq!(error.path(), "/users/0/name");
+/// assert_eq!(error.to_string(), "expected a value at line 2, column 23 in `/users/0/name`");
+/// # Ok::<(), json_proc::ParseError>(())
+/// ```
+#[inline]
+pub fn from_str(json: &str) -> Result<Value, ParseError> {
+    ParseOptions::new().parse(json)
+}
+
+/// Turns `json`, which can be in any [`Dialect`], into JSON with no
+/// whitespace.
+///
+/// Comments and trailing commas are dropped, keys are quoted, strings
+/// are put in double quotes and numbers are written in decimal, while
+/// everything else (like the order of keys, and duplicate keys) is
+/// kept as it is. `Infinity` and `NaN` are errors, since JSON can't
+/// have them.
+///
+/// ```
+/// let config = "{\n  name: 'probe', // for now\n  mask: 0xFF,\n  ratio: .5,\n  tags: ['a', 'b',],\n}";
+/// assert_eq!(json_proc::normalize(config)?, r#"{"name":"probe","mask":255,"ratio":0.5,"tags":["a","b"]}"#);
+///
+/// let error = json_proc::normalize("[1, -Infinity]").unwrap_err();
+/// assert_eq!(error.to_string(), "`-Infinity` isn't a JSON number at line 1, column 5 in `/1`");
+/// # Ok::<(), json_proc::ParseError>(())
+/// ```
+#[inline]
+pub fn normalize(json: &str) -> Result<String, ParseError> {
+    ParseOptions::new().dialect(Dialect::Json5).normalize(json)
+}
+
+/// Parses `json` (which has to be UTF-8) with the default [`ParseOptions`].
+#[inline]
+pub fn from_slice(json: &[u8]) -> Result<Value, ParseError> {
+    ParseOptions::new().parse_slice(json)
+}
+
+/// Why some JSON couldn't be parsed (or didn't fit the type it
+/// was parsed into), and where.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    message: Cow<'static, str>,
+    offset: usize,
+    line: usize,
+    column: usize,
+    path: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(message: impl Into<Cow<'static, str>>, offset: usize, line: usize, column: usize, path: String) -> Self {
+        Self {
+            message: message.into(),
+            offset,
+            line,
+            column,
+            path,
+        }
+    }
+
+    /// Moves an error in a value that was parsed on its own to where the
+    /// value is in a bigger document: at `offset`, `line` and `column`,
+    /// with the JSON pointer `path`.
+    pub(crate) fn relocate(mut self, offset: usize, line: usize, column: usize, path: &str) -> Self {
+        if self.line == 1 {
+            self.column += column - 1;
+        }
+        self.line += line - 1;
+        self.offset += offset;
+        self.path.insert_str(0, path);
+        self
+    }
+
+    /// What went wrong, like `expected a value`.
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The byte offset of the error from the start of the input.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The line of the error, starting at 1.
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column of the error in characters, starting at 1.
+    #[inline]
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// The JSON pointer (RFC 6901) to the value the error is in,
+    /// which is empty for the outermost value.
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.line, self.column)?;
+        if !self.path.is_empty() {
+            write!(f, " in `{}`", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<ParseError> for io::Error {
+    /// Wraps the error in an [`io::Error`] of kind [`InvalidData`][io::ErrorKind::InvalidData],
+    /// which [`io::Error::get_ref`] and `downcast_ref` get back.
+    #[inline]
+    fn from(error: ParseError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// The type of a JSON value, as told by its first character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueKind {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl fmt::Display for ValueKind {
+    /// Writes the kind like `a string`, for error messages.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ValueKind::Null => "null",
+            ValueKind::Bool => "a boolean",
+            ValueKind::Number => "a number",
+            ValueKind::String => "a string",
+            ValueKind::Array => "an array",
+            ValueKind::Object => "an object",
+        })
+    }
+}
+
+/// An array or object the deserializer is in, for the paths of errors.
+#[derive(Clone, Copy)]
+enum Frame {
+    Array {
+        /// The index of the element being read, if one is.
+        index: Option<usize>,
+        len: usize,
+    },
+    Object {
+        /// The offset of the key of the value being read, if one is.
+        key: Option<usize>,
+        first: bool,
+    },
+}
+
+/// A key of an object, and where it is (with its quotes).
+pub(crate) type KeySpan<'de> = (Cow<'de, str>, Range<usize>);
+
+/// A position to go back to with [`Deserializer::restore`].
+#[derive(Clone, Copy, Debug)]
+pub struct Checkpoint {
+    pos: usize,
+    depth: usize,
+}
+
+/// Reads JSON one value at a time, for [`FromJson`] impls.
+///
+/// Every method skips the whitespace before what it reads, and fails
+/// with a [`ParseError`] that knows where it happened. Arrays are read
+/// by calling [`begin_array`][Self::begin_array] and then
+/// [`next_element`][Self::next_element] until it returns `false`,
+/// reading an element each time it returns `true`. Objects are read
+/// the same way with [`begin_object`][Self::begin_object] and
+/// [`next_key`][Self::next_key].
+///
+/// ```
+/// # use json_proc::{Deserializer, FromJson, ParseError};
+/// struct Point(i32, i32);
+///
+/// impl<'de> FromJson<'de> for Point {
+///     fn from_json(de: &mut Deserializer<'de>) -> Result<Self, ParseError> {
+///         let (mut x, mut y) = (0, 0);
+///         de.begin_object()?;
+///         while let Some(key) = de.next_key()? {
+///             match &*key {
+///                 "x" => x = i32::from_json(de)?,
+///                 "y" => y = i32::from_json(de)?,
+///                 _ => de.skip_value()?,
+///             }
+///         }
+///         Ok(Point(x, y))
+///     }
+/// }
+///
+/// let Point(x, y) = Point::from_json_str(r#"{ "y": 2, "x": 1, "z": [] }"#)?;
+/// assert_eq!((x, y), (1, 2));
+/// # Ok::<(), ParseError>(())
+/// ```
+pub struct Deserializer<'de> {
+    json: &'de [u8],
+    pos: usize,
+    frames: Vec<Frame>,
+    options: ParseOptions,
+    /// How many [`attempt`][Self::attempt]s are being made.
+    attempts: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    #[inline]
+    pub fn new(json: &'de str) -> Self {
+        Self::from_slice(json.as_bytes())
+    }
+
+    /// Reads bytes, which are only valid JSON if they're UTF-8.
+    #[inline]
+    pub fn from_slice(json: &'de [u8]) -> Self {
+        Self {
+            json,
+            pos: 0,
+            frames: Vec::new(),
+            options: ParseOptions::new(),
+            attempts: 0,
+        }
+    }
+
+    #[inline]
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// What to do when an object has the same key more than once.
+    #[inline]
+    pub fn duplicate_keys(&self) -> DuplicateKeys {
+        self.options.duplicate_keys
+    }
+
+    /// Checks that there's nothing but whitespace left.
+    pub fn finish(mut self) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.pos < self.json.len() {
+            return Err(self.error("trailing characters after the value"));
+        }
+        Ok(())
+    }
+
+    /// The byte offset of the next thing to be read, after whitespace.
+    #[inline]
+    pub fn offset(&mut self) -> usize {
+        self.skip_whitespace();
+        self.pos
+    }
+
+    /// Starts reading `json` at `pos`, as if it were a value on its own
+    /// (so paths in errors start from there, but offsets don't).
+    #[inline]
+    pub(crate) fn at(json: &'de str, pos: usize) -> Self {
+        Self {
+            pos,
+            ..Self::new(json)
+        }
+    }
+
+    /// The byte offset of the next thing to be read, before whitespace.
+    #[inline]
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Saves the position, so a value can be read more than once
+    /// (like when trying each variant of an enum).
+    #[inline]
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            pos: self.pos,
+            depth: self.frames.len(),
+        }
+    }
+
+    /// Goes back to a [`checkpoint`][Self::checkpoint] made in the
+    /// same array or object, between the same two values.
+    #[inline]
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.pos = checkpoint.pos;
+        self.frames.truncate(checkpoint.depth);
+    }
+
+    /// Reads something with `read`, going back to where it started if
+    /// that fails, like when trying each variant of an enum.
+    ///
+    /// Errors made while trying aren't given a line, column or path,
+    /// since they're thrown away, and finding those means going over the
+    /// JSON again.
+    pub fn attempt<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T, ParseError>) -> Option<T> {
+        let checkpoint = self.checkpoint();
+        self.attempts += 1;
+        let result = read(self);
+        self.attempts -= 1;
+        match result {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.restore(checkpoint);
+                None
+            }
+        }
+    }
+
+    /// Makes an error at the current position.
+    #[inline]
+    pub fn error(&self, message: impl Into<Cow<'static, str>>) -> ParseError {
+        self.error_at(self.pos, message)
+    }
+
+    /// Makes an error at `offset`, with the path of the current position.
+    pub fn error_at(&self, offset: usize, message: impl Into<Cow<'static, str>>) -> ParseError {
+        if self.attempts > 0 {
+            return ParseError::new(message, offset, 0, 0, String::new());
+        }
+        let before = &self.json[..offset.min(self.json.len())];
+        let line_start = before.iter().rposition(|&byte| byte == b'\n').map_or(0, |i| i + 1);
+        ParseError::new(
+            message,
+            offset,
+            before.iter().filter(|&&byte| byte == b'\n').count() + 1,
+            // Count characters, not bytes (continuation bytes don't start one).
+            before[line_start..].iter().filter(|&&byte| byte & 0xC0 != 0x80).count() + 1,
+            self.path(),
+        )
+    }
+
+    /// Makes an error at the start of the key of the value being read
+    /// (or at the current position if there isn't one).
+    pub fn key_error(&self, message: impl Into<Cow<'static, str>>) -> ParseError {
+        match self.frames.last() {
+            Some(Frame::Object { key: Some(key), .. }) => self.error_at(*key, message),
+            _ => self.error(message),
+        }
+    }
+
+    /// Makes an error saying that the next value isn't what was
+    /// `expected`, like `expected a string, found an array`.
+    pub fn invalid_type(&mut self, expected: &str) -> ParseError {
+        match self.peek_kind() {
+            Ok(kind) => self.error(format!("expected {expected}, found {kind}")),
+            Err(error) => error,
+        }
+    }
+
+    /// The JSON pointer to the current position.
+    fn path(&self) -> String {
+        let mut path = String::new();
+        for frame in &self.frames {
+            let segment = match *frame {
+                Frame::Array { index: Some(index), .. } => Cow::Owned(index.to_string()),
+                Frame::Object { key: Some(key), .. } => {
+                    let mut de = Deserializer::from_slice(self.json).with_options(self.options);
+                    de.pos = key;
+                    // The key was read before, so this can't fail.
+                    de.read_name(true).unwrap_or_default()
+                }
+                _ => break,
+            };
+            path.push('/');
+            path.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+        }
+        path
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.json.get(self.pos).copied()
+    }
+
+    /// Whether comments and trailing commas are allowed.
+    #[inline]
+    fn jsonc(&self) -> bool {
+        self.options.dialect != Dialect::Json
+    }
+
+    #[inline]
+    fn json5(&self) -> bool {
+        self.options.dialect == Dialect::Json5
+    }
+
+    /// The character at the current position, if it's valid UTF-8.
+    fn peek_char(&self) -> Option<char> {
+        let bytes = &self.json[self.pos..self.json.len().min(self.pos + 4)];
+        let valid = std::str::from_utf8(bytes).unwrap_or_else(|error| {
+            std::str::from_utf8(&bytes[..error.valid_up_to()]).expect("valid up to here")
+        });
+        valid.chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            loop {
+                match self.peek() {
+                    Some(b' ' | b'\t' | b'\n' | b'\r') => self.pos += 1,
+                    Some(0x0B | 0x0C) if self.json5() => self.pos += 1,
+                    Some(0x80..) if self.json5() => match self.peek_char() {
+                        Some(ch) if is_json5_space(ch) => self.pos += ch.len_utf8(),
+                        _ => break,
+                    },
+                    _ => break,
+                }
+            }
+            if !self.jsonc() || self.peek() != Some(b'/') {
+                return;
+            }
+            let rest = &self.json[self.pos..];
+            self.pos += match rest.get(1) {
+                Some(b'/') => rest.iter().position(|&byte| byte == b'\n').unwrap_or(rest.len()),
+                // A comment that isn't closed runs to the end, where a
+                // value or `]` or `}` is missing.
+                Some(b'*') => rest[2..].windows(2).position(|pair| pair == b"*/").map_or(rest.len(), |end| end + 4),
+                _ => return,
+            };
+        }
+    }
+
+    /// Finds out what the next value is, without reading it.
+    pub fn peek_kind(&mut self) -> Result<ValueKind, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => Ok(ValueKind::Object),
+            Some(b'[') => Ok(ValueKind::Array),
+            Some(b'"') => Ok(ValueKind::String),
+            Some(b'\'') if self.json5() => Ok(ValueKind::String),
+            Some(b'-' | b'0'..=b'9') => Ok(ValueKind::Number),
+            Some(b'+' | b'.' | b'I' | b'N') if self.json5() => Ok(ValueKind::Number),
+            Some(b't' | b'f') => Ok(ValueKind::Bool),
+            Some(b'n') => Ok(ValueKind::Null),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &[u8]) -> Result<(), ParseError> {
+        if self.json[self.pos..].starts_with(keyword) {
+            self.pos += keyword.len();
+            Ok(())
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    pub fn parse_null(&mut self) -> Result<(), ParseError> {
+        match self.peek_kind()? {
+            ValueKind::Null => self.keyword(b"null"),
+            _ => Err(self.invalid_type("null")),
+        }
+    }
+
+    pub fn parse_bool(&mut self) -> Result<bool, ParseError> {
+        match (self.peek_kind()?, self.peek()) {
+            (ValueKind::Bool, Some(b't')) => self.keyword(b"true").map(|()| true),
+            (ValueKind::Bool, _) => self.keyword(b"false").map(|()| false),
+            _ => Err(self.invalid_type("a boolean")),
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    /// Reads a number, returning it as it's written (which
+    /// [`str::parse`] can always turn into an `f64`).
+    ///
+    /// JSON5 numbers are returned in JSON's syntax (so hexadecimal ones
+    /// are turned into decimal), except for `Infinity` and `NaN`.
+    pub fn parse_number(&mut self) -> Result<Cow<'de, str>, ParseError> {
+        if self.peek_kind()? != ValueKind::Number {
+            return Err(self.invalid_type("a number"));
+        }
+        if self.json5() {
+            return self.parse_json5_number();
+        }
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => {
+                self.digits();
+            }
+            _ => return Err(self.error("expected a digit")),
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if self.digits() == 0 {
+                return Err(self.error("expected a digit after `.`"));
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                return Err(self.error("expected a digit in the exponent"));
+            }
+        }
+        Ok(Cow::Borrowed(self.ascii(start..self.pos)))
+    }
+
+    fn parse_json5_number(&mut self) -> Result<Cow<'de, str>, ParseError> {
+        let start = self.pos;
+        let sign = match self.peek() {
+            Some(b'-') => "-",
+            Some(b'+') => "",
+            _ => return self.parse_unsigned_json5_number(start, ""),
+        };
+        self.pos += 1;
+        self.parse_unsigned_json5_number(start, sign)
+    }
+
+    /// Reads a JSON5 number after its sign, which starts at `start`.
+    fn parse_unsigned_json5_number(&mut self, start: usize, sign: &str) -> Result<Cow<'de, str>, ParseError> {
+        let rest = &self.json[self.pos..];
+        // Rust's float parsing reads these as they are.
+        if let Some(word) = [&b"Infinity"[..], b"NaN"].into_iter().find(|&word| rest.starts_with(word)) {
+            self.pos += word.len();
+            return Ok(Cow::Borrowed(self.ascii(start..self.pos)));
+        }
+        if rest.starts_with(b"0x") || rest.starts_with(b"0X") {
+            self.pos += 2;
+            let digits = self.pos;
+            while self.peek().is_some_and(|byte| byte.is_ascii_hexdigit()) {
+                self.pos += 1;
+            }
+            if self.pos == digits {
+                return Err(self.error("expected a hex digit"));
+            }
+            let value = u128::from_str_radix(self.ascii(digits..self.pos), 16)
+                .map_err(|_| self.error_at(start, "number out of range"))?;
+            return Ok(Cow::Owned(format!("{sign}{value}")));
+        }
+
+        let int = self.pos;
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => {
+                self.digits();
+            }
+            _ => {}
+        }
+        let int = int..self.pos;
+        let mut fraction = None;
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            let start = self.pos;
+            self.digits();
+            fraction = Some(start..self.pos);
+        }
+        if int.is_empty() && fraction.as_ref().is_none_or(Range::is_empty) {
+            return Err(self.error("expected a digit"));
+        }
+        let exponent = self.pos;
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                return Err(self.error("expected a digit in the exponent"));
+            }
+        }
+
+        // Only a `+` and a decimal point without digits on one side aren't JSON.
+        let plus = self.json[start] == b'+';
+        if !plus && !int.is_empty() && fraction.as_ref().is_none_or(|fraction| !fraction.is_empty()) {
+            return Ok(Cow::Borrowed(self.ascii(start..self.pos)));
+        }
+        let mut number = String::from(sign);
+        number.push_str(if int.is_empty() { "0" } else { self.ascii(int) });
+        if let Some(fraction) = fraction.filter(|fraction| !fraction.is_empty()) {
+            number.push('.');
+            number.push_str(self.ascii(fraction));
+        }
+        number.push_str(self.ascii(exponent..self.pos));
+        Ok(Cow::Owned(number))
+    }
+
+    /// Part of the JSON that's known to be ASCII.
+    #[inline]
+    fn ascii(&self, range: Range<usize>) -> &'de str {
+        std::str::from_utf8(&self.json[range]).expect("checked to be ASCII")
+    }
+
+    /// Reads a string, which is borrowed from the input unless
+    /// it has escapes in it.
+    #[inline]
+    pub fn parse_str(&mut self) -> Result<Cow<'de, str>, ParseError> {
+        self.read_str(true)
+    }
+
+    /// Reads a string (checking that it's valid) and ignores it,
+    /// which never allocates.
+    #[inline]
+    pub(crate) fn skip_str(&mut self) -> Result<(), ParseError> {
+        self.read_str(false).map(drop)
+    }
+
+    /// Reads a string, unescaping it only if it's going to be `kept`.
+    fn read_str(&mut self, kept: bool) -> Result<Cow<'de, str>, ParseError> {
+        if self.peek_kind()? != ValueKind::String {
+            return Err(self.invalid_type("a string"));
+        }
+        // JSON5 strings can be in single quotes, and can have control
+        // characters other than line breaks in them.
+        let quote = self.json[self.pos];
+        let json5 = self.json5();
+        self.pos += 1;
+        let mut string = Cow::Borrowed("");
+        loop {
+            let start = self.pos;
+            while let Some(byte) = self.peek() {
+                if byte == quote || byte == b'\\' || (byte < 0x20 && !(json5 && byte != b'\n' && byte != b'\r')) {
+                    break;
+                }
+                self.pos += 1;
+            }
+            // A run can't end in the middle of a valid character,
+            // since it ends at an ASCII byte (or the end).
+            let run = std::str::from_utf8(&self.json[start..self.pos])
+                .map_err(|error| self.error_at(start + error.valid_up_to(), "invalid UTF-8 in string"))?;
+            if string.is_empty() {
+                string = Cow::Borrowed(run);
+            } else if kept {
+                string.to_mut().push_str(run);
+            }
+            match self.peek() {
+                Some(byte) if byte == quote => {
+                    self.pos += 1;
+                    return Ok(string);
+                }
+                Some(b'\\') if !kept => {
+                    self.pos += 1;
+                    self.escape()?;
+                }
+                Some(b'\\') => {
+                    if let Cow::Borrowed(borrowed) = string {
+                        // Unescaping never makes the rest of the string longer,
+                        // so this is the only allocation.
+                        let mut owned = String::with_capacity(borrowed.len() + self.escaped_len(quote));
+                        owned.push_str(borrowed);
+                        string = Cow::Owned(owned);
+                    }
+                    self.pos += 1;
+                    if let Some(ch) = self.escape()? {
+                        string.to_mut().push(ch);
+                    }
+                }
+                Some(_) => return Err(self.error("control character in string")),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    /// The length of the rest of the string being read, up to its closing quote.
+    fn escaped_len(&self, quote: u8) -> usize {
+        let mut end = self.pos;
+        while let Some(&byte) = self.json.get(end) {
+            match byte {
+                _ if byte == quote => break,
+                b'\\' => end += 2,
+                _ => end += 1,
+            }
+        }
+        end.min(self.json.len()) - self.pos
+    }
+
+    /// Parses what's after the `\` of an escape, which is `None` for
+    /// JSON5's escaped line breaks.
+    fn escape(&mut self) -> Result<Option<char>, ParseError> {
+        let json5 = self.json5();
+        let ch = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{08}',
+            Some(b'f') => '\u{0C}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+                let high = self.hex(4)?;
+                if !(0xD800..0xE000).contains(&high) {
+                    return Ok(Some(char::from_u32(high).expect("not a surrogate")));
+                }
+                if high >= 0xDC00 || !self.json[self.pos..].starts_with(b"\\u") {
+                    return Err(self.error("unpaired surrogate in unicode escape"));
+                }
+                self.pos += 2;
+                let low = self.hex(4)?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Err(self.error("unpaired surrogate in unicode escape"));
+                }
+                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                return Ok(Some(char::from_u32(code).expect("surrogate pairs are valid")));
+            }
+            Some(b'v') if json5 => '\u{0B}',
+            Some(b'0') if json5 && !self.json.get(self.pos + 1).is_some_and(u8::is_ascii_digit) => '\0',
+            Some(b'x') if json5 => {
+                self.pos += 1;
+                let code = self.hex(2)?;
+                return Ok(Some(char::from_u32(code).expect("bytes are characters")));
+            }
+            Some(b'\r') if json5 => {
+                self.pos += 1;
+                if self.peek() == Some(b'\n') {
+                    self.pos += 1;
+                }
+                return Ok(None);
+            }
+            Some(b'\n') if json5 => {
+                self.pos += 1;
+                return Ok(None);
+            }
+            // Any other character (but a digit) is itself, except the
+            // line and paragraph separators, which are line breaks too.
+            Some(byte) if json5 && !byte.is_ascii_digit() => match self.peek_char() {
+                Some(ch @ ('\u{2028}' | '\u{2029}')) => {
+                    self.pos += ch.len_utf8();
+                    return Ok(None);
+                }
+                Some(ch) => {
+                    self.pos += ch.len_utf8();
+                    return Ok(Some(ch));
+                }
+                None => return Err(self.error("invalid UTF-8 in string")),
+            },
+            _ => return Err(self.error("invalid escape")),
+        };
+        self.pos += 1;
+        Ok(Some(ch))
+    }
+
+    /// Reads `len` hex digits.
+    fn hex(&mut self, len: usize) -> Result<u32, ParseError> {
+        let digits = self
+            .json
+            .get(self.pos..self.pos + len)
+            .filter(|digits| digits.iter().all(u8::is_ascii_hexdigit))
+            .ok_or_else(|| self.error(format!("expected {len} hex digits")))?;
+        self.pos += len;
+        let digits = std::str::from_utf8(digits).expect("hex digits are ASCII");
+        Ok(u32::from_str_radix(digits, 16).expect("checked above"))
+    }
+
+    /// Reads the `[` or `{` of an array or object.
+    fn begin(&mut self, kind: ValueKind, frame: Frame) -> Result<(), ParseError> {
+        if self.peek_kind()? != kind {
+            return Err(self.invalid_type(&kind.to_string()));
+        }
+        if self.frames.len() == self.options.max_depth {
+            return Err(self.error("nesting too deep"));
+        }
+        self.pos += 1;
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    /// Reads the `[` of an array.
+    #[inline]
+    pub fn begin_array(&mut self) -> Result<(), ParseError> {
+        self.begin(ValueKind::Array, Frame::Array { index: None, len: 0 })
+    }
+
+    /// Moves on to the next element of the array, returning `false`
+    /// (after reading the `]`) if there isn't one.
+    ///
+    /// # Panics
+    /// Panics if the last array or object begun was an object.
+    pub fn next_element(&mut self) -> Result<bool, ParseError> {
+        let Some(Frame::Array { index, len }) = self.frames.last_mut() else {
+            panic!("`next_element` called outside of an array");
+        };
+        *index = None;
+        let next = *len;
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b']') => {
+                self.pos += 1;
+                self.frames.pop();
+                return Ok(false);
+            }
+            Some(b',') if next != 0 => {
+                self.pos += 1;
+                if self.jsonc() {
+                    let comma = self.pos;
+                    self.skip_whitespace();
+                    if self.peek() == Some(b']') {
+                        self.pos += 1;
+                        self.frames.pop();
+                        return Ok(false);
+                    }
+                    self.pos = comma;
+                }
+            }
+            _ if next == 0 => {}
+            _ => return Err(self.error("expected `,` or `]`")),
+        }
+        if let Some(Frame::Array { index, len }) = self.frames.last_mut() {
+            *index = Some(next);
+            *len += 1;
+        }
+        Ok(true)
+    }
+
+    /// Reads the `{` of an object.
+    #[inline]
+    pub fn begin_object(&mut self) -> Result<(), ParseError> {
+        self.begin(ValueKind::Object, Frame::Object { key: None, first: true })
+    }
+
+    /// Reads the next key of the object (and the `:` after it),
+    /// returning `None` (after reading the `}`) if there isn't one.
+    ///
+    /// # Panics
+    /// Panics if the last array or object begun was an array.
+    #[inline]
+    pub fn next_key(&mut self) -> Result<Option<Cow<'de, str>>, ParseError> {
+        Ok(self.read_key(true)?.map(|(key, _)| key))
+    }
+
+    /// [`next_key`][Self::next_key], also returning where the key is
+    /// (with its quotes), and only unescaping it if it's going to be `kept`.
+    pub(crate) fn read_key(&mut self, kept: bool) -> Result<Option<KeySpan<'de>>, ParseError> {
+        let Some(Frame::Object { key, first }) = self.frames.last_mut() else {
+            panic!("`next_key` called outside of an object");
+        };
+        *key = None;
+        let first = std::mem::replace(first, false);
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'}') => {
+                self.pos += 1;
+                self.frames.pop();
+                return Ok(None);
+            }
+            Some(b',') if !first => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.jsonc() && self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    self.frames.pop();
+                    return Ok(None);
+                }
+            }
+            _ if first => {}
+            _ => return Err(self.error("expected `,` or `}`")),
+        }
+        let start = self.pos;
+        let name = self.read_name(kept)?;
+        let end = self.pos;
+        self.skip_whitespace();
+        if self.peek() != Some(b':') {
+            return Err(self.error("expected `:`"));
+        }
+        self.pos += 1;
+        if let Some(Frame::Object { key, .. }) = self.frames.last_mut() {
+            *key = Some(start);
+        }
+        Ok(Some((name, start..end)))
+    }
+
+    /// Reads the name in a key, which can be a JSON5 identifier.
+    pub(crate) fn read_name(&mut self, kept: bool) -> Result<Cow<'de, str>, ParseError> {
+        match self.peek() {
+            Some(b'"') => self.read_str(kept),
+            Some(b'\'') if self.json5() => self.read_str(kept),
+            Some(_) if self.json5() => self.read_identifier(kept),
+            _ => Err(self.error("expected a string key")),
+        }
+    }
+
+    /// Reads a JSON5 identifier, which can have `\uXXXX` escapes in it.
+    fn read_identifier(&mut self, kept: bool) -> Result<Cow<'de, str>, ParseError> {
+        let start = self.pos;
+        // Only used once there's an escape.
+        let mut name = String::new();
+        let mut escaped = false;
+        loop {
+            let at = self.pos;
+            let allowed = |ch: char| is_identifier_part(ch) && (at != start || is_identifier_start(ch));
+            let ch = if self.peek() == Some(b'\\') {
+                if self.json.get(at + 1) != Some(&b'u') {
+                    return Err(self.error("invalid escape"));
+                }
+                self.pos += 2;
+                let ch = char::from_u32(self.hex(4)?)
+                    .filter(|&ch| allowed(ch))
+                    .ok_or_else(|| self.error_at(at, "invalid character in key"))?;
+                if !escaped && kept {
+                    name.push_str(std::str::from_utf8(&self.json[start..at]).expect("read as characters"));
+                }
+                escaped = true;
+                ch
+            } else {
+                match self.peek_char() {
+                    Some(ch) if allowed(ch) => {
+                        self.pos += ch.len_utf8();
+                        ch
+                    }
+                    _ => break,
+                }
+            };
+            if escaped && kept {
+                name.push(ch);
+            }
+        }
+        if self.pos == start {
+            return Err(self.error("expected a string key"));
+        }
+        Ok(match escaped {
+            true => Cow::Owned(name),
+            false => Cow::Borrowed(std::str::from_utf8(&self.json[start..self.pos]).expect("read as characters")),
+        })
+    }
+
+    /// Reads a value of any kind, returning it as it's written.
+    pub fn raw_value(&mut self) -> Result<&'de str, ParseError> {
+        let start = self.offset();
+        self.skip_value()?;
+        Ok(std::str::from_utf8(&self.json[start..self.pos]).expect("checked while skipping"))
+    }
+
+    /// Reads a value of any kind as JSON: as it's written in
+    /// [`Dialect::Json`], and normalized like [`normalize`] does in the
+    /// others, so that what's read is always JSON.
+    pub(crate) fn json_value(&mut self) -> Result<Cow<'de, str>, ParseError> {
+        if self.jsonc() {
+            let mut json = String::new();
+            self.normalize_value(&mut json, None)?;
+            Ok(Cow::Owned(json))
+        } else {
+            self.raw_value().map(Cow::Borrowed)
+        }
+    }
+
+    /// Reads a value of any kind and writes it as JSON, with no whitespace,
+    /// for [`normalize`]. With a `depth`, members and elements are put on
+    /// their own lines instead, indented by two spaces per level past it.
+    pub(crate) fn normalize_value(&mut self, json: &mut String, depth: Option<usize>) -> Result<(), ParseError> {
+        let newline = |json: &mut String, depth: usize| {
+            json.push('\n');
+            json.extend(std::iter::repeat_n("  ", depth));
+        };
+        let inner = depth.map(|depth| depth + 1);
+        match self.peek_kind()? {
+            ValueKind::Null => {
+                self.parse_null()?;
+                json.push_str("null");
+            }
+            ValueKind::Bool => json.push_str(if self.parse_bool()? { "true" } else { "false" }),
+            ValueKind::Number => {
+                let start = self.pos;
+                let number = self.parse_number()?;
+                if !is_digits(&number) {
+                    return Err(self.error_at(start, format!("`{number}` isn't a JSON number")));
+                }
+                json.push_str(&number);
+            }
+            ValueKind::String => push_escaped(json, &self.parse_str()?),
+            ValueKind::Array => {
+                self.begin_array()?;
+                json.push('[');
+                let mut first = true;
+                while self.next_element()? {
+                    if !std::mem::replace(&mut first, false) {
+                        json.push(',');
+                    }
+                    if let Some(inner) = inner {
+                        newline(json, inner);
+                    }
+                    self.normalize_value(json, inner)?;
+                }
+                // Empty arrays and objects stay on one line.
+                if let (Some(depth), false) = (depth, first) {
+                    newline(json, depth);
+                }
+                json.push(']');
+            }
+            ValueKind::Object => {
+                self.begin_object()?;
+                json.push('{');
+                let mut first = true;
+                while let Some(key) = self.next_key()? {
+                    if !std::mem::replace(&mut first, false) {
+                        json.push(',');
+                    }
+                    if let Some(inner) = inner {
+                        newline(json, inner);
+                    }
+                    push_escaped(json, &key);
+                    json.push_str(if depth.is_some() { ": " } else { ":" });
+                    self.normalize_value(json, inner)?;
+                }
+                if let (Some(depth), false) = (depth, first) {
+                    newline(json, depth);
+                }
+                json.push('}');
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a value of any kind (checking that it's valid) and ignores it.
+    pub fn skip_value(&mut self) -> Result<(), ParseError> {
+        match self.peek_kind()? {
+            ValueKind::Null => self.parse_null(),
+            ValueKind::Bool => self.parse_bool().map(drop),
+            ValueKind::Number => self.parse_number().map(drop),
+            ValueKind::String => self.skip_str(),
+            ValueKind::Array => {
+                self.begin_array()?;
+                while self.next_element()? {
+                    self.skip_value()?;
+                }
+                Ok(())
+            }
+            ValueKind::Object => {
+                self.begin_object()?;
+                while self.read_key(false)?.is_some() {
+                    self.skip_value()?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Whether `ch` is whitespace in JSON5 (on top of JSON's), which is
+/// what JavaScript counts as whitespace and line breaks.
+fn is_json5_space(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{A0}' | '\u{FEFF}' | '\u{1680}' | '\u{2000}'..='\u{200A}' | '\u{2028}' | '\u{2029}' | '\u{202F}'
+            | '\u{205F}' | '\u{3000}'
+    )
+}
+
+/// Whether a JSON5 identifier can start with `ch`: a letter, `$` or `_`.
+fn is_identifier_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '$' || ch == '_'
+}
+
+/// Whether `ch` can be in a JSON5 identifier after its first character,
+/// which adds digits, combining marks and zero-width joiners.
+fn is_identifier_part(ch: char) -> bool {
+    is_identifier_start(ch) || ch.is_alphanumeric() || matches!(ch, '\u{300}'..='\u{36F}' | '\u{200C}' | '\u{200D}')
+}
+
+/// Whether a number from [`Deserializer::parse_number`] is written in
+/// digits, rather than being JSON5's `Infinity` or `NaN`.
+#[inline]
+pub(crate) fn is_digits(number: &str) -> bool {
+    number.ends_with(|ch: char| ch.is_ascii_digit())
+}
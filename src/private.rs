@@ -0,0 +1,327 @@
+//! Helpers for code generated by the macros.
+//!
+//! Nothing in here is public API, and it can change at any time.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{self, Display},
+    io, mem,
+    str::FromStr,
+};
+
+use crate::{
+    json_trait::{push_escaped, push_escaped_contents},
+    Deserializer, Map, ParseError, ToJson, Value,
+};
+
+/// An object being built by `json!` when it has members that are
+/// only known at runtime (like `..spread`), where later members
+/// replace earlier ones with the same key.
+#[derive(Default)]
+pub struct Object {
+    /// Pairs of keys (unescaped) and values (already JSON).
+    members: Vec<(String, String)>,
+    /// The position of each key in `members`.
+    index: HashMap<String, usize>,
+}
+
+impl Object {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds (or replaces) a member.
+    pub fn insert(&mut self, key: String, value: String) {
+        match self.index.get(&key) {
+            Some(&i) => self.members[i].1 = value,
+            None => {
+                self.index.insert(key.clone(), self.members.len());
+                self.members.push((key, value));
+            }
+        }
+    }
+
+    /// Adds every member of the JSON object `json`.
+    ///
+    /// # Panics
+    /// Panics if `json` isn't a JSON object.
+    pub fn spread(&mut self, json: &str) {
+        let mut de = Deserializer::new(json);
+        let spread = (|| {
+            de.begin_object()?;
+            while let Some(key) = de.next_key()? {
+                let value = de.raw_value()?;
+                self.insert(key.into_owned(), value.to_owned());
+            }
+            de.finish()
+        })();
+        if spread.is_err() {
+            panic!("spread value in json! object is not a JSON object: {json}");
+        }
+    }
+
+    pub fn finish(self) -> String {
+        let mut string = String::with_capacity(2);
+        string.push('{');
+        for (key, value) in &self.members {
+            push_escaped(&mut string, key);
+            string.push(':');
+            string.push_str(value);
+            string.push(',');
+        }
+        if string.ends_with(',') {
+            let _ = string.pop();
+        }
+        string.push('}');
+        string
+    }
+}
+
+/// Quotes and escapes a computed `[key]` of an object.
+pub fn quote_key<K: Display + ?Sized>(key: &K) -> String {
+    let key = key.to_string();
+    let mut json = String::with_capacity(key.len() + 2);
+    push_escaped(&mut json, &key);
+    json
+}
+
+/// Escapes everything written to it onto the inner `String`,
+/// for writing `fmt!("...")` strings without formatting them first.
+pub struct Escaper<'a>(pub &'a mut String);
+
+impl fmt::Write for Escaper<'_> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        push_escaped_contents(self.0, s);
+        Ok(())
+    }
+}
+
+/// The value `json_lazy!` evaluates to, which runs `push` (the code
+/// `json!` would have run) every time it's serialized.
+pub struct Lazy<F>(F);
+
+/// Makes a [`Lazy`], making sure `push` can be called more than once.
+#[inline]
+pub fn lazy<F: Fn(String) -> String>(push: F) -> Lazy<F> {
+    Lazy(push)
+}
+
+impl<F: Fn(String) -> String> ToJson for Lazy<F> {
+    #[inline]
+    fn to_json_string(&self) -> String {
+        (self.0)(String::with_capacity(2))
+    }
+
+    #[inline]
+    fn write_json(&self, json: &mut String) {
+        *json = (self.0)(mem::take(json));
+    }
+}
+
+impl<F: Fn(String) -> String> Display for Lazy<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_json_string())
+    }
+}
+
+impl<F> fmt::Debug for Lazy<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Lazy(..)")
+    }
+}
+
+/// A value in `json!(schema = "...", ...)` that's checked against the
+/// schema when it's serialized, since its type isn't known at compile time.
+pub struct Checked<'a, T: ?Sized> {
+    pub value: &'a T,
+    /// The JSON Schema types the value can have (any if empty).
+    pub types: &'static [&'static str],
+    /// The (minified) JSON the value can be (any if empty).
+    pub values: &'static [&'static str],
+    /// The JSON pointer to the value, for the panic message.
+    pub pointer: &'static str,
+    /// Whether `debug_assertions` are on where the value is.
+    pub enabled: bool,
+}
+
+impl<T: ToJson + ?Sized> Checked<'_, T> {
+    fn check(&self, json: &str) {
+        let json = json.trim();
+        let ty = match json.as_bytes().first() {
+            Some(b'{') => "object",
+            Some(b'[') => "array",
+            Some(b'"') => "string",
+            Some(b't' | b'f') => "boolean",
+            Some(b'n') => "null",
+            // Like in JSON Schema, a number with no fractional part is an
+            // integer, whatever Rust type it was written from (so `1.0_f64`,
+            // written as `1`, is one too).
+            _ => match json.parse::<f64>() {
+                Ok(number) if number.fract() == 0.0 => "integer",
+                _ => "number",
+            },
+        };
+        let type_matches = self.types.is_empty()
+            || self.types.contains(&ty)
+            || (ty == "integer" && self.types.contains(&"number"));
+        assert!(
+            type_matches,
+            "json! value at `{}` doesn't match the schema: expected {}, found {ty} `{json}`",
+            self.pointer,
+            self.types.join(" or "),
+        );
+        assert!(
+            self.values.is_empty() || self.values.contains(&json),
+            "json! value at `{}` doesn't match the schema: expected one of {}, found `{json}`",
+            self.pointer,
+            self.values.join(", "),
+        );
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Checked<'_, T> {
+    fn to_json_string(&self) -> String {
+        let mut json = String::new();
+        self.write_json(&mut json);
+        json
+    }
+
+    fn write_json(&self, json: &mut String) {
+        let start = json.len();
+        self.value.write_json(json);
+        if self.enabled {
+            self.check(&json[start..]);
+        }
+    }
+}
+
+/// The target of `json_write!`.
+///
+/// `write_json` is picked by method resolution: the inherent methods
+/// for `String` and `Vec<u8>` come first, then [`WriteFmt`] (on the
+/// `Writer` itself) and then [`WriteIo`] (on `&mut Writer`).
+pub struct Writer<'a, W: ?Sized>(pub &'a mut W);
+
+impl Writer<'_, String> {
+    #[inline]
+    pub fn write_json(self, push: impl FnOnce(String) -> String) -> fmt::Result {
+        *self.0 = push(mem::take(self.0));
+        Ok(())
+    }
+}
+
+impl Writer<'_, Vec<u8>> {
+    #[inline]
+    pub fn write_json(self, push: impl FnOnce(String) -> String) -> io::Result<()> {
+        self.0.extend_from_slice(push(String::new()).as_bytes());
+        Ok(())
+    }
+}
+
+pub trait WriteFmt {
+    fn write_json(self, push: impl FnOnce(String) -> String) -> fmt::Result;
+}
+
+impl<W: fmt::Write + ?Sized> WriteFmt for Writer<'_, W> {
+    #[inline]
+    fn write_json(self, push: impl FnOnce(String) -> String) -> fmt::Result {
+        self.0.write_str(&push(String::new()))
+    }
+}
+
+pub trait WriteIo {
+    fn write_json(self, push: impl FnOnce(String) -> String) -> io::Result<()>;
+}
+
+impl<W: io::Write + ?Sized> WriteIo for &mut Writer<'_, W> {
+    #[inline]
+    fn write_json(self, push: impl FnOnce(String) -> String) -> io::Result<()> {
+        self.0.write_all(push(String::new()).as_bytes())
+    }
+}
+
+/// Adds every member of `value` to `map`, for `..spread` in `json_value!`.
+///
+/// # Panics
+/// Panics if `value` isn't an object.
+pub fn spread_value(map: &mut Map, value: Value) {
+    match value {
+        Value::Object(members) => map.extend(members),
+        value => panic!("spread value in json_value! object is not a JSON object: {value}"),
+    }
+}
+
+/// Adds every element of `value` to `array`, for `..spread` in `json_value!`.
+///
+/// # Panics
+/// Panics if `value` isn't an array.
+pub fn extend_value(array: &mut Vec<Value>, value: Value) {
+    match value {
+        Value::Array(elements) => array.extend(elements),
+        value => panic!("spread value in json_value! array is not a JSON array: {value}"),
+    }
+}
+
+/// Moves on to element `index` of an array that `#[derive(FromJson)]`
+/// (or a tuple) expects to have `len` elements, starting at `start`.
+pub fn next_element(de: &mut Deserializer<'_>, start: usize, len: usize, index: usize) -> Result<(), ParseError> {
+    match de.next_element()? {
+        true => Ok(()),
+        false => Err(de.error_at(start, format!("expected an array of {len} elements, found {index}"))),
+    }
+}
+
+/// Ends an array that should have had `len` elements.
+pub fn end_array(de: &mut Deserializer<'_>, len: usize) -> Result<(), ParseError> {
+    match de.next_element()? {
+        true => Err(de.error(format!("expected an array of {len} elements, found more"))),
+        false => Ok(()),
+    }
+}
+
+/// Reads a `#[json(as_string)]` field with its `FromStr` impl.
+pub fn from_str_field<T: FromStr>(de: &mut Deserializer<'_>) -> Result<T, ParseError> {
+    let start = de.offset();
+    let string = de.parse_str()?;
+    string
+        .parse()
+        .map_err(|_| de.error_at(start, format!("invalid value `{string}`")))
+}
+
+/// Finds the string value of `tag` in the object `de` is at, for
+/// `#[json(tag = "...")]` enums, without moving past the object.
+pub fn find_tag<'de>(de: &mut Deserializer<'de>, tag: &str) -> Result<Cow<'de, str>, ParseError> {
+    let start = de.offset();
+    let checkpoint = de.checkpoint();
+    de.begin_object()?;
+    while let Some(key) = de.next_key()? {
+        if key == tag {
+            let value = de.parse_str()?;
+            de.restore(checkpoint);
+            return Ok(value);
+        }
+        de.skip_value()?;
+    }
+    Err(de.error_at(start, format!("missing field `{tag}`")))
+}
+
+/// Pushes the elements of the JSON array `json` onto `string`,
+/// each followed by a comma.
+///
+/// # Panics
+/// Panics if `json` isn't a JSON array.
+pub fn push_elements(string: &mut String, json: &str) {
+    let inner = json
+        .trim()
+        .strip_prefix('[')
+        .and_then(|json| json.strip_suffix(']'))
+        .unwrap_or_else(|| panic!("spread value in json! array is not a JSON array: {json}"))
+        .trim();
+    if !inner.is_empty() {
+        string.push_str(inner);
+        string.push(',');
+    }
+}
@@ -0,0 +1,825 @@
+//! [`JsonReader`], which reads JSON from an [`io::Read`] one event at a time.
+
+use std::{io, marker::PhantomData, mem, str};
+
+use crate::{parse::ParseError, Deserializer, Dialect, FromJsonOwned, ParseOptions, ValueKind};
+
+/// How many bytes a [`JsonReader`] reads at once.
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// One step through JSON read by a [`JsonReader`].
+///
+/// Keys, strings and numbers borrow the reader, so they have to be
+/// copied out (or dropped) before the next event is read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// A key of an object. The value it's the key of is read next.
+    Key(&'a str),
+    String(&'a str),
+    /// A number, as it's written, which [`str::parse`] turns into any
+    /// number type it fits in.
+    Number(&'a str),
+    Bool(bool),
+    Null,
+}
+
+impl Event<'_> {
+    /// The kind of value this event starts, or `None` for keys and the
+    /// ends of arrays and objects.
+    pub fn kind(&self) -> Option<ValueKind> {
+        Some(match self {
+            Self::StartObject => ValueKind::Object,
+            Self::StartArray => ValueKind::Array,
+            Self::String(_) => ValueKind::String,
+            Self::Number(_) => ValueKind::Number,
+            Self::Bool(_) => ValueKind::Bool,
+            Self::Null => ValueKind::Null,
+            Self::EndObject | Self::EndArray | Self::Key(_) => return None,
+        })
+    }
+}
+
+/// An array or object the reader is in.
+enum Frame {
+    /// `index` is the element being read, if one is.
+    Array { index: Option<usize>, len: usize },
+    /// `key` is the key of the value being read, if `has_key`.
+    Object { key: String, has_key: bool },
+}
+
+/// What the reader expects next.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Value,
+    /// An element or the `]` of an array that was just begun.
+    FirstElement,
+    /// A key or the `}` of an object that was just begun.
+    FirstKey,
+    /// A `,`, the end of the array or object, or the end of the input.
+    AfterValue,
+    Done,
+}
+
+/// Where something is in the input.
+#[derive(Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+/// A pull parser that reads JSON from an [`io::Read`] as a stream of
+/// [`Event`]s, without ever holding the whole document.
+///
+/// The input is read a few kilobytes at a time, and only the key,
+/// string or number being read is kept, so memory stays bounded by the
+/// nesting depth and the longest string, however big the document is.
+/// Use [`array_elements`][Self::array_elements] to read the elements
+/// of an array as typed values one at a time, which keeps one element.
+///
+/// The input has to be one JSON value (with whitespace around it), and
+/// it's checked as strictly as by [`from_str`][crate::from_str], with
+/// the same errors, unless the options allow
+/// [JSONC][crate::Dialect::Jsonc] (JSON5 can't be read this way). They're [`io::Error`]s of kind
+/// [`InvalidData`][io::ErrorKind::InvalidData] wrapping a [`ParseError`],
+/// since reading can fail too.
+///
+/// ```
+/// # use json_proc::{Event, JsonReader};
+/// let mut reader = JsonReader::new(r#"{ "name": "probe", "tags": [1, 2] }"#.as_bytes());
+/// assert_eq!(reader.next_event()?, Some(Event::StartObject));
+/// assert_eq!(reader.next_event()?, Some(Event::Key("name")));
+/// assert_eq!(reader.next_event()?, Some(Event::String("probe")));
+/// assert_eq!(reader.next_event()?, Some(Event::Key("tags")));
+/// reader.skip_value()?;
+/// assert_eq!(reader.next_event()?, Some(Event::EndObject));
+/// assert_eq!(reader.next_event()?, None);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct JsonReader<R> {
+    reader: R,
+    options: ParseOptions,
+    buf: Box<[u8]>,
+    /// The unread bytes are `buf[pos..filled]`.
+    pos: usize,
+    filled: usize,
+    /// The offset of `buf[0]` in the input.
+    consumed: usize,
+    line: usize,
+    column: usize,
+    state: State,
+    frames: Vec<Frame>,
+    /// The text of the last key, string or number.
+    scratch: String,
+    /// The bytes of the string being read since its start or last escape.
+    run: Vec<u8>,
+    /// Every byte read while this is `Some`, for `ArrayElements`.
+    capture: Option<Vec<u8>>,
+}
+
+impl<R: io::Read> JsonReader<R> {
+    /// Makes a reader of the JSON in `reader`, which doesn't need to be
+    /// buffered.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            options: ParseOptions::new(),
+            buf: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            consumed: 0,
+            line: 1,
+            column: 1,
+            state: State::Value,
+            frames: Vec::new(),
+            scratch: String::new(),
+            run: Vec::new(),
+            capture: None,
+        }
+    }
+
+    /// Sets the options to read with. The maximum depth and the dialect
+    /// apply to the events, and all of them apply to the elements read by
+    /// [`array_elements`][Self::array_elements].
+    ///
+    /// Only [`Dialect::Json`] and [`Dialect::Jsonc`] can be read: with
+    /// [`Dialect::Json5`], reading fails with an [`io::Error`] of kind
+    /// [`InvalidInput`][io::ErrorKind::InvalidInput].
+    ///
+    /// ```
+    /// # use json_proc::{Dialect, Event, JsonReader, ParseOptions};
+    /// let options = ParseOptions::new().dialect(Dialect::Jsonc);
+    /// let mut reader = JsonReader::new("[1, /* two */ 2,] // end".as_bytes()).with_options(options);
+    /// reader.skip_value()?;
+    /// assert_eq!(reader.next_event()?, None);
+    ///
+    /// let options = ParseOptions::new().dialect(Dialect::Json5);
+    /// let mut reader = JsonReader::new("[1]".as_bytes()).with_options(options);
+    /// assert!(reader.next_event().is_err());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[inline]
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Reads the next event, or `None` after the end of the value (once
+    /// the rest of the input is checked to be whitespace).
+    pub fn next_event(&mut self) -> io::Result<Option<Event<'_>>> {
+        self.check_dialect()?;
+        loop {
+            self.skip_whitespace()?;
+            match self.state {
+                State::Done => {
+                    return match self.peek()? {
+                        Some(_) => Err(self.error("trailing characters after the value").into()),
+                        None => Ok(None),
+                    };
+                }
+                State::Value => return self.value().map(Some),
+                State::FirstElement => {
+                    if self.peek()? == Some(b']') {
+                        return Ok(Some(self.end(Event::EndArray)));
+                    }
+                    self.start_element();
+                    return self.value().map(Some);
+                }
+                State::FirstKey => {
+                    if self.peek()? == Some(b'}') {
+                        return Ok(Some(self.end(Event::EndObject)));
+                    }
+                    return self.key().map(Some);
+                }
+                State::AfterValue => {
+                    let byte = self.peek()?;
+                    match self.frames.last_mut() {
+                        None => self.state = State::Done,
+                        Some(Frame::Array { index, .. }) => {
+                            *index = None;
+                            match byte {
+                                Some(b',') => {
+                                    self.bump();
+                                    self.skip_whitespace()?;
+                                    if self.jsonc() && self.peek()? == Some(b']') {
+                                        return Ok(Some(self.end(Event::EndArray)));
+                                    }
+                                    self.start_element();
+                                    self.state = State::Value;
+                                }
+                                Some(b']') => return Ok(Some(self.end(Event::EndArray))),
+                                _ => return Err(self.error("expected `,` or `]`").into()),
+                            }
+                        }
+                        Some(Frame::Object { has_key, .. }) => {
+                            *has_key = false;
+                            match byte {
+                                Some(b',') => {
+                                    self.bump();
+                                    self.skip_whitespace()?;
+                                    if self.jsonc() && self.peek()? == Some(b'}') {
+                                        return Ok(Some(self.end(Event::EndObject)));
+                                    }
+                                    return self.key().map(Some);
+                                }
+                                Some(b'}') => return Ok(Some(self.end(Event::EndObject))),
+                                _ => return Err(self.error("expected `,` or `}`").into()),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Skips the next value, along with everything in it. If the next
+    /// event is a key, its value is skipped with it.
+    ///
+    /// This is an error at the end of an array or object, or after the
+    /// end of the value.
+    pub fn skip_value(&mut self) -> io::Result<()> {
+        self.skip_whitespace()?;
+        let at_end = match (self.state, self.peek()?) {
+            (State::FirstElement | State::AfterValue, Some(b']')) => true,
+            (State::FirstKey | State::AfterValue, Some(b'}')) => true,
+            (state, _) => state == State::Done || (state == State::AfterValue && self.frames.is_empty()),
+        };
+        if at_end {
+            return Err(self.error("expected a value").into());
+        }
+        let depth = self.frames.len();
+        loop {
+            self.next_event()?;
+            if self.frames.len() == depth && self.state == State::AfterValue {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads the next value, which has to be an array, as an iterator of
+    /// its elements parsed as `T`s.
+    ///
+    /// Each element is read (and kept) on its own, then parsed with
+    /// [`FromJson`][crate::FromJson], so the whole array never is. Errors
+    /// in an element are at its place in the input. The iterator stops
+    /// after the `]` or the first error, and the reader carries on after
+    /// the array.
+    ///
+    /// ```
+    /// # use json_proc::{FromJson, JsonReader};
+    /// #[derive(FromJson, Debug, PartialEq)]
+    /// struct Reading {
+    ///     sensor: String,
+    ///     value: f64,
+    /// }
+    ///
+    /// let json = r#"[{ "sensor": "a", "value": 1.5 }, { "sensor": "b", "value": -2 }]"#;
+    /// let mut reader = JsonReader::new(json.as_bytes());
+    /// let readings = reader.array_elements::<Reading>()?.collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(readings[1], Reading { sensor: "b".into(), value: -2.0 });
+    /// assert_eq!(reader.next_event()?, None);
+    ///
+    /// let mut reader = JsonReader::new(r#"[{ "sensor": "a", "value": 1.5 },
+    ///     { "sensor": "b" }]"#.as_bytes());
+    /// let error = reader.array_elements::<Reading>()?.nth(1).unwrap().unwrap_err();
+    /// assert_eq!(error.to_string(), "missing field `value` at line 2, column 5 in `/1`");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn array_elements<T: FromJsonOwned>(&mut self) -> io::Result<ArrayElements<'_, R, T>> {
+        self.check_dialect()?;
+        self.skip_whitespace()?;
+        let start = self.position();
+        let found = match self.next_event()? {
+            Some(Event::StartArray) => {
+                return Ok(ArrayElements {
+                    depth: self.frames.len(),
+                    reader: self,
+                    raw: Vec::new(),
+                    done: false,
+                    marker: PhantomData,
+                });
+            }
+            event => event.and_then(|event| event.kind()),
+        };
+        let message = match found {
+            Some(kind) => format!("expected an array, found {kind}"),
+            None => "expected an array".to_owned(),
+        };
+        Err(self.error_at(start, message).into())
+    }
+
+    /// The byte offset of the next unread byte in the input.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.consumed + self.pos
+    }
+
+    /// The line (from 1) of the next unread byte.
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column (from 1, in characters) of the next unread byte.
+    #[inline]
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// How many arrays and objects the reader is in.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The JSON pointer to the value being read, like in [`ParseError`]s.
+    pub fn path(&self) -> String {
+        let mut path = String::new();
+        for frame in &self.frames {
+            match frame {
+                Frame::Array { index: Some(index), .. } => {
+                    path.push('/');
+                    path.push_str(&index.to_string());
+                }
+                Frame::Object { key, has_key: true } => {
+                    path.push('/');
+                    path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                }
+                _ => break,
+            }
+        }
+        path
+    }
+
+    /// Gets back the reader. Anything that was read into the buffer but
+    /// not parsed yet is lost.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Whether comments and trailing commas are allowed.
+    #[inline]
+    fn jsonc(&self) -> bool {
+        self.options.dialect == Dialect::Jsonc
+    }
+
+    /// Fails if the options ask for a dialect that can't be read.
+    fn check_dialect(&self) -> io::Result<()> {
+        match self.options.dialect {
+            Dialect::Json5 => Err(io::Error::new(io::ErrorKind::InvalidInput, "a `JsonReader` can't read JSON5")),
+            Dialect::Json | Dialect::Jsonc => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn position(&self) -> Position {
+        Position {
+            offset: self.offset(),
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error_at(&self, at: Position, message: impl Into<std::borrow::Cow<'static, str>>) -> ParseError {
+        ParseError::new(message, at.offset, at.line, at.column, self.path())
+    }
+
+    #[inline]
+    fn error(&self, message: impl Into<std::borrow::Cow<'static, str>>) -> ParseError {
+        self.error_at(self.position(), message)
+    }
+
+    /// Reads more of the input into the buffer, once it's all been read,
+    /// returning `false` at the end of the input.
+    fn fill(&mut self) -> io::Result<bool> {
+        self.consumed += self.filled;
+        self.pos = 0;
+        self.filled = 0;
+        self.read_more()
+    }
+
+    /// Reads more of the input into the buffer after `filled`,
+    /// returning `false` at the end of the input.
+    fn read_more(&mut self) -> io::Result<bool> {
+        loop {
+            match self.reader.read(&mut self.buf[self.filled..]) {
+                Ok(read) => {
+                    self.filled += read;
+                    return Ok(read != 0);
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    #[inline]
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        if self.pos == self.filled && !self.fill()? {
+            return Ok(None);
+        }
+        Ok(Some(self.buf[self.pos]))
+    }
+
+    /// The byte after the one `peek` returned (which there has to be).
+    fn peek_second(&mut self) -> io::Result<Option<u8>> {
+        if self.pos + 1 == self.filled {
+            // Keep the byte `peek` returned, at the start of the buffer.
+            self.buf[0] = self.buf[self.pos];
+            self.consumed += self.pos;
+            self.pos = 0;
+            self.filled = 1;
+            if !self.read_more()? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(self.buf[self.pos + 1]))
+    }
+
+    /// Moves past the byte `peek` returned.
+    #[inline]
+    fn bump(&mut self) {
+        let byte = self.buf[self.pos];
+        self.pos += 1;
+        if let Some(capture) = &mut self.capture {
+            capture.push(byte);
+        }
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if byte & 0xC0 != 0x80 {
+            // Count characters, not bytes (continuation bytes don't start one).
+            self.column += 1;
+        }
+    }
+
+    /// Skips whitespace, and comments in JSONC.
+    fn skip_whitespace(&mut self) -> io::Result<()> {
+        loop {
+            match self.peek()? {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.bump(),
+                Some(b'/') if self.jsonc() => {
+                    if !self.skip_comment()? {
+                        return Ok(());
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Skips a `//` or `/* */` comment, from its `/`, returning `false`
+    /// (without moving) if the `/` doesn't start one. Like in
+    /// [`Deserializer`], a comment that isn't closed runs to the end.
+    fn skip_comment(&mut self) -> io::Result<bool> {
+        match self.peek_second()? {
+            Some(b'/') => {
+                while !matches!(self.peek()?, Some(b'\n') | None) {
+                    self.bump();
+                }
+            }
+            Some(b'*') => {
+                self.bump();
+                self.bump();
+                let mut star = false;
+                while let Some(byte) = self.peek()? {
+                    self.bump();
+                    if star && byte == b'/' {
+                        break;
+                    }
+                    star = byte == b'*';
+                }
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Moves on to the next element of the array the reader is in.
+    fn start_element(&mut self) {
+        if let Some(Frame::Array { index, len }) = self.frames.last_mut() {
+            *index = Some(*len);
+            *len += 1;
+        }
+    }
+
+    /// Reads the `]` or `}` of the array or object the reader is in.
+    fn end(&mut self, event: Event<'static>) -> Event<'static> {
+        self.bump();
+        self.frames.pop();
+        self.state = State::AfterValue;
+        event
+    }
+
+    /// Reads the `[` or `{` of an array or object.
+    fn begin(&mut self, frame: Frame, state: State, event: Event<'static>) -> io::Result<Event<'static>> {
+        if self.frames.len() == self.options.max_depth {
+            return Err(self.error("nesting too deep").into());
+        }
+        self.bump();
+        self.frames.push(frame);
+        self.state = state;
+        Ok(event)
+    }
+
+    fn value(&mut self) -> io::Result<Event<'_>> {
+        match self.peek()? {
+            Some(b'{') => self.begin(
+                Frame::Object {
+                    key: String::new(),
+                    has_key: false,
+                },
+                State::FirstKey,
+                Event::StartObject,
+            ),
+            Some(b'[') => self.begin(Frame::Array { index: None, len: 0 }, State::FirstElement, Event::StartArray),
+            Some(b'"') => {
+                self.read_string()?;
+                self.state = State::AfterValue;
+                Ok(Event::String(&self.scratch))
+            }
+            Some(b'-' | b'0'..=b'9') => {
+                self.read_number()?;
+                self.state = State::AfterValue;
+                Ok(Event::Number(&self.scratch))
+            }
+            Some(b't') => self.keyword(b"true", Event::Bool(true)),
+            Some(b'f') => self.keyword(b"false", Event::Bool(false)),
+            Some(b'n') => self.keyword(b"null", Event::Null),
+            Some(_) => Err(self.error("expected a value").into()),
+            None => Err(self.error("unexpected end of input").into()),
+        }
+    }
+
+    /// Reads a key of an object and the `:` after it.
+    fn key(&mut self) -> io::Result<Event<'_>> {
+        if self.peek()? != Some(b'"') {
+            return Err(self.error("expected a string key").into());
+        }
+        self.read_string()?;
+        self.skip_whitespace()?;
+        if self.peek()? != Some(b':') {
+            return Err(self.error("expected `:`").into());
+        }
+        self.bump();
+        if let Some(Frame::Object { key, has_key }) = self.frames.last_mut() {
+            key.clear();
+            key.push_str(&self.scratch);
+            *has_key = true;
+        }
+        self.state = State::Value;
+        Ok(Event::Key(&self.scratch))
+    }
+
+    fn keyword(&mut self, keyword: &[u8], event: Event<'static>) -> io::Result<Event<'static>> {
+        let start = self.position();
+        for &expected in keyword {
+            if self.peek()? != Some(expected) {
+                return Err(self.error_at(start, "expected a value").into());
+            }
+            self.bump();
+        }
+        self.state = State::AfterValue;
+        Ok(event)
+    }
+
+    /// Moves the next (ASCII) byte into `scratch`.
+    #[inline]
+    fn push_byte(&mut self) {
+        self.scratch.push(char::from(self.buf[self.pos]));
+        self.bump();
+    }
+
+    /// Moves digits into `scratch`, returning how many there were.
+    fn digits(&mut self) -> io::Result<usize> {
+        let mut count = 0;
+        while let Some(b'0'..=b'9') = self.peek()? {
+            self.push_byte();
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn read_number(&mut self) -> io::Result<()> {
+        self.scratch.clear();
+        if self.peek()? == Some(b'-') {
+            self.push_byte();
+        }
+        match self.peek()? {
+            Some(b'0') => self.push_byte(),
+            Some(b'1'..=b'9') => {
+                self.digits()?;
+            }
+            _ => return Err(self.error("expected a digit").into()),
+        }
+        if self.peek()? == Some(b'.') {
+            self.push_byte();
+            if self.digits()? == 0 {
+                return Err(self.error("expected a digit after `.`").into());
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek()? {
+            self.push_byte();
+            if let Some(b'+' | b'-') = self.peek()? {
+                self.push_byte();
+            }
+            if self.digits()? == 0 {
+                return Err(self.error("expected a digit in the exponent").into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a string (from its opening quote) into `scratch`.
+    fn read_string(&mut self) -> io::Result<()> {
+        self.scratch.clear();
+        self.bump();
+        loop {
+            // Copy everything up to the next quote, escape or control
+            // character, across as many reads as it takes.
+            let start = self.position();
+            self.run.clear();
+            while self.pos < self.filled || self.fill()? {
+                let rest = &self.buf[self.pos..self.filled];
+                let len = rest
+                    .iter()
+                    .position(|&byte| byte == b'"' || byte == b'\\' || byte < 0x20)
+                    .unwrap_or(rest.len());
+                let chunk = &rest[..len];
+                self.run.extend_from_slice(chunk);
+                if let Some(capture) = &mut self.capture {
+                    capture.extend_from_slice(chunk);
+                }
+                self.column += chunk.iter().filter(|&&byte| byte & 0xC0 != 0x80).count();
+                self.pos += len;
+                if self.pos < self.filled {
+                    break;
+                }
+            }
+            match str::from_utf8(&self.run) {
+                Ok(run) => self.scratch.push_str(run),
+                Err(error) => {
+                    // The run has no newlines, so the error is on its line.
+                    let valid = &self.run[..error.valid_up_to()];
+                    let at = Position {
+                        offset: start.offset + valid.len(),
+                        line: start.line,
+                        column: start.column + valid.iter().filter(|&&byte| byte & 0xC0 != 0x80).count(),
+                    };
+                    return Err(self.error_at(at, "invalid UTF-8 in string").into());
+                }
+            }
+            match self.peek()? {
+                Some(b'"') => {
+                    self.bump();
+                    return Ok(());
+                }
+                Some(b'\\') => {
+                    self.bump();
+                    let ch = self.escape()?;
+                    self.scratch.push(ch);
+                }
+                Some(_) => return Err(self.error("control character in string").into()),
+                None => return Err(self.error("unterminated string").into()),
+            }
+        }
+    }
+
+    /// Reads what's after the `\` of an escape.
+    fn escape(&mut self) -> io::Result<char> {
+        let ch = match self.peek()? {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{08}',
+            Some(b'f') => '\u{0C}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.bump();
+                let high = self.hex()?;
+                if !(0xD800..0xE000).contains(&high) {
+                    return Ok(char::from_u32(high).expect("not a surrogate"));
+                }
+                let after_high = self.position();
+                if high >= 0xDC00 || self.peek()? != Some(b'\\') {
+                    return Err(self.error("unpaired surrogate in unicode escape").into());
+                }
+                self.bump();
+                if self.peek()? != Some(b'u') {
+                    return Err(self.error_at(after_high, "unpaired surrogate in unicode escape").into());
+                }
+                self.bump();
+                let low = self.hex()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Err(self.error("unpaired surrogate in unicode escape").into());
+                }
+                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                return Ok(char::from_u32(code).expect("surrogate pairs are valid"));
+            }
+            _ => return Err(self.error("invalid escape").into()),
+        };
+        self.bump();
+        Ok(ch)
+    }
+
+    fn hex(&mut self) -> io::Result<u32> {
+        let start = self.position();
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.peek()?.and_then(|byte| char::from(byte).to_digit(16));
+            let Some(digit) = digit else {
+                return Err(self.error_at(start, "expected 4 hex digits").into());
+            };
+            self.bump();
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    /// Moves on to the next element of the array `ArrayElements` reads,
+    /// returning `false` (after reading the `]`) if there isn't one.
+    fn next_element(&mut self) -> io::Result<bool> {
+        self.skip_whitespace()?;
+        let byte = self.peek()?;
+        if let Some(Frame::Array { index, .. }) = self.frames.last_mut() {
+            *index = None;
+        }
+        match (self.state, byte) {
+            (State::FirstElement | State::AfterValue, Some(b']')) => {
+                self.end(Event::EndArray);
+                return Ok(false);
+            }
+            (State::AfterValue, Some(b',')) => {
+                self.bump();
+                self.skip_whitespace()?;
+                if self.jsonc() && self.peek()? == Some(b']') {
+                    self.end(Event::EndArray);
+                    return Ok(false);
+                }
+            }
+            (State::AfterValue, _) => return Err(self.error("expected `,` or `]`").into()),
+            _ => {}
+        }
+        self.start_element();
+        self.state = State::Value;
+        Ok(true)
+    }
+}
+
+/// An iterator over the elements of an array read by a [`JsonReader`],
+/// made by [`JsonReader::array_elements`].
+pub struct ArrayElements<'r, R, T> {
+    reader: &'r mut JsonReader<R>,
+    /// The depth of the array.
+    depth: usize,
+    /// The JSON of the element being parsed.
+    raw: Vec<u8>,
+    done: bool,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<R: io::Read, T: FromJsonOwned> ArrayElements<'_, R, T> {
+    fn read(&mut self) -> io::Result<Option<T>> {
+        let reader = &mut *self.reader;
+        debug_assert_eq!(reader.frames.len(), self.depth, "the array isn't the one being read");
+        if !reader.next_element()? {
+            return Ok(None);
+        }
+        let start = reader.position();
+        let path = reader.path();
+        self.raw.clear();
+        reader.capture = Some(mem::take(&mut self.raw));
+        let skipped = reader.skip_value();
+        self.raw = reader.capture.take().unwrap_or_default();
+        skipped?;
+
+        let mut de = Deserializer::from_slice(&self.raw).with_options(reader.options);
+        T::from_json(&mut de)
+            .map(Some)
+            .map_err(|error| error.relocate(start.offset, start.line, start.column, &path).into())
+    }
+}
+
+impl<R: io::Read, T: FromJsonOwned> Iterator for ArrayElements<'_, R, T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.read();
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result.transpose()
+    }
+}
+
+impl<R: io::Read, T: FromJsonOwned> std::iter::FusedIterator for ArrayElements<'_, R, T> {}
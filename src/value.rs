@@ -0,0 +1,357 @@
+//! The [`Value`] type, an in-memory JSON document.
+
+mod index;
+mod map;
+mod number;
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::BuildHasher,
+};
+
+pub use index::ValueIndex;
+pub use map::Map;
+pub use number::Number;
+
+use crate::{json_trait::push_escaped, Json, ToJson};
+
+/// Any JSON value.
+///
+/// Objects keep their keys in the order they were inserted in, like
+/// the output of [`json!`][crate::json].
+///
+/// This can be built with [`json_value!`][crate::json_value], which takes
+/// the same syntax as [`json!`][crate::json], or from most std types with
+/// [`From`]:
+/// ```
+/// # use json_proc::{json_value, Value};
+/// let mut value = json_value!({ "name": "ann", "ids": [1, 2] });
+/// assert_eq!(value["name"], "ann");
+/// assert_eq!(value["ids"][1], 2);
+/// assert!(value["missing"].is_null());
+///
+/// value["ids"][0] = Value::from(10);
+/// value["new"] = Value::from(vec![true]);
+/// assert_eq!(value.to_string(), r#"{"name":"ann","ids":[10,2],"new":[true]}"#);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<Value>),
+    Object(Map),
+}
+
+impl Value {
+    /// Gets the element at `index` of an array, or the value of the key
+    /// `index` of an object, if there is one.
+    #[inline]
+    pub fn get<I: ValueIndex + ?Sized>(&self, index: &I) -> Option<&Value> {
+        index.index_into(self)
+    }
+
+    /// Like [`get`][Self::get], but mutable.
+    #[inline]
+    pub fn get_mut<I: ValueIndex + ?Sized>(&mut self, index: &I) -> Option<&mut Value> {
+        index.index_into_mut(self)
+    }
+
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(bool) => Some(*bool),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            Value::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    /// The number as an `i64`, if it's an integer that fits in one.
+    #[inline]
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(Number::as_i64)
+    }
+
+    /// The number as a `u64`, if it's an integer that fits in one.
+    #[inline]
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(Number::as_u64)
+    }
+
+    /// The number as an `f64`, which may lose precision for big integers.
+    #[inline]
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(Number::as_f64)
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_object(&self) -> Option<&Map> {
+        match self {
+            Value::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_object_mut(&mut self) -> Option<&mut Map> {
+        match self {
+            Value::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Replaces self with `null`, returning what it was.
+    #[inline]
+    pub fn take(&mut self) -> Value {
+        std::mem::take(self)
+    }
+}
+
+impl ToJson for Value {
+    fn to_json_string(&self) -> String {
+        let mut json = String::new();
+        self.write_json(&mut json);
+        json
+    }
+
+    fn write_json(&self, json: &mut String) {
+        match self {
+            Value::Null => json.push_str("null"),
+            Value::Bool(bool) => json.push_str(if *bool { "true" } else { "false" }),
+            Value::Number(number) => number.write_json(json),
+            Value::String(string) => push_escaped(json, string),
+            Value::Array(array) => array.write_json(json),
+            Value::Object(map) => map.write_json(json),
+        }
+    }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_json_string())
+    }
+}
+
+impl From<Value> for Json {
+    #[inline]
+    fn from(value: Value) -> Self {
+        value.to_json()
+    }
+}
+
+macro_rules! from_impl {
+    { $($ty:ty => |$value:ident| $conversion:expr),* $(,)? } => {
+        $(
+            impl From<$ty> for Value {
+                #[inline]
+                fn from($value: $ty) -> Self {
+                    $conversion
+                }
+            }
+        )*
+    };
+}
+
+from_impl! {
+    () => |_value| Value::Null,
+    bool => |value| Value::Bool(value),
+    String => |value| Value::String(value),
+    &str => |value| Value::String(value.to_owned()),
+    &String => |value| Value::String(value.clone()),
+    Cow<'_, str> => |value| Value::String(value.into_owned()),
+    char => |value| Value::String(value.to_string()),
+    Number => |value| Value::Number(value),
+    Map => |value| Value::Object(value),
+}
+
+macro_rules! from_number_impl {
+    { $($ty:ty)* } => {
+        $(
+            impl From<$ty> for Value {
+                #[inline]
+                fn from(value: $ty) -> Self {
+                    Value::Number(Number::from(value))
+                }
+            }
+        )*
+    };
+}
+
+from_number_impl! {
+    u8 u16 u32 u64 u128 usize
+    i8 i16 i32 i64 i128 isize
+}
+
+impl From<f32> for Value {
+    /// Converts `value`, which is `null` if it's infinite or NaN.
+    #[inline]
+    fn from(value: f32) -> Self {
+        Value::from(f64::from(value))
+    }
+}
+
+impl From<f64> for Value {
+    /// Converts `value`, which is `null` if it's infinite or NaN.
+    #[inline]
+    fn from(value: f64) -> Self {
+        Number::from_f64(value).map_or(Value::Null, Value::Number)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    #[inline]
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    #[inline]
+    fn from(value: Vec<T>) -> Self {
+        Value::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>, const N: usize> From<[T; N]> for Value {
+    #[inline]
+    fn from(value: [T; N]) -> Self {
+        Value::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<Value>> From<&[T]> for Value {
+    #[inline]
+    fn from(value: &[T]) -> Self {
+        Value::Array(value.iter().cloned().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    #[inline]
+    fn from(value: BTreeMap<K, V>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>, S: BuildHasher> From<HashMap<K, V, S>> for Value {
+    #[inline]
+    fn from(value: HashMap<K, V, S>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+impl<T: Into<Value>> FromIterator<T> for Value {
+    /// Collects into an array.
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Value::Array(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    /// Collects into an object.
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Value::Object(iter.into_iter().collect())
+    }
+}
+
+macro_rules! eq_impl {
+    { $($ty:ty => |$value:ident, $other:ident| $eq:expr),* $(,)? } => {
+        $(
+            impl PartialEq<$ty> for Value {
+                #[inline]
+                fn eq(&self, $other: &$ty) -> bool {
+                    let $value = self;
+                    $eq
+                }
+            }
+
+            impl PartialEq<Value> for $ty {
+                #[inline]
+                fn eq(&self, other: &Value) -> bool {
+                    other == self
+                }
+            }
+        )*
+    };
+}
+
+eq_impl! {
+    bool => |value, other| value.as_bool() == Some(*other),
+    str => |value, other| value.as_str() == Some(other),
+    &str => |value, other| value.as_str() == Some(*other),
+    String => |value, other| value.as_str() == Some(other.as_str()),
+    f32 => |value, other| value.as_f64() == Some(f64::from(*other)),
+    f64 => |value, other| value.as_f64() == Some(*other),
+}
+
+macro_rules! eq_int_impl {
+    { $($ty:ty)* } => {
+        $(
+            impl PartialEq<$ty> for Value {
+                #[inline]
+                fn eq(&self, other: &$ty) -> bool {
+                    self.as_number().is_some_and(|number| *number == Number::from(*other))
+                }
+            }
+
+            impl PartialEq<Value> for $ty {
+                #[inline]
+                fn eq(&self, other: &Value) -> bool {
+                    other == self
+                }
+            }
+        )*
+    };
+}
+
+eq_int_impl! {
+    u8 u16 u32 u64 u128 usize
+    i8 i16 i32 i64 i128 isize
+}
@@ -0,0 +1,137 @@
+use std::ops::{Index, IndexMut};
+
+use crate::Value;
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for usize {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl<T: Sealed + ?Sized> Sealed for &T {}
+}
+
+/// Something a [`Value`] can be indexed with: a `usize` for arrays,
+/// or a string for objects.
+pub trait ValueIndex: private::Sealed {
+    #[doc(hidden)]
+    fn index_into<'a>(&self, value: &'a Value) -> Option<&'a Value>;
+
+    #[doc(hidden)]
+    fn index_into_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value>;
+
+    /// Used by `IndexMut`, which inserts missing keys.
+    #[doc(hidden)]
+    fn index_or_insert<'a>(&self, value: &'a mut Value) -> &'a mut Value;
+}
+
+impl ValueIndex for usize {
+    fn index_into<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        value.as_array()?.get(*self)
+    }
+
+    fn index_into_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value> {
+        value.as_array_mut()?.get_mut(*self)
+    }
+
+    fn index_or_insert<'a>(&self, value: &'a mut Value) -> &'a mut Value {
+        match value {
+            Value::Array(array) => {
+                let len = array.len();
+                array
+                    .get_mut(*self)
+                    .unwrap_or_else(|| panic!("index {self} out of range for JSON array of length {len}"))
+            }
+            value => panic!("cannot index into JSON {} with a number", kind(value)),
+        }
+    }
+}
+
+impl ValueIndex for str {
+    fn index_into<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        value.as_object()?.get(self)
+    }
+
+    fn index_into_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value> {
+        value.as_object_mut()?.get_mut(self)
+    }
+
+    fn index_or_insert<'a>(&self, value: &'a mut Value) -> &'a mut Value {
+        // Like in JavaScript, `null` becomes an object when a key is set.
+        if value.is_null() {
+            *value = Value::Object(Default::default());
+        }
+        match value {
+            Value::Object(map) => map.entry(self),
+            value => panic!("cannot index into JSON {} with a string", kind(value)),
+        }
+    }
+}
+
+impl ValueIndex for String {
+    #[inline]
+    fn index_into<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.as_str().index_into(value)
+    }
+
+    #[inline]
+    fn index_into_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value> {
+        self.as_str().index_into_mut(value)
+    }
+
+    #[inline]
+    fn index_or_insert<'a>(&self, value: &'a mut Value) -> &'a mut Value {
+        self.as_str().index_or_insert(value)
+    }
+}
+
+impl<T: ValueIndex + ?Sized> ValueIndex for &T {
+    #[inline]
+    fn index_into<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        (**self).index_into(value)
+    }
+
+    #[inline]
+    fn index_into_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value> {
+        (**self).index_into_mut(value)
+    }
+
+    #[inline]
+    fn index_or_insert<'a>(&self, value: &'a mut Value) -> &'a mut Value {
+        (**self).index_or_insert(value)
+    }
+}
+
+/// The name of the type of `value`, for panic messages.
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+impl<I: ValueIndex> Index<I> for Value {
+    type Output = Value;
+
+    /// Gets the element or member at `index`, which is `null` if
+    /// there isn't one (or if this isn't an array or object).
+    fn index(&self, index: I) -> &Value {
+        static NULL: Value = Value::Null;
+        index.index_into(self).unwrap_or(&NULL)
+    }
+}
+
+impl<I: ValueIndex> IndexMut<I> for Value {
+    /// Gets the element or member at `index`, inserting `null` if
+    /// there's no member with that key.
+    ///
+    /// # Panics
+    /// Panics if the array index is out of range, or if this isn't
+    /// an array or object (or `null`, which becomes an object).
+    fn index_mut(&mut self, index: I) -> &mut Value {
+        index.index_or_insert(self)
+    }
+}
@@ -0,0 +1,252 @@
+use std::{collections::HashMap, fmt, slice, vec};
+
+use crate::{json_trait::push_escaped, ToJson, Value};
+
+/// How many members a [`Map`] can have before it's indexed.
+const INDEXED_LEN: usize = 16;
+
+/// The members of a JSON object, in the order they were inserted in.
+///
+/// Small maps look keys up by going through every member, which is
+/// fast for the small objects JSON usually has. Bigger ones also keep
+/// a hash index of where each key is, so looking up and inserting keys
+/// take the same time however many members there are.
+#[derive(Clone, Default)]
+pub struct Map {
+    members: Vec<(String, Value)>,
+    /// The position of each key in `members`, once there are more
+    /// than `INDEXED_LEN` of them.
+    index: Option<HashMap<String, usize>>,
+}
+
+impl Map {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            members: Vec::with_capacity(capacity),
+            index: None,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Where `key` is in `members`.
+    fn position(&self, key: &str) -> Option<usize> {
+        match &self.index {
+            Some(index) => index.get(key).copied(),
+            None => self.members.iter().position(|(k, _)| k == key),
+        }
+    }
+
+    /// Adds a member with a key that isn't in the map yet, returning its position.
+    fn push(&mut self, key: String, value: Value) -> usize {
+        let i = self.members.len();
+        match &mut self.index {
+            Some(index) => {
+                index.insert(key.clone(), i);
+            }
+            None if i == INDEXED_LEN => {
+                let mut index = HashMap::with_capacity(self.members.capacity().max(i + 1));
+                index.extend(self.members.iter().enumerate().map(|(i, (key, _))| (key.clone(), i)));
+                index.insert(key.clone(), i);
+                self.index = Some(index);
+            }
+            None => {}
+        }
+        self.members.push((key, value));
+        i
+    }
+
+    #[inline]
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.position(key).map(|i| &self.members[i].1)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.position(key).map(|i| &mut self.members[i].1)
+    }
+
+    #[inline]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    /// Inserts a member, returning the value it replaced. A replaced
+    /// member keeps its position.
+    pub fn insert(&mut self, key: impl Into<String>, value: Value) -> Option<Value> {
+        let key = key.into();
+        match self.position(&key) {
+            Some(i) => Some(std::mem::replace(&mut self.members[i].1, value)),
+            None => {
+                self.push(key, value);
+                None
+            }
+        }
+    }
+
+    /// Gets the value of `key`, inserting `null` if there isn't one.
+    pub fn entry(&mut self, key: impl Into<String>) -> &mut Value {
+        let key = key.into();
+        let i = match self.position(&key) {
+            Some(i) => i,
+            None => self.push(key, Value::Null),
+        };
+        &mut self.members[i].1
+    }
+
+    /// Removes a member, keeping the order of the others.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let i = self.position(key)?;
+        let (key, value) = self.members.remove(i);
+        if let Some(index) = &mut self.index {
+            index.remove(&key);
+            for position in index.values_mut().filter(|position| **position > i) {
+                *position -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.members.iter())
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut(self.members.iter_mut())
+    }
+
+    #[inline]
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.members.iter().map(|(key, _)| key)
+    }
+
+    #[inline]
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.members.iter().map(|(_, value)| value)
+    }
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Map").field("members", &self.members).finish()
+    }
+}
+
+impl PartialEq for Map {
+    /// Whether both maps have the same members, in any order.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl ToJson for Map {
+    fn to_json_string(&self) -> String {
+        let mut json = String::new();
+        self.write_json(&mut json);
+        json
+    }
+
+    fn write_json(&self, json: &mut String) {
+        json.push('{');
+        for (i, (key, value)) in self.members.iter().enumerate() {
+            if i != 0 {
+                json.push(',');
+            }
+            push_escaped(json, key);
+            json.push(':');
+            value.write_json(json);
+        }
+        json.push('}');
+    }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        Value::Object(self.clone())
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Map {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Map::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> Extend<(K, V)> for Map {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value.into());
+        }
+    }
+}
+
+/// An iterator over the members of a [`Map`].
+pub struct Iter<'a>(slice::Iter<'a, (String, Value)>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a String, &'a Value);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, value)| (key, value))
+    }
+}
+
+/// A mutable iterator over the members of a [`Map`].
+pub struct IterMut<'a>(slice::IterMut<'a, (String, Value)>);
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a String, &'a mut Value);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, value)| (&*key, value))
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (String, Value);
+    type IntoIter = vec::IntoIter<(String, Value)>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.members.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = (&'a String, &'a Value);
+    type IntoIter = Iter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Map {
+    type Item = (&'a String, &'a mut Value);
+    type IntoIter = IterMut<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
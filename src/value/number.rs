@@ -0,0 +1,152 @@
+use std::fmt;
+
+use crate::{ToJson, Value};
+
+/// A JSON number, which is an integer (if it fits in an `i64` or `u64`)
+/// or a finite `f64`.
+#[derive(Clone, Copy, Debug)]
+pub struct Number(N);
+
+#[derive(Clone, Copy, Debug)]
+enum N {
+    PosInt(u64),
+    /// Always less than zero.
+    NegInt(i64),
+    /// Always finite.
+    Float(f64),
+}
+
+impl Number {
+    /// Converts `float`, which can't be infinite or NaN since JSON
+    /// doesn't have those.
+    #[inline]
+    pub fn from_f64(float: f64) -> Option<Self> {
+        float.is_finite().then_some(Self(N::Float(float)))
+    }
+
+    #[inline]
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    #[inline]
+    pub fn is_u64(&self) -> bool {
+        matches!(self.0, N::PosInt(_))
+    }
+
+    #[inline]
+    pub fn is_f64(&self) -> bool {
+        matches!(self.0, N::Float(_))
+    }
+
+    #[inline]
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.0 {
+            N::PosInt(int) => i64::try_from(int).ok(),
+            N::NegInt(int) => Some(int),
+            N::Float(_) => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.0 {
+            N::PosInt(int) => Some(int),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_f64(&self) -> f64 {
+        match self.0 {
+            N::PosInt(int) => int as f64,
+            N::NegInt(int) => int as f64,
+            N::Float(float) => float,
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.0, other.0) {
+            (N::PosInt(a), N::PosInt(b)) => a == b,
+            (N::NegInt(a), N::NegInt(b)) => a == b,
+            (N::Float(a), N::Float(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+macro_rules! from_unsigned_impl {
+    { $($ty:ty)* } => {
+        $(
+            impl From<$ty> for Number {
+                #[inline]
+                fn from(int: $ty) -> Self {
+                    Self(N::PosInt(int as u64))
+                }
+            }
+        )*
+    };
+}
+
+from_unsigned_impl! { u8 u16 u32 u64 usize }
+
+macro_rules! from_signed_impl {
+    { $($ty:ty)* } => {
+        $(
+            impl From<$ty> for Number {
+                #[inline]
+                fn from(int: $ty) -> Self {
+                    if int < 0 {
+                        Self(N::NegInt(int as i64))
+                    } else {
+                        Self(N::PosInt(int as u64))
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_signed_impl! { i8 i16 i32 i64 isize }
+
+impl From<u128> for Number {
+    /// Converts `int`, as an `f64` if it doesn't fit in a `u64`.
+    #[inline]
+    fn from(int: u128) -> Self {
+        u64::try_from(int).map_or(Self(N::Float(int as f64)), Self::from)
+    }
+}
+
+impl From<i128> for Number {
+    /// Converts `int`, as an `f64` if it doesn't fit in an `i64`.
+    #[inline]
+    fn from(int: i128) -> Self {
+        i64::try_from(int).map_or(Self(N::Float(int as f64)), Self::from)
+    }
+}
+
+impl ToJson for Number {
+    #[inline]
+    fn to_json_string(&self) -> String {
+        self.to_string()
+    }
+
+    #[inline]
+    fn to_value(&self) -> Value {
+        Value::Number(*self)
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            N::PosInt(int) => fmt::Display::fmt(&int, f),
+            N::NegInt(int) => fmt::Display::fmt(&int, f),
+            // Keep a `.0` on whole floats, so they're still floats when parsed.
+            N::Float(float) if float.fract() == 0.0 => write!(f, "{float:.1}"),
+            N::Float(float) => fmt::Display::fmt(&float, f),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Checks that borrowed strings are read without allocating, by
+//! counting the allocations made on the test's own thread.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    borrow::Cow,
+    cell::Cell,
+};
+
+use json_proc::{Deserializer, FromJson};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Runs `f`, returning what it returns and how many allocations it made.
+fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let value = f();
+    (value, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[derive(FromJson, Debug)]
+struct Message<'a> {
+    id: &'a str,
+    title: Cow<'a, str>,
+    reply_to: Option<&'a str>,
+    size: u32,
+}
+
+#[test]
+fn test_borrowed_strings() {
+    let payload = "x".repeat(10_000);
+    let json = format!(r#"{{"id": "m-1", "payload": "{payload}", "title": "hello", "size": 3, "tags": ["a", "b"]}}"#);
+
+    // The deserializer keeps a stack of the arrays and objects it's in,
+    // which is all that reading anything should allocate.
+    let (_, skipping) = allocations(|| Deserializer::new(&json).skip_value().unwrap());
+    let (message, count) = allocations(|| Message::from_json_str(&json).unwrap());
+    assert_eq!(count, skipping);
+    assert_eq!((message.id, message.reply_to, message.size), ("m-1", None, 3));
+    assert!(matches!(message.title, Cow::Borrowed("hello")));
+    assert!(json.as_bytes().as_ptr_range().contains(&message.id.as_ptr()));
+
+    // Escaped strings are unescaped into a new `String`.
+    let json = r#"{"id": "m-2", "title": "tab\there", "size": 1}"#;
+    let (message, count) = allocations(|| Message::from_json_str(json).unwrap());
+    assert_eq!(count, skipping + 1);
+    assert!(matches!(message.title, Cow::Owned(ref title) if title == "tab\there"));
+
+    let error = Message::from_json_str(r#"{"id": "\u0041", "title": "", "size": 1}"#).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "can't borrow a string with escapes (use `Cow<str>` instead) at line 1, column 8 in `/id`"
+    );
+}
@@ -0,0 +1,59 @@
+//! Checks that generated code doesn't rely on the prelude, on
+//! `ToJson` being imported or on the crate being named `json_proc`.
+#![no_implicit_prelude]
+
+pub extern crate json_proc as renamed;
+extern crate std;
+
+/// Stands in for a facade crate that re-exports `json_proc`.
+mod facade {
+    pub use ::renamed as json;
+}
+
+use renamed::json;
+
+#[derive(renamed::ToJson, renamed::FromJson, PartialEq, Debug)]
+#[json(crate = "facade::json")]
+struct Point<T> {
+    x: T,
+    y: T,
+}
+
+#[derive(renamed::ToJson, renamed::FromJson, PartialEq, Debug)]
+#[json(crate = "::renamed")]
+enum Shape {
+    Circle { radius: u32 },
+    Empty,
+}
+
+#[test]
+fn test_renamed_crate() {
+    let string = "shadowed";
+    let value = json!(crate = ::renamed, {
+        "point": Point { x: 1, y: 2 },
+        "shapes": [Shape::Circle { radius: 3 }, Shape::Empty],
+        "string": string,
+        "empty": [],
+        "formatted": fmt!("{string}!"),
+    });
+    ::std::assert_eq!(
+        value,
+        r#"{"point":{"x":1,"y":2},"shapes":[{"radius":3},"Empty"],"string":"shadowed","empty":[],"formatted":"shadowed!"}"#
+    );
+    ::std::assert_eq!(json!(crate = facade::json, []), "[]");
+
+    let mut buf = ::std::string::String::new();
+    ::std::assert!(renamed::json_write!(crate = facade::json, &mut buf, { "string": string }).is_ok());
+    ::std::assert_eq!(buf, r#"{"string":"shadowed"}"#);
+
+    let lazy = renamed::json_lazy!(crate = facade::json, [string]);
+    ::std::assert_eq!(json!(crate = facade::json, { "lazy": lazy }), r#"{"lazy":["shadowed"]}"#);
+
+    let value = renamed::json_value!(crate = facade::json, { "string": string, "items": [for map in [1] => map] });
+    ::std::assert_eq!(renamed::ToJson::to_json_string(&value), r#"{"string":"shadowed","items":[1]}"#);
+
+    let shapes: ::std::vec::Vec<Shape> = renamed::FromJson::from_json_str(r#"[{"radius":3},"Empty"]"#).unwrap();
+    ::std::assert_eq!(shapes, [Shape::Circle { radius: 3 }, Shape::Empty]);
+    let point: Point<u8> = renamed::FromJson::from_json_str(r#"{"y":2,"x":1}"#).unwrap();
+    ::std::assert_eq!(point, Point { x: 1, y: 2 });
+}
@@ -0,0 +1,116 @@
+//! Runs the parser against all 318 `test_parsing` cases of JSONTestSuite
+//! (<https://github.com/nst/JSONTestSuite>), vendored as they are in
+//! `fixtures/JSONTestSuite`.
+//!
+//! Files starting with `y_` have to parse, ones starting with `n_` have
+//! to fail and ones starting with `i_` can do either (but can't panic).
+
+use std::{fs, io, path::Path};
+
+use json_proc::{from_slice, Dialect, JsonDoc, JsonReader, ParseOptions, ToJson};
+
+/// Gives the reader one byte at a time, so every token crosses a read.
+struct Trickle<'a>(&'a [u8]);
+
+impl io::Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some((&first, rest)) = self.0.split_first() else {
+            return Ok(0);
+        };
+        buf[0] = first;
+        self.0 = rest;
+        Ok(1)
+    }
+}
+
+/// Reads every event of `json` with `options`, returning the first error.
+fn read_events(json: &[u8], options: ParseOptions) -> io::Result<()> {
+    let mut reader = JsonReader::new(Trickle(json)).with_options(options);
+    while reader.next_event()?.is_some() {}
+    Ok(())
+}
+
+#[test]
+fn test_json_test_suite() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/JSONTestSuite");
+    let mut failures = Vec::new();
+    let mut count = 0;
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let json = fs::read(&path).unwrap();
+        let result = from_slice(&json);
+        count += 1;
+        // Indexing has to agree with parsing, on everything.
+        if let Ok(json) = std::str::from_utf8(&json) {
+            match (JsonDoc::parse(json), &result) {
+                (Ok(doc), Ok(value)) if doc.root().to_value() != *value => {
+                    failures.push(format!("{name}: indexed as {}", doc.root().to_value()))
+                }
+                (Ok(_), Err(error)) => failures.push(format!("{name}: indexed, but parsing failed with {error}")),
+                (Err(error), Ok(_)) => failures.push(format!("{name}: parsed, but indexing failed with {error}")),
+                _ => {}
+            }
+        }
+        // So does streaming, with the same errors (it doesn't turn
+        // numbers into anything, so it can't find them out of range).
+        match (read_events(&json, ParseOptions::new()), &result) {
+            (Ok(()), Err(error)) if !error.message().contains("out of range") => {
+                failures.push(format!("{name}: streamed, but parsing failed with {error}"))
+            }
+            (Err(error), Ok(_)) => failures.push(format!("{name}: parsed, but streaming failed with {error}")),
+            (Err(error), Err(expected)) if error.to_string() != expected.to_string() => {
+                failures.push(format!("{name}: streaming failed with {error} instead of {expected}"))
+            }
+            _ => {}
+        }
+        // JSON has to read the same in the lenient dialects.
+        for dialect in [Dialect::Jsonc, Dialect::Json5] {
+            let lenient = ParseOptions::new().dialect(dialect).parse_slice(&json);
+            if name.starts_with("y_") && lenient != result {
+                failures.push(format!("{name}: read as {lenient:?} in {dialect:?}"));
+            }
+        }
+        // And streaming JSONC, which a few of the `n_` cases are.
+        let jsonc = ParseOptions::new().dialect(Dialect::Jsonc);
+        match (read_events(&json, jsonc), jsonc.parse_slice(&json)) {
+            (Ok(()), Err(error)) if !error.message().contains("out of range") => {
+                failures.push(format!("{name}: streamed as JSONC, but parsing failed with {error}"))
+            }
+            (Err(error), Ok(_)) => failures.push(format!("{name}: parsed as JSONC, but streaming failed with {error}")),
+            (Err(error), Err(expected)) if error.to_string() != expected.to_string() => {
+                failures.push(format!("{name}: streaming JSONC failed with {error} instead of {expected}"))
+            }
+            _ => {}
+        }
+        match (&name[..2], result) {
+            ("y_", Ok(value)) => {
+                // What's parsed has to serialize to JSON that parses to the same thing.
+                let reparsed = from_slice(value.to_json_string().as_bytes());
+                if reparsed.as_ref() != Ok(&value) {
+                    failures.push(format!("{name}: round trip gave {reparsed:?}"));
+                }
+            }
+            ("y_", Err(error)) => failures.push(format!("{name}: {error}")),
+            ("n_", Ok(value)) => failures.push(format!("{name}: parsed as {value}")),
+            _ => {}
+        }
+    }
+    assert_eq!(count, 318, "the fixtures should be the whole suite");
+    assert!(failures.is_empty(), "{} of {count} cases failed:\n{}", failures.len(), failures.join("\n"));
+}
+
+#[test]
+fn test_implementation_defined() {
+    // Numbers that don't fit in a float are errors instead of infinity,
+    // and lone surrogates are errors since a `String` can't hold them.
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/JSONTestSuite");
+    let parses = |name: &str| from_slice(&fs::read(dir.join(name)).unwrap()).is_ok();
+    assert!(parses("i_number_double_huge_neg_exp.json"));
+    assert!(parses("i_number_too_big_pos_int.json"));
+    assert!(!parses("i_structure_500_nested_arrays.json"));
+    assert!(!parses("i_number_real_pos_overflow.json"));
+    assert!(!parses("i_string_invalid_lonely_surrogate.json"));
+    assert!(!parses("i_string_invalid_utf-8.json"));
+    assert!(!parses("i_structure_UTF-8_BOM_empty_object.json"));
+}
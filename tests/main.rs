@@ -13,6 +13,7 @@ mod tests {
     use json_proc::*;
 
     #[derive(ToJson)]
+    #[json(typed)]
     struct Test<T>
     where
         T: Default,
@@ -22,9 +23,11 @@ mod tests {
     }
 
     #[derive(ToJson)]
+    #[json(typed)]
     struct Tuple<T>(T, u32, String);
 
     #[derive(ToJson)]
+    #[json(typed)]
     enum Test2 {
         Hello { hello: String },
         Two(String, u8),
@@ -47,6 +50,12 @@ mod tests {
 
     #[cfg(test)]
     macro_rules! check_tt {
+        // For syntax serde_json doesn't have, check against what it should be equivalent to.
+        ($lhs:tt => $rhs:tt) => {{
+            let lhs = json_like_serde(::json_proc::json!($lhs));
+            let rhs = serde_json_str!($rhs);
+            assert_eq!(lhs, rhs);
+        }};
         ($($tt:tt)+) => {{
             let lhs = json_like_serde(::json_proc::json!($($tt)+));
             let rhs = serde_json_str!($($tt)+);
@@ -66,6 +75,8 @@ mod tests {
     }
 
     #[cfg_attr(test, test)]
+    // "tooBig" is outside of the safe integer range on purpose.
+    #[allow(deprecated)]
     pub fn test() {
         println!("{}", json!({}));
         let value = String::from("ga");
@@ -93,7 +104,7 @@ mod tests {
             ]
             ,
             "e2": false,
-            "fake": format!("Can I see the syntax highlights pls {}", true),
+            "fake": fmt!("Can I see the syntax highlights pls {}", true),
             "es2": format!("hello: {} {hello}", "world!", hello = value),
             "test22": strc,
             "test2Enum": [
@@ -104,7 +115,7 @@ mod tests {
             "a_null": null,
             value: value,
             "biggol Tuple": (1,2,3,4u128,5,-5208314976i64, 0usize, 184729163128763821312i128),
-            "tooBig": 98237912693271637
+            "tooBig": 98237912693271637i64
         });
         let dur = start.elapsed();
         println!("{finished}\nTook: {dur:?}");
@@ -187,4 +198,1089 @@ mod tests {
             "text": String::from("The answer is"),
         })
     }
+
+    #[derive(ToJson)]
+    #[json(transparent)]
+    struct Wrapper {
+        inner: Vec<u8>,
+    }
+
+    #[derive(ToJson)]
+    #[json(transparent)]
+    struct Id<T>(u64, std::marker::PhantomData<T>);
+
+    struct Amount(i64);
+
+    impl std::fmt::Display for Amount {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}.{:02} \"USD\"", self.0 / 100, self.0 % 100)
+        }
+    }
+
+    #[derive(ToJson)]
+    struct Order {
+        #[json(as_string)]
+        id: u64,
+        #[json(display)]
+        total: Amount,
+    }
+
+    #[derive(ToJson)]
+    enum Event {
+        Paid(#[json(display)] Amount),
+    }
+
+    #[test]
+    fn test_transparent() {
+        assert_eq!(Wrapper { inner: vec![1, 2] }.to_json_string(), "[1,2]");
+        assert_eq!(Id::<Order>(7, std::marker::PhantomData).to_json_string(), "7");
+    }
+
+    #[test]
+    fn test_as_string() {
+        let order = Order { id: 42, total: Amount(1250) };
+        assert_eq!(
+            json_like_serde(order.to_json_string()),
+            serde_json_str!({ "id": "42", "total": "12.50 \"USD\"" })
+        );
+        assert_eq!(Event::Paid(Amount(5)).to_json_string(), r#""0.05 \"USD\"""#);
+    }
+
+    #[derive(ToJson)]
+    #[json(repr)]
+    enum Status {
+        Ok = 200,
+        NotFound = 404,
+        Gone,
+    }
+
+    #[derive(ToJson)]
+    #[json(repr)]
+    #[repr(u8)]
+    enum Opcode {
+        Ping,
+        Pong,
+        Close = 8,
+    }
+
+    #[derive(ToJson)]
+    enum Level {
+        Low,
+        High,
+    }
+
+    #[test]
+    fn test_repr() {
+        assert_eq!(json!([Status::Ok, Status::NotFound, Status::Gone]), "[200,404,405]");
+        assert_eq!(json!([Opcode::Ping, Opcode::Pong, Opcode::Close]), "[0,1,8]");
+        assert_eq!(json!([Level::Low, Level::High]), r#"["Low","High"]"#);
+    }
+
+    /// Doesn't implement `ToJson`.
+    struct NotJson;
+
+    #[derive(ToJson)]
+    struct Borrowed<'a, T, const N: usize> {
+        name: &'a str,
+        items: [T; N],
+    }
+
+    #[derive(ToJson)]
+    struct Shared<T, M> {
+        value: std::sync::Arc<T>,
+        marker: std::marker::PhantomData<M>,
+    }
+
+    trait Named {
+        fn name(&self) -> String;
+    }
+
+    impl Named for u8 {
+        fn name(&self) -> String {
+            format!("u8 #{self}")
+        }
+    }
+
+    #[derive(ToJson)]
+    #[json(bound = "")]
+    struct Unbounded<T> {
+        count: usize,
+        #[json(as_string)]
+        label: std::borrow::Cow<'static, str>,
+        marker: std::marker::PhantomData<fn() -> T>,
+    }
+
+    struct Label<T>(T);
+
+    impl<T: Named> std::fmt::Display for Label<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&self.0.name())
+        }
+    }
+
+    #[derive(ToJson)]
+    struct Labeled<T> {
+        #[json(as_string, bound = "T: Named")]
+        label: Label<T>,
+    }
+
+    #[derive(ToJson, FromJson, Debug, PartialEq)]
+    struct Tree<T> {
+        value: T,
+        children: Vec<Tree<T>>,
+    }
+
+    #[test]
+    fn test_generics() {
+        let name = String::from("nums");
+        let borrowed = Borrowed { name: &name, items: [1u8, 2, 3] };
+        assert_eq!(borrowed.to_json_string(), r#"{"name":"nums","items":[1,2,3]}"#);
+
+        let shared = Shared::<_, NotJson> {
+            value: std::sync::Arc::new(vec![true]),
+            marker: std::marker::PhantomData,
+        };
+        assert_eq!(shared.to_json_string(), r#"{"value":[true],"marker":null}"#);
+
+        let unbounded = Unbounded::<NotJson> {
+            count: 1,
+            label: "one".into(),
+            marker: std::marker::PhantomData,
+        };
+        assert_eq!(unbounded.to_json_string(), r#"{"count":1,"label":"one","marker":null}"#);
+
+        assert_eq!(Labeled { label: Label(3u8) }.to_json_string(), r#"{"label":"u8 #3"}"#);
+
+        // Recursive types only need their parameters bounded.
+        let tree = Tree { value: 1u8, children: vec![Tree { value: 2, children: Vec::new() }] };
+        let json = tree.to_json_string();
+        assert_eq!(json, r#"{"value":1,"children":[{"value":2,"children":[]}]}"#);
+        assert_eq!(Tree::<u8>::from_json_str(&json).unwrap(), tree);
+    }
+
+    #[test]
+    fn test_spread() {
+        let base = Test { yes: String::from("{\"a\": [1, \"}\"]}"), test: 1u8 };
+        let spread = json!({
+            "first": 0,
+            ..base,
+            "test": 2,
+            ..json!({ "nested": { "deep": [1, 2] } }),
+            ..std::collections::BTreeMap::<String, u8>::new(),
+        });
+        assert_eq!(
+            json_like_serde(spread),
+            serde_json_str!({
+                "first": 0,
+                "yes": "{\"a\": [1, \"}\"]}",
+                "test": 2,
+                "nested": { "deep": [1, 2] }
+            })
+        );
+
+        // Keys are the same however they're escaped.
+        let escaped = Json::try_from(r#"{ "\u0061": 2, "b\"": 3, "a\/": 4 }"#).unwrap();
+        let key = "b\"";
+        assert_eq!(json!({ "a": 1, [key]: 0, ..escaped, "a/": 5 }), r#"{"a":2,"b\"":3,"a/":5}"#);
+        let many = (0..100).map(|i| (i.to_string(), i)).collect::<std::collections::BTreeMap<_, _>>();
+        let merged = json!({ "0": null, ..many, "99": null }).into_string();
+        assert_eq!(from_str(&merged).unwrap().as_object().unwrap().len(), 100);
+        assert!(merged.starts_with(r#"{"0":0,"1":1,"10":10,"#) && merged.ends_with(r#""99":null}"#));
+
+        let items = vec![String::from("a,b"), String::from("]")];
+        assert_eq!(
+            json_like_serde(json!([..items, "c", ..Vec::<u8>::new()])),
+            serde_json_str!(["a,b", "]", "c"])
+        );
+        assert_eq!(json!([..Vec::<u8>::new()]), "[]");
+    }
+
+    #[test]
+    fn test_optional_entries() {
+        let some = Some(vec![1u8]);
+        let none = None::<u8>;
+        let flag = true;
+        check_tt!({
+            "kept"?: some,
+            "removed"?: none,
+            "plain": none,
+        } => {
+            "kept": [1],
+            "plain": null,
+        });
+        assert_eq!(
+            json!({
+                if flag => "mode": "on",
+                if !flag => "mode": "off",
+                "gone": undefined,
+                "list": [undefined],
+            }),
+            r#"{"mode":"on","list":[null]}"#
+        );
+        assert_eq!(json!({ "a"?: none, if !flag => "b": 1 }), "{}");
+        assert_eq!(json!({ ..Test { yes: String::new(), test: 0 }, "test"?: some }), r#"{"yes":"","test":[1]}"#);
+    }
+
+    #[test]
+    fn test_comprehensions() {
+        let items = vec![(1u32, "one"), (2, "two"), (3, "three")];
+        check_tt!({
+            "all": [for (id, name) in &items => { "id": id, "name": name, "tags": [name, id] }],
+            "odd": [0, for (id, _) in &items if id % 2 == 1 => id, 4],
+            "none": [for item in &items if false => item.0],
+            "nested": [for (id, _) in &items => [for n in 0..*id => n]],
+        } => {
+            "all": [
+                { "id": 1, "name": "one", "tags": ["one", 1] },
+                { "id": 2, "name": "two", "tags": ["two", 2] },
+                { "id": 3, "name": "three", "tags": ["three", 3] },
+            ],
+            "odd": [0, 1, 3, 4],
+            "none": [],
+            "nested": [[0], [0, 1], [0, 1, 2]],
+        });
+    }
+
+    #[test]
+    fn test_keys() {
+        let name = "computed \"key\"";
+        let id = 7;
+        check_tt!({
+            [name]: 1,
+            [format!("{id}-suffix")]: 2,
+            1: "int",
+            2u8: "suffixed int",
+            'c': "char",
+            name,
+            id,
+        } => {
+            "computed \"key\"": 1,
+            "7-suffix": 2,
+            "1": "int",
+            "2": "suffixed int",
+            "c": "char",
+            "name": "computed \"key\"",
+            "id": 7,
+        });
+        let map = std::collections::BTreeMap::from([("a\"b", 1)]);
+        check_tt!({ "map": map } => { "map": { "a\"b": 1 } });
+    }
+
+    #[test]
+    fn test_exprs_as_keys() {
+        // The tests turn on `exprs-as-keys`, where a variable is a key
+        // with or without the brackets.
+        let key = String::from("k");
+        let r#type = "kind";
+        check_tt!({ key: 1, r#type: 2, key } => { "k": 1, "kind": 2, "key": "k" });
+        check_tt!({ [key]: 1, [r#type]: 2 } => { "k": 1, "kind": 2 });
+        check_tt!({ key.to_uppercase(): 1, [key.len()]: 2 } => { "K": 1, "1": 2 });
+    }
+
+    #[test]
+    fn test_dotted_keys() {
+        let host = "localhost";
+        let debug = None::<bool>;
+        check_tt!({
+            @"server.http.port": 8080,
+            "plain.key": 0,
+            @"server.http.host": host,
+            @"server.name": "main",
+            @"server.debug"?: debug,
+            if true => @"logging.level": "info",
+            @"x": [1],
+        } => {
+            "server": {
+                "http": { "port": 8080, "host": "localhost" },
+                "name": "main",
+            },
+            "plain.key": 0,
+            "logging": { "level": "info" },
+            "x": [1],
+        });
+
+        // Objects made up for dotted keys are left out if nothing's in them.
+        let none = None::<u8>;
+        check_tt!({ if false => @"a.b": 1, @"x.y"?: none, @"x.z.w"?: none } => {});
+        check_tt!({ @"a.b"?: Some(1), @"a.c.d"?: none, "z": 0 } => { "a": { "b": 1 }, "z": 0 });
+        assert_eq!(json!({ ..json!({ "a": 0 }), @"a.b"?: none }), r#"{"a":0}"#);
+        assert_eq!(json_value!({ if false => @"a.b": 1, @"x.y"?: none }), json_value!({}));
+        assert_eq!(json_value!({ @"a.b"?: none, @"a.c": 2 }), json_value!({ "a": { "c": 2 } }));
+
+        // Like other keys, dotted ones can repeat if they're guarded.
+        let verbose = false;
+        check_tt!({
+            if verbose => @"log.level": "debug",
+            if !verbose => @"log.level": "info",
+            #[cfg(test)]
+            @"log.target": "test",
+            #[cfg(not(test))]
+            @"log.target": "main",
+        } => { "log": { "level": "info", "target": "test" } });
+    }
+
+    #[test]
+    fn test_cfg_entries() {
+        let items = [1, 2];
+        check_tt!({
+            #[cfg(test)]
+            "test": true,
+            #[cfg(not(test))]
+            "test": false,
+            #[cfg(any())]
+            "never": 0,
+            "array": [
+                #[cfg(any())] 0,
+                1,
+                #[cfg(all())] 2,
+                #[cfg(any())] ..items,
+                #[cfg(any())] for item in items => item,
+            ],
+            #[cfg(any())]
+            "last": null,
+            "items": [..items],
+        } => {
+            "test": true,
+            "array": [1, 2],
+            "items": [1, 2],
+        });
+        check_tt!([#[cfg(any())] 1] => []);
+        check_tt!({ #[cfg(any())] "only": 1 } => {});
+        check_tt!({ #[cfg(any())] @"c.d": 1, "z": 0 } => { "z": 0 });
+        check_tt!({ #[cfg(any())] @"c.d": 1, #[cfg(all())] @"c.e": 2 } => { "c": { "e": 2 } });
+        assert_eq!(json_value!({ #[cfg(any())] @"c.d": 1 }), json_value!({}));
+    }
+
+    #[test]
+    fn test_fmt_strings() {
+        let id = 7;
+        let name = "Robert \"Bobby\" Tables";
+        check_tt!({
+            "message": fmt!("user {id} logged in"),
+            "quoted": fmt!("{name}\n{}", '\\'),
+            "nested": [fmt!("{:03}", id), { "inner": fmt!("{id:?}") }],
+            [fmt!("user-{id}")]: true,
+        } => {
+            "message": "user 7 logged in",
+            "quoted": "Robert \"Bobby\" Tables\n\\",
+            "nested": ["007", { "inner": "7" }],
+            "user-7": true,
+        });
+        let extra = std::collections::BTreeMap::from([("x", 1)]);
+        check_tt!({ "a": fmt!("{}", "b"), ..extra } => { "a": "b", "x": 1 });
+    }
+
+    #[test]
+    fn test_json_write() {
+        use std::{fmt, io};
+
+        let id = 3;
+        let mut string = String::from("event: ");
+        let result: fmt::Result = json_write!(&mut string, { "id": id, "empty": {}, "list": [..[1, 2]] });
+        assert!(result.is_ok());
+        json_write!(&mut string, []).unwrap();
+        assert_eq!(string, r#"event: {"id":3,"empty":{},"list":[1,2]}[]"#);
+
+        // The buffer can already end with a comma.
+        let mut string = String::from("[1,");
+        json_write!(&mut string, {}).unwrap();
+        assert_eq!(string, "[1,{}");
+
+        let mut bytes = vec![0xff];
+        let result: io::Result<()> = json_write!(&mut bytes, fmt!("{id}"));
+        result.unwrap();
+        assert_eq!(bytes, b"\xff\"3\"");
+
+        struct Framed(u32);
+        impl fmt::Display for Framed {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("<")?;
+                json_write!(f, { "frame": self.0 })?;
+                f.write_str(">")
+            }
+        }
+        assert_eq!(Framed(4).to_string(), r#"<{"frame":4}>"#);
+
+        let mut cursor = io::Cursor::new(Vec::new());
+        json_write!(&mut cursor, [null, "a"]).unwrap();
+        let writer: &mut dyn io::Write = &mut cursor;
+        json_write!(writer, 1).unwrap();
+        assert_eq!(cursor.into_inner(), br#"[null,"a"]1"#);
+    }
+
+    #[test]
+    fn test_json_lazy() {
+        use std::cell::Cell;
+
+        struct Counted<'a>(&'a Cell<u32>);
+        impl ToJson for Counted<'_> {
+            fn to_json_string(&self) -> String {
+                self.0.set(self.0.get() + 1);
+                self.0.get().to_string()
+            }
+        }
+
+        let count = Cell::new(0);
+        let items = vec![1, 2];
+        let lazy = json_lazy!({ "count": Counted(&count), "items": [..items, for i in &items => i * 10] });
+        assert_eq!(count.get(), 0);
+        assert_eq!(lazy.to_string(), r#"{"count":1,"items":[1,2,10,20]}"#);
+        assert_eq!(count.get(), 1);
+
+        let inner = json_lazy!(["a", fmt!("{}", count.get())]);
+        check_tt!({
+            "lazy": lazy,
+            "inner": [inner, Some(&inner)],
+            "nested": json_lazy!({ "inner": inner }),
+            ..json_lazy!({ "spread": null }),
+        } => {
+            "lazy": { "count": 2, "items": [1, 2, 10, 20] },
+            "inner": [["a", "2"], ["a", "2"]],
+            "nested": { "inner": ["a", "2"] },
+            "spread": null,
+        });
+        // The count is only read, so it's the same every time.
+        count.set(5);
+        assert_eq!(format!("{inner}"), r#"["a","5"]"#);
+
+        let mut buf = String::from("log: ");
+        json_write!(&mut buf, { "lazy": inner }).unwrap();
+        assert_eq!(buf, r#"log: {"lazy":["a","5"]}"#);
+    }
+
+    #[test]
+    fn test_json_type() {
+        let inner = json!({ "a": [1, "x, y"] });
+        let outer = json!({ "inner": inner, "list": [inner.as_json_str(), &inner] });
+        assert_eq!(
+            outer,
+            r#"{"inner":{"a":[1,"x, y"]},"list":[{"a":[1,"x, y"]},{"a":[1,"x, y"]}]}"#
+        );
+
+        let pretty = json!({ "a": { "b": [1, 2], "c": {}, "d": [] }, "s": "{ [\"x\": 1] }" }).pretty();
+        assert_eq!(
+            pretty,
+            "{\n  \"a\": {\n    \"b\": [\n      1,\n      2\n    ],\n    \"c\": {},\n    \"d\": []\n  },\n  \"s\": \"{ [\\\"x\\\": 1] }\"\n}"
+        );
+        assert_eq!(pretty.minify(), r#"{"a":{"b":[1,2],"c":{},"d":[]},"s":"{ [\"x\": 1] }"}"#);
+        assert_eq!(Json::try_from("{ \"\\u0041\": [ ] }").unwrap().minify(), r#"{"A":[]}"#);
+        // Text that isn't JSON is left alone.
+        assert_eq!(Json::from_string_unchecked("]".into()).pretty(), "]");
+        assert_eq!(Json::from_string_unchecked("[1, 2]]".into()).minify(), "[1, 2]]");
+
+        let parsed = Json::parse(String::from(" [1, {\"a\": null}] ")).unwrap();
+        assert_eq!(parsed, " [1, {\"a\": null}] ");
+        assert_eq!(Json::try_from(String::from("tru")).unwrap_err().to_string(), "expected a value at line 1, column 1");
+        let borrowed: &JsonStr = "\"s\"".try_into().unwrap();
+        assert_eq!(borrowed, "\"s\"");
+        assert!(<&JsonStr>::try_from("1 2").is_err());
+        assert!(Json::try_from("{'a': 1}").is_err());
+
+        assert_eq!(vec![1, 2].to_json(), "[1,2]");
+        assert_eq!("a\"b".to_json().into_bytes(), br#""a\"b""#);
+        assert_eq!(String::from(json!(null)), "null");
+        let borrowed: &JsonStr = &inner;
+        assert_eq!(borrowed.to_owned(), inner);
+        assert_eq!(format!("{borrowed} {inner:?}"), r#"{"a":[1,"x, y"]} "{\"a\":[1,\"x, y\"]}""#);
+    }
+
+    #[test]
+    fn test_include_json() {
+        const DEFAULTS: &str = include_json!("tests/fixtures/defaults.json");
+        assert_eq!(
+            DEFAULTS,
+            r#"{"name":"server","port":8080,"ratio":-1.5e3,"tags":["a","bé","😀"],"nested":{"empty":{},"list":[]},"escaped":"line\nbreak \"quoted\" / {braces}","enabled":true,"missing":null}"#
+        );
+
+        let mut calls = 0;
+        let mut id = || {
+            calls += 1;
+            7
+        };
+        let user = Test { yes: String::from("y"), test: 1u8 };
+        let json = include_json!("tests/fixtures/template.json", user = user, name = "Ann", id = id(), r#type = "admin");
+        assert_eq!(calls, 1);
+        assert_eq!(
+            json,
+            r#"{"user":{"yes":"y","test":1},"greeting":"Hello, Ann! {not a placeholder} ${}","ids":[7,7,3],"static":{"a":[1,2]},"type":"admin"}"#
+        );
+    }
+
+    #[test]
+    fn test_schema() {
+        let id = 12u64;
+        let name = "ann";
+        let tags = ["a", "b"];
+        let value = json!(schema = "tests/fixtures/event.schema.json", {
+            "kind": "created",
+            "id": id,
+            "score": 1.5,
+            "user": { "name": name, "admin"?: None::<bool> },
+            "tags": [for tag in tags => tag, "c"],
+        });
+        assert_eq!(
+            value,
+            r#"{"kind":"created","id":12,"score":1.5,"user":{"name":"ann"},"tags":["a","b","c"]}"#
+        );
+        let lazy = json_lazy!(crate = ::json_proc, schema = "tests/fixtures/event.schema.json", {
+            "kind": "deleted",
+            "id": -1,
+            "score": None::<f64>,
+        });
+        assert_eq!(lazy.to_string(), r#"{"kind":"deleted","id":-1,"score":null}"#);
+
+        // Floats with no fractional part are integers, like in JSON Schema.
+        let id = 3.0f64;
+        let value = json!(schema = "tests/fixtures/event.schema.json", { "kind": "created", "id": id, "score": 2 });
+        assert_eq!(value, r#"{"kind":"created","id":3,"score":2}"#);
+        assert_eq!(json!(schema = "tests/fixtures/event.schema.json", { "kind": "created", "id": 4.0 }), r#"{"kind":"created","id":4}"#);
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "json! value at `/id` doesn't match the schema: expected integer, found number `1.5`"))]
+    fn test_schema_runtime_float() {
+        let id = 1.5f64;
+        let _ = json!(schema = "tests/fixtures/event.schema.json", { "kind": "created", "id": id });
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "json! value at `/user/name` doesn't match the schema"))]
+    fn test_schema_runtime() {
+        let name = 5;
+        let _ = json!(schema = "tests/fixtures/event.schema.json", {
+            "kind": "created",
+            "id": 1,
+            "user": { "name": name },
+        });
+    }
+
+    #[test]
+    fn test_typed() {
+        #[derive(ToJson)]
+        #[json(typed)]
+        struct Event {
+            r#type: &'static str,
+            id: u32,
+            #[cfg(test)]
+            debug: bool,
+        }
+
+        let id = 8;
+        assert_eq!(
+            json!(as Event { "type": "click", id, #[cfg(test)] "debug": true }),
+            r#"{"type":"click","id":8,"debug":true}"#
+        );
+        assert_eq!(json!(as Test<u8> { "yes": fmt!("{id}"), "test": 1 }), r#"{"yes":"8","test":1}"#);
+        assert_eq!(json!(as Test2::Hello { hello: "hi" }), r#"{"hello":"hi"}"#);
+
+        let lazy = json_lazy!(as Tuple::<Vec<u8>> { 0: [1, 2], 1: 2, 2: String::new() });
+        assert_eq!(lazy.to_string(), r#"[[1,2],2,""]"#);
+        // Like everything else in `json_lazy!`, the fields are only
+        // evaluated when it's written, every time it is.
+        let count = std::cell::Cell::new(0u8);
+        let next = || {
+            count.set(count.get() + 1);
+            count.get()
+        };
+        let lazy = json_lazy!(as Test<u8> { "yes": String::new(), "test": next() });
+        assert_eq!(count.get(), 0);
+        assert_eq!(lazy.to_string(), r#"{"yes":"","test":1}"#);
+        assert_eq!(json!([lazy, lazy]), r#"[{"yes":"","test":2},{"yes":"","test":3}]"#);
+
+        // Keys are what the fields are renamed to.
+        #[derive(ToJson)]
+        #[json(typed)]
+        struct Renamed {
+            #[json(rename = "userId")]
+            user_id: u32,
+            #[json(rename = "display name")]
+            name: &'static str,
+            #[json(skip)]
+            #[allow(dead_code)]
+            cache: u8,
+        }
+        assert_eq!(
+            json!(as Renamed { "userId": 1, "display name": "ann", "cache": 0 }),
+            r#"{"userId":1,"display name":"ann"}"#
+        );
+
+        // The variant is found through the enum, whatever it's called,
+        // and the struct through its module.
+        type Alias = Test2;
+        assert_eq!(json!(as Alias::Hello { hello: "hi" }), r#"{"hello":"hi"}"#);
+        #[allow(non_snake_case)]
+        mod Shapes {
+            #[derive(json_proc::ToJson)]
+            #[json(typed)]
+            pub struct Point {
+                pub x: i32,
+            }
+        }
+        assert_eq!(json!(as Shapes::Point { "x": 1 }), r#"{"x":1}"#);
+
+        // Values are evaluated where the macro is, and nothing is moved
+        // out of the type, so it can implement `Drop`.
+        #[derive(ToJson)]
+        #[json(typed)]
+        struct Guard {
+            name: String,
+        }
+        impl Drop for Guard {
+            fn drop(&mut self) {}
+        }
+        let name = || Some(String::from("lock"));
+        let guard = || Some(json!(as Guard { "name": name()? }));
+        assert_eq!(guard().unwrap(), r#"{"name":"lock"}"#);
+    }
+
+    #[test]
+    fn test_derive_without_typed() {
+        // Without `#[json(typed)]`, nothing but the impl is generated,
+        // so types that couldn't be built field by field still work.
+        #[derive(ToJson)]
+        struct Guard {
+            name: String,
+        }
+        impl Drop for Guard {
+            fn drop(&mut self) {}
+        }
+        #[derive(ToJson)]
+        struct Unsized<T: ?Sized> {
+            len: u8,
+            data: T,
+        }
+
+        let guard = Guard { name: String::from("lock") };
+        assert_eq!(guard.to_json_string(), r#"{"name":"lock"}"#);
+        let data: &Unsized<[u8]> = &Unsized { len: 2, data: [1, 2] };
+        assert_eq!(data.to_json_string(), r#"{"len":2,"data":[1,2]}"#);
+    }
+
+    #[test]
+    fn test_value() {
+        let extra = json_value!({ "b": 2, "a": null });
+        let maybe: Option<&str> = None;
+        let name = "ann";
+        let mut value = json_value!({
+            "a": 1,
+            ..extra,
+            [name]: [for i in 0..3 if i != 1 => i, ..vec![true]],
+            "opt"?: maybe,
+            "text": fmt!("hi {name}"),
+            "nested": { "deep": [1.5, "x"] },
+        });
+        assert_eq!(value.to_string(), r#"{"a":null,"b":2,"ann":[0,2,true],"text":"hi ann","nested":{"deep":[1.5,"x"]}}"#);
+        assert_eq!(value.to_json(), json!({ "a": null, "b": 2, "ann": [0, 2, true], "text": "hi ann", "nested": { "deep": [1.5, "x"] } }));
+
+        assert!(value["a"].is_null());
+        assert_eq!(value["b"], 2);
+        assert_eq!(value["ann"][2], true);
+        assert_eq!(value["nested"]["deep"][0], 1.5);
+        assert_eq!(value["text"], "hi ann");
+        assert!(value["missing"][3].is_null());
+        assert_eq!(value.get("ann").and_then(|ann| ann.get(&1)).and_then(Value::as_u64), Some(2));
+        assert_eq!(value.get("missing"), None);
+
+        value["a"]["created"] = Value::from("yes");
+        value["b"] = Value::from(-3i8);
+        *value.get_mut("ann").unwrap().get_mut(&0).unwrap() = Value::from(Some('c'));
+        if let Some(map) = value.as_object_mut() {
+            map.remove("nested");
+            map.insert("text", Value::from(vec![f64::NAN]));
+        }
+        assert_eq!(value.to_string(), r#"{"a":{"created":"yes"},"b":-3,"ann":["c",2,true],"text":[null]}"#);
+
+        // Objects are equal whatever order their keys are in.
+        assert_eq!(json_value!({ "x": 1, "y": [] }), json_value!({ "y": [], "x": 1 }));
+        assert_ne!(json_value!([1, 2]), json_value!([2, 1]));
+        assert_ne!(json_value!(1), json_value!(1.0));
+
+        let map: Value = [("k", 1)].into_iter().collect();
+        assert_eq!(map, json_value!({ "k": 1 }));
+        let array: Value = (1..=2).collect();
+        assert_eq!(array, json_value!([1, 2]));
+        assert_eq!(Value::from(u128::MAX).as_f64(), Some(u128::MAX as f64));
+        assert_eq!(Test { yes: "1".into(), test: 2 }.to_value(), json_value!({ "yes": "1", "test": 2 }));
+        assert_eq!(json_value!(as Test<u8> { "yes": "3", "test": 4 })["test"], 4);
+
+        // Infinite and NaN floats are `null`, whether written or converted.
+        #[derive(ToJson)]
+        struct Floats {
+            a: f32,
+            b: f64,
+        }
+        let floats = Floats { a: f32::INFINITY, b: f64::NAN };
+        assert_eq!(floats.to_json_string(), r#"{"a":null,"b":null}"#);
+        assert_eq!(floats.to_value(), json_value!({ "a": null, "b": null }));
+        assert_eq!(json_value!([f64::NEG_INFINITY, 0.5]), json_value!([null, 0.5]));
+
+        // Big maps are indexed, and keep their order through removals.
+        let mut map = (0..100).map(|i| (format!("k{i}"), i)).collect::<Map>();
+        assert_eq!(map.insert("k50", Value::from("x")), Some(Value::from(50)));
+        assert_eq!(map.remove("k10"), Some(Value::from(10)));
+        assert_eq!(map.remove("k10"), None);
+        *map.entry("new") = Value::from(true);
+        assert_eq!((map.len(), map.get("k50"), map.get("k99")), (100, Some(&Value::from("x")), Some(&Value::from(99))));
+        assert_eq!(map.keys().position(|key| key == "k11"), Some(10));
+        assert_eq!(map.keys().last().map(String::as_str), Some("new"));
+        let wide = format!("{{{}}}", (0..50_000).map(|i| format!(r#""{i}":{i}"#)).collect::<Vec<_>>().join(","));
+        assert_eq!(from_str(&wide).unwrap()["49999"], 49999);
+    }
+
+    #[test]
+    fn test_parse() {
+        let id = 3;
+        let json = json!({ "id": id, "tags": ["a", fmt!("b{id}")], "ratio": 2.5, "none": null });
+        assert_eq!(from_str(json.as_str()).unwrap(), json_value!({ "id": 3, "tags": ["a", "b3"], "ratio": 2.5, "none": null }));
+        assert_eq!(from_str(" \"\\u00e9\\ud83d\\ude00\" ").unwrap(), "é😀");
+        assert_eq!(from_str("-12").unwrap().as_i64(), Some(-12));
+        assert_eq!(from_str("1e2").unwrap().to_string(), "100.0");
+
+        let error = from_str("[1, {\"a/b\": [true, nope]}]").unwrap_err();
+        assert_eq!(error.message(), "expected a value");
+        assert_eq!((error.line(), error.column(), error.offset()), (1, 20, 19));
+        assert_eq!(error.path(), "/1/a~1b/1");
+
+        let error = from_str("{\n  \"é\": \"\\x\"\n}").unwrap_err();
+        assert_eq!(error.to_string(), "invalid escape at line 2, column 10 in `/é`");
+        assert_eq!(from_str("[1,]").unwrap_err().to_string(), "expected a value at line 1, column 4 in `/1`");
+        assert_eq!(from_str("{} {}").unwrap_err().to_string(), "trailing characters after the value at line 1, column 4");
+        assert_eq!(from_slice(b"[\"\xff\"]").unwrap_err().to_string(), "invalid UTF-8 in string at line 1, column 3 in `/0`");
+
+        let duplicates = r#"{ "a": 1, "b": 2, "a": 3 }"#;
+        assert_eq!(from_str(duplicates).unwrap().to_string(), r#"{"a":3,"b":2}"#);
+        let first = ParseOptions::new().duplicate_keys(DuplicateKeys::First);
+        assert_eq!(first.parse(duplicates).unwrap().to_string(), r#"{"a":1,"b":2}"#);
+        let error = ParseOptions::new().duplicate_keys(DuplicateKeys::Error).parse(duplicates).unwrap_err();
+        assert_eq!((error.message(), error.offset(), error.path()), ("duplicate key", 18, "/a"));
+
+        let deep = format!("{}{}", "[".repeat(200), "]".repeat(200));
+        assert_eq!(from_str(&deep).unwrap_err().message(), "nesting too deep");
+        assert!(ParseOptions::new().max_depth(200).parse(&deep).is_ok());
+        assert!(ParseOptions::new().max_depth(0).parse("[]").is_err());
+        assert!(ParseOptions::new().max_depth(0).parse("1").is_ok());
+    }
+
+    #[derive(ToJson, FromJson, Debug, PartialEq)]
+    #[json(deny_unknown_fields)]
+    struct Account {
+        #[json(rename = "type")]
+        kind: String,
+        nickname: Option<String>,
+        #[json(default = "default_limit")]
+        limit: u32,
+        #[json(as_string)]
+        id: u64,
+        #[json(skip)]
+        cached: Vec<u8>,
+        history: Vec<(u8, bool)>,
+    }
+
+    fn default_limit() -> u32 {
+        100
+    }
+
+    #[derive(ToJson, FromJson, Debug, PartialEq)]
+    #[json(tag = "kind", content = "data")]
+    enum Action {
+        #[json(rename = "start")]
+        Start,
+        Move(i32, i32),
+        Say { text: String },
+    }
+
+    #[derive(ToJson, FromJson, Debug, PartialEq)]
+    enum Untagged {
+        Unit,
+        Pair(u8, u8),
+        Named { name: String },
+        Any(Value),
+    }
+
+    #[derive(ToJson, FromJson, Debug, PartialEq)]
+    struct Unit;
+
+    #[derive(ToJson, FromJson, Debug, PartialEq)]
+    struct Empty {}
+
+    #[derive(FromJson, Debug, PartialEq)]
+    enum Either {
+        A { a: u32 },
+        B { b: u32 },
+    }
+
+    #[test]
+    fn test_from_json() {
+        let account = Account {
+            kind: "admin".into(),
+            nickname: None,
+            limit: 5,
+            id: 7,
+            cached: vec![],
+            history: vec![(1, true)],
+        };
+        let json = account.to_json_string();
+        assert_eq!(json, r#"{"type":"admin","nickname":null,"limit":5,"id":"7","history":[[1,true]]}"#);
+        assert_eq!(Account::from_json_str(&json).unwrap(), account);
+
+        let account = Account::from_json_str(r#"{ "id": "9", "history": [], "type": "user" }"#).unwrap();
+        assert_eq!((account.nickname, account.limit, account.id), (None, 100, 9));
+
+        let error = |json: &str| Account::from_json_str(json).unwrap_err().to_string();
+        assert_eq!(error(r#"{"id": "1", "history": []}"#), "missing field `type` at line 1, column 1");
+        assert_eq!(
+            error(r#"{"type": "a", "id": "1", "history": [], "extra": 1}"#),
+            "unknown field `extra` at line 1, column 41 in `/extra`"
+        );
+        assert_eq!(
+            error(r#"{"type": "a", "id": "x", "history": []}"#),
+            "invalid value `x` at line 1, column 21 in `/id`"
+        );
+        assert_eq!(
+            error(r#"{"type": "a", "id": "1", "history": [[1, true, 2]]}"#),
+            "expected an array of 2 elements, found more at line 1, column 47 in `/history/0/2`"
+        );
+        assert_eq!(
+            error(r#"{"type": "a", "type": "b"}"#),
+            "duplicate field `type` at line 1, column 15 in `/type`"
+        );
+
+        // Structs without fields are their name.
+        assert_eq!(Unit.to_json_string(), r#""Unit""#);
+        assert_eq!(Unit::from_json_str(&Unit.to_json_string()).unwrap(), Unit);
+        assert_eq!(Empty::from_json_str(&Empty {}.to_json_string()).unwrap(), Empty {});
+        assert_eq!(Unit.to_value(), "Unit");
+        assert_eq!(
+            Empty::from_json_str(r#""Unit""#).unwrap_err().to_string(),
+            "expected `\"Empty\"` at line 1, column 1"
+        );
+
+        for action in [Action::Start, Action::Move(-1, 2), Action::Say { text: "hi".into() }] {
+            assert_eq!(Action::from_json_str(&action.to_json_string()).unwrap(), action);
+        }
+        assert_eq!(Action::Start.to_json_string(), r#"{"kind":"start"}"#);
+        assert_eq!(Action::Move(-1, 2).to_json_string(), r#"{"kind":"Move","data":[-1,2]}"#);
+        assert_eq!(
+            Action::from_json_str(r#"{"data": {"text": "hey"}, "kind": "Say"}"#).unwrap(),
+            Action::Say { text: "hey".into() }
+        );
+        assert_eq!(
+            Action::from_json_str(r#"{"kind": "Stop"}"#).unwrap_err().to_string(),
+            "unknown variant `Stop` of `Action` at line 1, column 1"
+        );
+
+        for value in [
+            Untagged::Unit,
+            Untagged::Pair(1, 2),
+            Untagged::Named { name: "n".into() },
+            Untagged::Any(json_value!({ "other": [1] })),
+        ] {
+            assert_eq!(Untagged::from_json_str(&value.to_json_string()).unwrap(), value);
+        }
+        assert_eq!(Untagged::from_json_str(r#""Other""#).unwrap(), Untagged::Any(json_value!("Other")));
+
+        // Variants that don't match don't look back over the JSON before
+        // them, so long arrays of untagged enums read in linear time.
+        let many = format!("[{}]", vec![r#"{"b": 1}"#; 20_000].join(",\n"));
+        assert_eq!(Vec::<Either>::from_json_str(&many).unwrap().len(), 20_000);
+        let error = Vec::<Either>::from_json_str(r#"[{"a": 1}, {"c": 2}]"#).unwrap_err();
+        assert_eq!(error.to_string(), "data did not match any variant of `Either` at line 1, column 12 in `/1`");
+
+        let parsed = <(u8, String, [bool; 2])>::from_json_str(r#"[1, "a", [true, false]]"#).unwrap();
+        assert_eq!(parsed, (1, "a".to_owned(), [true, false]));
+        assert_eq!(
+            <(u8, u8)>::from_json_str("[1]").unwrap_err().to_string(),
+            "expected an array of 2 elements, found 1 at line 1, column 1"
+        );
+        let map = std::collections::BTreeMap::<u8, Option<bool>>::from_json_str(r#"{"1": true, "2": null}"#).unwrap();
+        assert_eq!(map.into_iter().collect::<Vec<_>>(), [(1, Some(true)), (2, None)]);
+    }
+
+    #[test]
+    fn test_json_doc() {
+        let json = r#"{ "a": [0, 1, { "b": null }, "t\u00e9st"], "n": -2.5, "a\/b": true, "dup": 1, "dup": 2 }"#;
+        let doc = JsonDoc::parse(json).unwrap();
+        let a = doc.get("a").unwrap();
+        assert_eq!((a.kind(), a.len()), (ValueKind::Array, 4));
+        assert_eq!(a.get(3).and_then(|value| value.as_str()).as_deref(), Some("tést"));
+        assert!(a.get(2).and_then(|value| value.get("b")).unwrap().is_null());
+        assert_eq!(a.get(1).and_then(|value| value.as_u64()), Some(1));
+        assert!(a.get(4).is_none());
+        assert_eq!(a.get(2).unwrap().raw(), r#"{ "b": null }"#);
+        assert_eq!(doc.get("n").and_then(|value| value.as_f64()), Some(-2.5));
+        assert_eq!(doc.get("n").and_then(|value| value.as_i64()), None);
+        assert_eq!(doc.get("a/b").and_then(|value| value.as_bool()), Some(true));
+        assert!(doc.get(0).is_none());
+        assert_eq!(doc.get("dup").and_then(|value| value.as_u64()), Some(2));
+        assert_eq!(doc.root().to_value(), from_str(json).unwrap());
+
+        let keys = doc.root().members().map(|(key, _)| key).collect::<Vec<_>>();
+        assert_eq!(keys, ["a", "n", "a/b", "dup", "dup"]);
+        let elements = a.elements().map(|value| value.kind()).collect::<Vec<_>>();
+        assert_eq!(elements, [ValueKind::Number, ValueKind::Number, ValueKind::Object, ValueKind::String]);
+
+        let first = ParseOptions::new().duplicate_keys(DuplicateKeys::First).parse_doc(json).unwrap();
+        assert_eq!(first.get("dup").and_then(|value| value.as_u64()), Some(1));
+        let error = ParseOptions::new().duplicate_keys(DuplicateKeys::Error).parse_doc(json).unwrap_err();
+        assert_eq!((error.message(), error.path()), ("duplicate key", "/dup"));
+
+        // Errors are the same as `from_str`'s.
+        for json in ["[1, {\"a\": [true, nope]}]", "[1,]", "{} {}", "\"\\x\""] {
+            assert_eq!(JsonDoc::parse(json).unwrap_err(), from_str(json).unwrap_err());
+        }
+        let error = a.get(3).unwrap().parse::<u8>().unwrap_err();
+        assert_eq!(error.to_string(), "expected a number, found a string at line 1, column 30");
+    }
+
+    #[test]
+    fn test_json_reader() {
+        let json = "{ \"a\": [1, -2.5e3, true, null, \"t\\u00e9st\"],\n  \"skip\": { \"x\": [[]] }, \"b\": {} }";
+        let mut reader = JsonReader::new(json.as_bytes());
+        let mut events = Vec::new();
+        while let Some(event) = reader.next_event().unwrap() {
+            events.push(format!("{event:?}"));
+            if event == json_proc::Event::Key("skip") {
+                reader.skip_value().unwrap();
+                assert_eq!((reader.line(), reader.column()), (2, 24));
+            }
+        }
+        assert_eq!(
+            events.join(" "),
+            r#"StartObject Key("a") StartArray Number("1") Number("-2.5e3") Bool(true) Null String("tést") EndArray Key("skip") Key("b") StartObject EndObject EndObject"#
+        );
+        assert_eq!(reader.offset(), json.len());
+
+        let error = |json: &str| {
+            let mut reader = JsonReader::new(json.as_bytes());
+            loop {
+                match reader.next_event() {
+                    Ok(Some(_)) => {}
+                    Ok(None) => panic!("no error in {json}"),
+                    Err(error) => break error.to_string(),
+                }
+            }
+        };
+        assert_eq!(error(r#"{"a": [1, tru]}"#), "expected a value at line 1, column 11 in `/a/1`");
+        assert_eq!(error("[1 2]"), "expected `,` or `]` at line 1, column 4");
+        assert_eq!(error("[1] 2"), "trailing characters after the value at line 1, column 5");
+        assert_eq!(error(r#"{"a": "\x"}"#), "invalid escape at line 1, column 9 in `/a`");
+
+        // Only values can be skipped.
+        let mut reader = JsonReader::new("[]".as_bytes());
+        reader.next_event().unwrap();
+        assert!(reader.skip_value().is_err());
+
+        // Elements of an array that isn't at the top, and errors in them.
+        let json = r#"{ "points": [[1, 2], [3, 4]], "rest": [[5, 6], [7, "8"]] }"#;
+        let mut reader = JsonReader::new(json.as_bytes());
+        reader.next_event().unwrap();
+        reader.next_event().unwrap();
+        let points = reader.array_elements::<(u8, u8)>().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(points, [(1, 2), (3, 4)]);
+        assert_eq!(reader.next_event().unwrap(), Some(json_proc::Event::Key("rest")));
+        let mut rest = reader.array_elements::<(u8, u8)>().unwrap();
+        assert_eq!(rest.next().unwrap().unwrap(), (5, 6));
+        let error = rest.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "expected a number, found a string at line 1, column 52 in `/rest/1/1`");
+        assert!(rest.next().is_none());
+
+        let mut reader = JsonReader::new(r#"{"a": 1}"#.as_bytes());
+        let error = reader.array_elements::<u8>().err().unwrap();
+        assert_eq!(error.to_string(), "expected an array, found an object at line 1, column 1");
+    }
+
+    #[test]
+    fn test_dialects() {
+        let jsonc = ParseOptions::new().dialect(Dialect::Jsonc);
+        let json5 = ParseOptions::new().dialect(Dialect::Json5);
+        let config = "/* header */ {\n  \"a\": [1, 2,], // two\n  \"b\": {\"c\": null,},\n}\n// end";
+        assert_eq!(from_str(config).unwrap_err().to_string(), "expected a value at line 1, column 1");
+        assert_eq!(jsonc.parse(config).unwrap(), json_value!({ "a": [1, 2], "b": { "c": null } }));
+        assert_eq!(json5.parse(config).unwrap(), jsonc.parse(config).unwrap());
+        assert_eq!(jsonc.parse("[1, /* open").unwrap_err().to_string(), "unexpected end of input at line 1, column 12 in `/1`");
+        assert_eq!(jsonc.parse("[1,,]").unwrap_err().to_string(), "expected a value at line 1, column 4 in `/1`");
+        assert_eq!(jsonc.parse("{a: 1}").unwrap_err().to_string(), "expected a string key at line 1, column 2");
+        assert_eq!(jsonc.parse("['a']").unwrap_err().to_string(), "expected a value at line 1, column 2 in `/0`");
+
+        #[derive(FromJson, Debug, PartialEq)]
+        struct Limits {
+            name: String,
+            mask: u8,
+            offset: i32,
+            ratio: f64,
+            ceiling: f64,
+            floor: f32,
+        }
+        let limits = "{ name: 'it\\'s \"x\"', mask: 0xFF, offset: -0x10, ratio: +.5, ceiling: Infinity, floor: -Infinity, }";
+        let limits = json5.parse_as::<Limits>(limits).unwrap();
+        assert_eq!(limits.name, "it's \"x\"");
+        assert_eq!((limits.mask, limits.offset, limits.ratio), (255, -16, 0.5));
+        assert_eq!((limits.ceiling, limits.floor), (f64::INFINITY, f32::NEG_INFINITY));
+        assert!(json5.parse_as::<f64>("NaN").unwrap().is_nan());
+        assert_eq!(json5.parse_as::<Vec<f64>>("[5., .25e1, 1e2]").unwrap(), [5.0, 2.5, 100.0]);
+
+        // A `Value` can't be infinite, and numbers too big still aren't.
+        assert_eq!(json5.parse("{ a: [NaN] }").unwrap_err().to_string(), "`NaN` isn't a JSON number at line 1, column 7 in `/a/0`");
+        assert_eq!(json5.parse_as::<f64>("1e999").unwrap_err().to_string(), "`1e999` is out of range for f64 at line 1, column 1");
+        assert_eq!(json5.parse_as::<u8>("Infinity").unwrap_err().to_string(), "expected an integer, found `Infinity` at line 1, column 1");
+        assert_eq!(json5.parse_as::<u8>("0x100").unwrap_err().to_string(), "`256` is out of range for u8 at line 1, column 1");
+        assert_eq!(json5.parse("0x").unwrap_err().to_string(), "expected a hex digit at line 1, column 3");
+        assert_eq!(json5.parse("{ 1a: 1 }").unwrap_err().to_string(), "expected a string key at line 1, column 3");
+        assert_eq!(json5.parse("{ $id: { x_1: tru } }").unwrap_err().to_string(), "expected a value at line 1, column 15 in `/$id/x_1`");
+
+        let doc = json5.parse_doc("{ name: 'a\\'b', 'quoted': 0x1F, list: [1,], }").unwrap();
+        assert_eq!(doc.get("name").and_then(|name| name.as_str()).as_deref(), Some("a'b"));
+        assert_eq!(doc.get("quoted").and_then(|value| value.as_u64()), Some(31));
+        assert_eq!(doc.root().members().map(|(key, _)| key).collect::<Vec<_>>(), ["name", "quoted", "list"]);
+        assert_eq!(doc.root().to_value(), json_value!({ "name": "a'b", "quoted": 31, "list": [1] }));
+
+        let normalized = normalize("// c\n{ b: [ 'x', 0x10, -.5, ], a: { }, 'a': null, }").unwrap();
+        assert_eq!(normalized, r#"{"b":["x",16,-0.5],"a":{},"a":null}"#);
+        assert_eq!(jsonc.normalize("[1, /* no */ 2,]").unwrap(), "[1,2]");
+        assert!(jsonc.normalize("[0x1]").is_err());
+        assert_eq!(from_str(&normalized).unwrap(), json5.parse("{ b: ['x', 16, -0.5], a: {}, a: null }").unwrap());
+
+        // The rest of JSON5's escapes, whitespace and identifiers.
+        let strings = "\u{FEFF}[ '\\x41\\0\\v\\a', 'one \\\ntwo \\\r\nthree',\u{A0}'tab\there', \"\\u00e9\\'\" ]";
+        assert_eq!(json5.parse_as::<Vec<String>>(strings).unwrap(), ["A\0\u{0B}a", "one two three", "tab\there", "é'"]);
+        assert_eq!(json5.normalize(strings).unwrap(), r#"["A\u0000\u000ba","one two three","tab\there","é'"]"#);
+        assert_eq!(json5.parse("'\\01'").unwrap_err().to_string(), "invalid escape at line 1, column 3");
+        assert_eq!(json5.parse("'a\nb'").unwrap_err().to_string(), "control character in string at line 1, column 3");
+        assert!(json5.parse("'\\x4'").is_err() && jsonc.parse("\"\\x41\"").is_err());
+        let keys = "{ café: 1, naïve_2: 2, \\u0061b: 3, $_: 4 }";
+        assert_eq!(json5.parse(keys).unwrap(), json_value!({ "café": 1, "naïve_2": 2, "ab": 3, "$_": 4 }));
+        let doc = json5.parse_doc(keys).unwrap();
+        assert_eq!(doc.root().members().map(|(key, _)| key).collect::<Vec<_>>(), ["café", "naïve_2", "ab", "$_"]);
+        assert_eq!(json5.parse("{ \\u0031a: 1 }").unwrap_err().to_string(), "invalid character in key at line 1, column 3");
+        assert_eq!(json5.parse("{ a-b: 1 }").unwrap_err().to_string(), "expected `:` at line 1, column 4");
+
+        // A `JsonReader` reads JSONC, with the same errors, but not JSON5.
+        let events = |options: ParseOptions, json: &str| {
+            let mut reader = JsonReader::new(json.as_bytes()).with_options(options);
+            let mut events = Vec::new();
+            while let Some(event) = reader.next_event()? {
+                events.push(format!("{event:?}"));
+            }
+            Ok::<_, std::io::Error>(events.join(" "))
+        };
+        let expected = r#"StartObject Key("a") StartArray Number("1") Number("2") EndArray Key("b") StartObject Key("c") Null EndObject EndObject"#;
+        assert_eq!(events(jsonc, config).unwrap(), expected);
+        assert_eq!(events(jsonc, "[1, /* open").unwrap_err().to_string(), "unexpected end of input at line 1, column 12 in `/1`");
+        assert_eq!(events(jsonc, "[1,,]").unwrap_err().to_string(), "expected a value at line 1, column 4 in `/1`");
+        assert_eq!(events(jsonc, "[1 / 2]").unwrap_err().to_string(), jsonc.parse("[1 / 2]").unwrap_err().to_string());
+        assert_eq!(events(jsonc, "{\"a\": 1,}/").unwrap_err().to_string(), "trailing characters after the value at line 1, column 10");
+        assert!(events(ParseOptions::new(), "[1,]").is_err());
+        assert_eq!(events(json5, "[1]").unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        let mut reader = JsonReader::new("[ // one\n 1, /* two */ 2, ]".as_bytes()).with_options(jsonc);
+        let elements = reader.array_elements::<u8>().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(elements, [1, 2]);
+        assert_eq!(reader.next_event().unwrap(), None);
+
+        // A `Json` read in a lenient dialect is still JSON.
+        let raw = json5.parse_as::<Json>("{ a: 'x', /* c */ b: 0x10, }").unwrap();
+        assert_eq!(json!({ "cfg": raw }), r#"{"cfg":{"a":"x","b":16}}"#);
+        assert_eq!(jsonc.parse_as::<Json>("[1, // one\n 2,]").unwrap(), "[1,2]");
+        assert_eq!(ParseOptions::new().parse_as::<Json>("[1, 2]").unwrap(), "[1, 2]");
+    }
 }
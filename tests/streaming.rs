@@ -0,0 +1,101 @@
+//! Checks that a `JsonReader` reads a big array in bounded memory, by
+//! tracking the bytes the test's own thread has allocated.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    io,
+};
+
+use json_proc::{FromJson, JsonReader};
+
+struct TrackingAllocator;
+
+thread_local! {
+    static LIVE: Cell<usize> = const { Cell::new(0) };
+    static PEAK: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = LIVE.try_with(|live| {
+            live.set(live.get() + layout.size());
+            let _ = PEAK.try_with(|peak| peak.set(peak.get().max(live.get())));
+        });
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = LIVE.try_with(|live| live.set(live.get().saturating_sub(layout.size())));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+/// Writes a JSON array of `count` readings as it's read, without ever
+/// holding more than one of them.
+struct Readings {
+    next: usize,
+    count: usize,
+    pending: Vec<u8>,
+}
+
+impl io::Read for Readings {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            self.pending = match self.next {
+                0 => b"[".to_vec(),
+                next if next <= self.count => {
+                    let comma = if next < self.count { "," } else { "" };
+                    let reading = format!(r#"{{ "sensor": "s-{}", "value": {next}.5, "tags": ["raw"] }}"#, next % 7);
+                    format!("\n  {reading}{comma}").into_bytes()
+                }
+                next if next == self.count + 1 => b"\n]\n".to_vec(),
+                _ => return Ok(0),
+            };
+            self.next += 1;
+        }
+        let len = self.pending.len().min(buf.len());
+        buf[..len].copy_from_slice(&self.pending[..len]);
+        self.pending.drain(..len);
+        Ok(len)
+    }
+}
+
+#[derive(FromJson)]
+struct Reading {
+    sensor: String,
+    value: f64,
+}
+
+#[test]
+fn test_bounded_memory() {
+    const COUNT: usize = 100_000;
+    let mut reader = JsonReader::new(Readings {
+        next: 0,
+        count: COUNT,
+        pending: Vec::new(),
+    });
+
+    let before = LIVE.with(Cell::get);
+    PEAK.with(|peak| peak.set(before));
+    let mut sum = 0.0;
+    let mut read = 0;
+    for reading in reader.array_elements::<Reading>().unwrap() {
+        let reading = reading.unwrap();
+        assert!(reading.sensor.starts_with("s-"));
+        sum += reading.value;
+        read += 1;
+    }
+    assert_eq!(reader.next_event().unwrap(), None);
+    assert_eq!(read, COUNT);
+    assert_eq!(sum, (1..=COUNT).map(|n| n as f64 + 0.5).sum::<f64>());
+
+    // The input is about 6 MB, but only the buffer and one reading are
+    // ever held.
+    assert!(reader.offset() > 5_000_000);
+    let peak = PEAK.with(Cell::get) - before;
+    assert!(peak < 16 * 1024, "peaked at {peak} bytes");
+}